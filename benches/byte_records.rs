@@ -0,0 +1,60 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::io::Write;
+use std::sync::OnceLock;
+
+use test::Bencher;
+use vcfire::VcfFile;
+
+const SAMPLES: usize = 1000;
+const RECORDS: usize = 200;
+
+/// Write a file with many samples once, so the benchmarks are dominated by reading wide lines.
+fn wide_file() -> &'static str {
+    static PATH: OnceLock<String> = OnceLock::new();
+    PATH.get_or_init(|| {
+        let path = std::env::temp_dir().join("vcfire_bench_wide.vcf");
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+        write!(file, "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT").unwrap();
+        for sample in 0..SAMPLES {
+            write!(file, "\tS{}", sample).unwrap();
+        }
+        writeln!(file).unwrap();
+        for record in 0..RECORDS {
+            write!(file, "1\t{}\t.\tA\tG\t50\tPASS\tAF=0.5\tGT:DP:GQ", (record + 1) * 100).unwrap();
+            for sample in 0..SAMPLES {
+                write!(file, "\t0/1:{}:99", sample % 60).unwrap();
+            }
+            writeln!(file).unwrap();
+        }
+        path.to_str().unwrap().into()
+    })
+}
+
+#[bench]
+fn bench_lending_records(b: &mut Bencher) {
+    let vcf_file = VcfFile::parse(wide_file(), false).unwrap();
+    b.iter(|| {
+        let mut records = vcf_file.lending_records().unwrap();
+        let mut sum = 0u64;
+        while let Some(record) = records.next() {
+            sum += record.unwrap().position as u64;
+        }
+        sum
+    });
+}
+
+#[bench]
+fn bench_byte_records(b: &mut Bencher) {
+    let vcf_file = VcfFile::parse(wide_file(), false).unwrap();
+    b.iter(|| {
+        let mut records = vcf_file.byte_records().unwrap();
+        let mut sum = 0u64;
+        while let Some(record) = records.next() {
+            sum += record.unwrap().position as u64;
+        }
+        sum
+    });
+}
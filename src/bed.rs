@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::io::BufRead;
+
+use crate::{RecordIterator, VcfFile, VcfRecord};
+
+/// Iterator over the records of a [`VcfFile`] overlapping any interval of a BED file, created by
+/// [`VcfFile::intersect_bed`]. Both inputs are read in a single pass, keeping only the intervals that may still
+/// overlap a later record in memory.
+pub struct BedIntersection<'a, R> {
+    records: RecordIterator<'a>,
+    bed: io::Lines<R>,
+    bed_line: u64,
+    // chromosome and start of the interval read last, to check that the intervals are sorted
+    last: Option<(String, u32)>,
+
+    // the next interval not yet compared to any record, as a 1-based, inclusive range
+    pending: Option<(String, u32, u32)>,
+    // intervals of the current chromosome that started before the end of the last record
+    active: Vec<(u32, u32)>,
+    // intervals of chromosomes the file may still reach, read while searching those of the current chromosome
+    buffered: HashMap<String, Vec<(u32, u32)>>,
+    // buffered intervals of the current chromosome not yet compared to any record
+    queued: VecDeque<(u32, u32)>,
+    chromosome: Option<String>,
+    passed: HashSet<String>,
+}
+
+impl VcfFile {
+    /// Get a lazy iterator over all records overlapping any interval of the BED file read from `bed`, e.g. to
+    /// restrict a file to the targets of an exome capture kit. Records spanning multiple bases overlap an interval
+    /// if any base up to their [`VcfRecord::end_position`] lies in it. Each record is yielded once, even if it
+    /// overlaps multiple intervals.
+    ///
+    /// Both inputs must be sorted by position within each chromosome, with chromosomes in the same order, although
+    /// either may contain chromosomes the other lacks. Intervals of a chromosome the header doesn't declare, found
+    /// before those of the current chromosome, are kept in memory until the file reaches it.
+    ///
+    /// BED intervals are 0-based and half-open, and `track`, `browser` and `#` comment lines are skipped. Malformed
+    /// or unsorted intervals are reported as errors of kind [`io::ErrorKind::InvalidData`].
+    pub fn intersect_bed<R: BufRead>(&self, bed: R) -> io::Result<BedIntersection<'_, R>> {
+        let mut intersection = BedIntersection {
+            records: self.records()?,
+            bed: bed.lines(),
+            bed_line: 0,
+            last: None,
+            pending: None,
+            active: Vec::new(),
+            buffered: HashMap::new(),
+            queued: VecDeque::new(),
+            chromosome: None,
+            passed: HashSet::new(),
+        };
+        intersection.pending = intersection.read_interval()?;
+        Ok(intersection)
+    }
+}
+
+impl<'a, R: BufRead> BedIntersection<'a, R> {
+    /// Read the next non-empty interval of the BED file as a 1-based, inclusive range.
+    fn read_interval(&mut self) -> io::Result<Option<(String, u32, u32)>> {
+        while let Some(line) = self.bed.next() {
+            let line = line?;
+            self.bed_line += 1;
+            if line.is_empty() || line.starts_with('#') || line.starts_with("track") || line.starts_with("browser") {
+                continue;
+            }
+
+            let malformed = || {
+                let message = format!("line {} of the BED file is not a valid interval", self.bed_line);
+                io::Error::new(io::ErrorKind::InvalidData, message)
+            };
+            let mut fields = line.split('\t');
+            let chromosome = fields.next().filter(|chromosome| !chromosome.is_empty()).ok_or_else(malformed)?;
+            let mut position = || fields.next().and_then(|field| field.trim_end().parse::<u32>().ok());
+            let (start, end) = position().zip(position()).filter(|(start, end)| start <= end).ok_or_else(malformed)?;
+
+            let chromosome = self.records.options.normalize_contig(chromosome);
+            if let Some((previous, previous_start)) = &self.last {
+                if *previous == chromosome && *previous_start > start {
+                    let message = format!("line {} of the BED file is not sorted by position", self.bed_line);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                }
+            }
+            self.last = Some((chromosome.clone(), start));
+            // empty intervals cover no bases
+            if start < end {
+                return Ok(Some((chromosome, start + 1, end)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Replace the pending interval by the next one of the BED file.
+    fn advance(&mut self) -> io::Result<()> {
+        self.pending = self.read_interval()?;
+        Ok(())
+    }
+
+    /// Check whether the pending interval lies on the chromosome and its start passes the filter.
+    fn is_pending(&self, chromosome: &str, filter: impl Fn(u32) -> bool) -> bool {
+        self.pending.as_ref().is_some_and(|(pending, start, _)| pending == chromosome && filter(*start))
+    }
+
+    /// Drop the intervals of the previous chromosome and read up to those of the new one. Intervals of chromosomes
+    /// the file has passed already, or that the header declares before the new one, are skipped. Intervals of
+    /// other chromosomes without a declared order are buffered, as the file may still reach them.
+    fn enter_chromosome(&mut self, chromosome: &str) -> io::Result<()> {
+        if let Some(previous) = self.chromosome.replace(chromosome.into()) {
+            self.passed.insert(previous);
+        }
+        self.active.clear();
+
+        let header = self.records.header;
+        let rank = header.contig_index(chromosome);
+        while let Some((pending, start, end)) = &self.pending {
+            if pending == chromosome {
+                break;
+            }
+            if !self.passed.contains(pending) {
+                match (rank, header.contig_index(pending)) {
+                    // the file reaches the chromosome of the interval later
+                    (Some(rank), Some(pending_rank)) if pending_rank > rank => break,
+                    (Some(_), Some(_)) => {}
+                    _ => self.buffered.entry(pending.clone()).or_default().push((*start, *end)),
+                }
+            }
+            self.advance()?;
+        }
+        self.queued = self.buffered.remove(chromosome).unwrap_or_default().into();
+        Ok(())
+    }
+
+    fn overlaps(&mut self, record: &VcfRecord) -> io::Result<bool> {
+        if self.chromosome.as_ref() != Some(&record.chromosome) {
+            self.enter_chromosome(&record.chromosome)?;
+        }
+
+        let end = record.end_position();
+        while self.queued.front().is_some_and(|&(start, _)| start <= end) {
+            self.active.extend(self.queued.pop_front());
+        }
+        while self.is_pending(&record.chromosome, |start| start <= end) {
+            let (_, start, interval_end) = self.pending.take().unwrap();
+            self.active.push((start, interval_end));
+            self.advance()?;
+        }
+        // records are sorted, so intervals ending before this record can't overlap any later record either
+        self.active.retain(|&(_, interval_end)| interval_end >= record.position);
+        Ok(self.active.iter().any(|&(start, _)| start <= end))
+    }
+}
+
+impl<'a, R: BufRead> Iterator for BedIntersection<'a, R> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // the scan stops once all intervals are passed
+            if self.pending.is_none() && self.active.is_empty() && self.queued.is_empty() && self.buffered.is_empty() {
+                return None;
+            }
+
+            let record = match self.records.next()? {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e)),
+            };
+            match self.overlaps(&record) {
+                Ok(true) => return Some(Ok(record)),
+                Ok(false) => {}
+                Err(e) => {
+                    self.pending = None;
+                    self.active.clear();
+                    self.queued.clear();
+                    self.buffered.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use super::*;
+
+    #[test]
+    fn test_intersect_bed() {
+        let vcf_file = VcfFile::parse("tests/data/gvcf_blocks.vcf", false).expect("failed to open VCF file");
+        let sites = |bed: &[u8]| {
+            let records = vcf_file.intersect_bed(bed)?;
+            records.map(|rec| rec.map(|rec| (rec.chromosome, rec.position))).collect::<io::Result<Vec<_>>>()
+        };
+
+        let bed = BufReader::new(File::open("tests/data/targets.bed").unwrap());
+        let records = vcf_file.intersect_bed(bed).unwrap().map(|rec| rec.unwrap());
+        let targets = vec![("1".into(), 101), ("1".into(), 150), ("2".into(), 10)];
+        assert_eq!(records.map(|rec| (rec.chromosome, rec.position)).collect::<Vec<_>>(), targets);
+
+        // only the block ending at 450 reaches into the interval, and chromosome 3 is not in the file
+        let overlapping = sites(b"1\t420\t430\n3\t0\t1000\n").unwrap();
+        assert_eq!(overlapping, vec![("1".into(), 350)]);
+        // intervals ending right before a record don't overlap it, and empty intervals cover nothing
+        assert_eq!(sites(b"1\t0\t0\n1\t200\t299\n2\t0\t9\n").unwrap(), vec![]);
+        // intervals of a chromosome the file lacks don't hide those of later chromosomes
+        assert_eq!(sites(b"1\t120\t160\nX\t0\t100\n2\t14\t15\n").unwrap(), targets);
+
+        assert_eq!(sites(b"1\t100\t200\n1\t50\t60\n").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(sites(b"1\tabc\t200\n").unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_intersect_bed_declared_contigs() {
+        let vcf_file = VcfFile::parse("tests/data/contig_length.vcf", false).expect("failed to open VCF file");
+        let sites = |bed: &[u8]| {
+            let records = vcf_file.intersect_bed(bed).unwrap().map(|rec| rec.unwrap());
+            records.map(|rec| (rec.chromosome, rec.position)).collect::<Vec<_>>()
+        };
+
+        // the undeclared chr3 lies between shared contigs
+        let records = sites(b"chr1\t999\t1000\nchr3\t0\t100\nchr2\t0\t10\nchrUn\t4999\t5000\n");
+        assert_eq!(records, vec![("chr1".into(), 1000), ("chrUn".into(), 5000)]);
+        // intervals of declared contigs past the current one are not read ahead
+        assert_eq!(sites(b"chr2\t499\t500\n"), vec![("chr2".into(), 500)]);
+    }
+}
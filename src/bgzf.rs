@@ -0,0 +1,312 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+
+#[cfg(feature = "remote")]
+use flate2::read::GzDecoder;
+use flate2::read::MultiGzDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+
+/// Maximum amount of uncompressed data per BGZF block, chosen like htslib so that the compressed block is
+/// guaranteed to fit into the 64 KiB limit.
+const MAX_BLOCK_DATA: usize = 0xff00;
+
+/// Maximum size of a complete BGZF block, including header and footer.
+pub(crate) const MAX_BLOCK_SIZE: usize = 0x10000;
+
+/// Size of the fixed gzip header of a BGZF block, including the `BC` extra subfield.
+const BLOCK_HEADER_SIZE: usize = 18;
+
+/// Size of the gzip footer (CRC32 and ISIZE).
+const BLOCK_FOOTER_SIZE: usize = 8;
+
+/// The empty block that terminates every BGZF file.
+const EOF_BLOCK: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00, 0x1b, 0x00,
+    0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// A position in a BGZF file. The upper 48 bits are the offset of a block in the compressed file, the lower 16
+/// bits are the offset into the decompressed data of that block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct VirtualOffset(pub u64);
+
+impl VirtualOffset {
+    pub fn new(compressed_offset: u64, uncompressed_offset: u16) -> Self {
+        VirtualOffset(compressed_offset << 16 | uncompressed_offset as u64)
+    }
+
+    /// Offset of the BGZF block in the compressed file
+    pub fn compressed_offset(&self) -> u64 {
+        self.0 >> 16
+    }
+
+    /// Offset into the decompressed data of the block
+    pub fn uncompressed_offset(&self) -> u16 {
+        self.0 as u16
+    }
+}
+
+/// A writer that compresses its input into BGZF blocks, the gzip-compatible format used by bgzip. Files written
+/// this way can be indexed with tabix, since every block boundary is addressable by a [`VirtualOffset`].
+///
+/// The writer must be closed with [`BgzfWriter::finish`] to flush the last block and append the EOF marker.
+/// Dropping it attempts the same, but ignores errors.
+pub struct BgzfWriter<W: Write> {
+    inner: Option<W>,
+    buffer: Vec<u8>,
+    compressed_offset: u64,
+    level: Compression,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    /// Create a writer compressing at the default level 6.
+    pub fn new(inner: W) -> Self {
+        Self::with_level(inner, Compression::default().level())
+    }
+
+    /// Create a writer compressing at the given level, from 0 (no compression) over 1 (fastest) to 9 (smallest).
+    ///
+    /// # Panics
+    /// If the level is larger than 9.
+    pub fn with_level(inner: W, level: u32) -> Self {
+        assert!(level <= 9, "compression level must be at most 9");
+        BgzfWriter {
+            inner: Some(inner),
+            buffer: Vec::with_capacity(MAX_BLOCK_DATA),
+            compressed_offset: 0,
+            level: Compression::new(level),
+        }
+    }
+
+    /// The virtual offset at which the next written byte will be located in the output.
+    pub fn virtual_offset(&self) -> VirtualOffset {
+        VirtualOffset::new(self.compressed_offset, self.buffer.len() as u16)
+    }
+
+    /// Flush the remaining data, write the EOF marker block and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_block()?;
+        let mut inner = self.inner.take().expect("BGZF writer already finished");
+        inner.write_all(&EOF_BLOCK)?;
+        inner.flush()?;
+        Ok(inner)
+    }
+
+    /// Compress the buffered data into a single block and write it to the underlying writer.
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let mut cdata = Self::deflate(&self.buffer, self.level)?;
+        if cdata.len() + BLOCK_HEADER_SIZE + BLOCK_FOOTER_SIZE > MAX_BLOCK_SIZE {
+            // incompressible data, fall back to stored blocks
+            cdata = Self::deflate(&self.buffer, Compression::none())?;
+        }
+
+        let block_size = cdata.len() + BLOCK_HEADER_SIZE + BLOCK_FOOTER_SIZE;
+        let mut crc = Crc::new();
+        crc.update(&self.buffer);
+
+        let inner = self.inner.as_mut().expect("BGZF writer already finished");
+        inner.write_all(&[0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0])?;
+        inner.write_all(&((block_size - 1) as u16).to_le_bytes())?;
+        inner.write_all(&cdata)?;
+        inner.write_all(&crc.sum().to_le_bytes())?;
+        inner.write_all(&(self.buffer.len() as u32).to_le_bytes())?;
+
+        self.compressed_offset += block_size as u64;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    fn deflate(data: &[u8], level: Compression) -> io::Result<Vec<u8>> {
+        let mut encoder = DeflateEncoder::new(Vec::with_capacity(data.len()), level);
+        encoder.write_all(data)?;
+        encoder.finish()
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(MAX_BLOCK_DATA - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+
+        // flush eagerly, so the virtual offset always points into a block that has space left
+        if self.buffer.len() == MAX_BLOCK_DATA {
+            self.write_block()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.inner.as_mut().expect("BGZF writer already finished").flush()
+    }
+}
+
+impl<W: Write> Drop for BgzfWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.write_block();
+            if let Some(inner) = self.inner.as_mut() {
+                let _ = inner.write_all(&EOF_BLOCK);
+            }
+        }
+    }
+}
+
+/// Open a BGZF compressed file and position the returned reader, buffering `capacity` decompressed bytes, at the
+/// given virtual offset.
+pub(crate) fn open_at(
+    path: &str,
+    offset: VirtualOffset,
+    capacity: usize,
+) -> io::Result<BufReader<MultiGzDecoder<File>>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset.compressed_offset()))?;
+
+    let mut reader = BufReader::with_capacity(capacity, MultiGzDecoder::new(file));
+    let mut remaining = offset.uncompressed_offset() as usize;
+    while remaining > 0 {
+        let available = reader.fill_buf()?.len();
+        if available == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "virtual offset points beyond block end"));
+        }
+        let consumed = available.min(remaining);
+        reader.consume(consumed);
+        remaining -= consumed;
+    }
+
+    Ok(reader)
+}
+
+/// The decompressed data of consecutive BGZF blocks, e.g. of a byte range downloaded from a remote file.
+#[cfg(feature = "remote")]
+pub(crate) struct BlockRange {
+    data: Vec<u8>,
+    // compressed offset of each block in the file, and the start of its data in the decompressed buffer
+    blocks: Vec<(u64, usize)>,
+}
+
+#[cfg(feature = "remote")]
+impl BlockRange {
+    /// Decompress all complete blocks of `data`, which starts at offset `base` of the compressed file. A truncated
+    /// block at the end is ignored.
+    pub(crate) fn decompress(data: &[u8], base: u64) -> io::Result<BlockRange> {
+        use std::io::Read;
+
+        let mut range = BlockRange {
+            data: Vec::new(),
+            blocks: Vec::new(),
+        };
+
+        let mut position = 0;
+        while let Some(header) = data.get(position..position + BLOCK_HEADER_SIZE) {
+            if header[..2] != [0x1f, 0x8b] || header[12..14] != *b"BC" {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed BGZF block header"));
+            }
+            let block_size = u16::from_le_bytes([header[16], header[17]]) as usize + 1;
+            let Some(block) = data.get(position..position + block_size) else {
+                break;
+            };
+
+            range.blocks.push((base + position as u64, range.data.len()));
+            GzDecoder::new(block).read_to_end(&mut range.data)?;
+            position += block_size;
+        }
+
+        Ok(range)
+    }
+
+    /// Translate a virtual offset into an index of the decompressed data, if it points into one of the blocks.
+    pub(crate) fn position(&self, offset: VirtualOffset) -> Option<usize> {
+        let block = self
+            .blocks
+            .binary_search_by_key(&offset.compressed_offset(), |&(compressed, _)| compressed)
+            .ok()?;
+        Some(self.blocks[block].1 + offset.uncompressed_offset() as usize)
+    }
+
+    /// The decompressed data of all blocks.
+    pub(crate) fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn test_bgzf_round_trip() {
+        let data = (0..100_000).map(|i| format!("line {}\n", i)).collect::<String>();
+
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(data.as_bytes()).unwrap();
+        let second_block = writer.virtual_offset();
+        let compressed = writer.finish().unwrap();
+
+        assert!(second_block.compressed_offset() > 0);
+        assert!(compressed.ends_with(&EOF_BLOCK));
+
+        let mut decompressed = String::new();
+        MultiGzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_compression_level() {
+        let mut data = String::from("##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n");
+        for i in 1..20_000 {
+            data += &format!("1\t{}\trs{}\tA\tG\t{}\tPASS\tDP={}\n", i * 7, i * 31 % 1000, i % 60, i * 13 % 97);
+        }
+
+        let mut positions = Vec::new();
+        let mut sizes = Vec::new();
+        for level in [1, 9] {
+            let path = std::env::temp_dir().join(format!("vcfire_compression_level_{}.vcf.gz", level));
+            let mut writer = BgzfWriter::with_level(std::fs::File::create(&path).unwrap(), level);
+            writer.write_all(data.as_bytes()).unwrap();
+            writer.finish().unwrap();
+            sizes.push(std::fs::metadata(&path).unwrap().len());
+
+            let vcf_file = crate::VcfFile::parse(path.to_str().unwrap(), true).expect("failed to open VCF file");
+            positions.push(vcf_file.records().unwrap().map(|rec| rec.unwrap().position).collect::<Vec<_>>());
+        }
+
+        assert_eq!(positions[0].len(), 19_999);
+        assert_eq!(positions[0], positions[1]);
+        assert!(sizes[1] < sizes[0]);
+    }
+
+    #[test]
+    #[cfg(feature = "remote")]
+    fn test_decompress_block_range() {
+        let data = (0..100_000).map(|i| format!("line {}\n", i)).collect::<String>();
+
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(&data.as_bytes()[..1000]).unwrap();
+        writer.flush().unwrap();
+        let second_block = writer.virtual_offset();
+        writer.write_all(&data.as_bytes()[1000..]).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        // start at the second block and cut off the end of the compressed data
+        let base = second_block.compressed_offset();
+        let truncated = &compressed[base as usize..compressed.len() - 100];
+        let range = BlockRange::decompress(truncated, base).unwrap();
+        assert_eq!(range.position(VirtualOffset::new(base, 6)), Some(6));
+        assert_eq!(range.position(VirtualOffset::new(0, 6)), None);
+
+        let decompressed = range.into_data();
+        assert!(data.as_bytes()[1000..].starts_with(&decompressed));
+        assert!(decompressed.len() < data.len() - 1000);
+    }
+}
@@ -0,0 +1,242 @@
+use std::io;
+use std::str;
+
+use crate::error::column_name;
+use crate::{ParseOptions, RecordIterator, VcfFile, VcfHeader, VcfParseError, VcfRecord};
+
+/// A record borrowed from the byte buffer of a [`ByteRecords`] iterator. Lines are split into columns without
+/// validating UTF-8, which is only checked for a single column when it is requested as `&str`. Since VCF columns
+/// are ASCII except for free text, this skips validating every byte of wide lines with many samples. Only POS is
+/// parsed, and like for [`crate::VcfRecordRef`], neither contig normalization nor strict validation is applied.
+pub struct ByteRecord<'a> {
+    pub position: u32,
+
+    chromosome: &'a [u8],
+    id: &'a [u8],
+    reference_bases: &'a [u8],
+    alternate_bases: &'a [u8],
+    quality: &'a [u8],
+    filter_status: &'a [u8],
+    info: &'a [u8],
+    format: Option<&'a [u8]>,
+    samples: Option<&'a [u8]>,
+
+    line: &'a [u8],
+    header: &'a VcfHeader,
+    options: &'a ParseOptions,
+}
+
+/// Iterator over the records of a [`VcfFile`] that reads lines as bytes and lends each record from its internal
+/// buffer. Like [`crate::LendingRecords`], it can't implement [`Iterator`], so use
+/// `while let Some(record) = records.next()` instead.
+pub struct ByteRecords<'a> {
+    records: RecordIterator<'a>,
+    buffer: Vec<u8>,
+}
+
+impl VcfFile {
+    /// Open the VCF file and get a lazy iterator lending records read as bytes, see [`ByteRecord`].
+    pub fn byte_records(&self) -> io::Result<ByteRecords<'_>> {
+        Ok(ByteRecords {
+            records: self.records()?,
+            buffer: Vec::with_capacity(self.options.line_capacity()),
+        })
+    }
+}
+
+impl<'a> ByteRecords<'a> {
+    /// Get the next record. Records with missing columns or a malformed POS are reported as errors of kind
+    /// [`io::ErrorKind::InvalidData`], as in strict mode.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<io::Result<ByteRecord<'_>>> {
+        match self.records.read_line_bytes(&mut self.buffer) {
+            Ok(true) => Some(
+                ByteRecord::parse(&self.buffer, self.records.header, self.records.options)
+                    .map_err(|e| self.records.located(e, &self.buffer)),
+            ),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'a> ByteRecord<'a> {
+    fn parse(
+        line: &'a [u8],
+        header: &'a VcfHeader,
+        options: &'a ParseOptions,
+    ) -> Result<ByteRecord<'a>, VcfParseError> {
+        let fields_without_samples = 8 + header.has_end_column as usize + header.sample_names.is_some() as usize;
+        // a FORMAT column without any samples is followed by no sample column
+        let required = fields_without_samples + !header.samples().is_empty() as usize;
+        let mut fields = line.splitn(fields_without_samples + 1, |&b| b == b'\t');
+        let columns = [(); 8].map(|_| fields.next().unwrap_or_default());
+        let found = line.splitn(required, |&b| b == b'\t').count();
+        if found < required {
+            return Err(VcfParseError::MissingColumns {
+                expected: required,
+                found,
+                space_delimited: line.contains(&b' '),
+            });
+        }
+
+        let [chromosome, position, id, reference_bases, alternate_bases, quality, filter_status, info] = columns;
+        let position = parse_position(position)?;
+        if header.has_end_column {
+            fields.next();
+        }
+        let (format, samples) = if header.sample_names.is_some() {
+            (fields.next(), Some(fields.next().unwrap_or_default()))
+        } else {
+            (None, None)
+        };
+
+        Ok(ByteRecord {
+            position,
+            chromosome,
+            id,
+            reference_bases,
+            alternate_bases,
+            quality,
+            filter_status,
+            info,
+            format,
+            samples,
+            line,
+            header,
+            options,
+        })
+    }
+
+    pub fn chromosome(&self) -> Result<&'a str, VcfParseError> {
+        text(self.chromosome, "CHROM")
+    }
+
+    /// Raw ID column, `.` if the record has no identifiers
+    pub fn id(&self) -> Result<&'a str, VcfParseError> {
+        text(self.id, "ID")
+    }
+
+    pub fn reference_bases(&self) -> Result<&'a str, VcfParseError> {
+        text(self.reference_bases, "REF")
+    }
+
+    /// Raw, comma-separated ALT column
+    pub fn alternate_bases(&self) -> Result<&'a str, VcfParseError> {
+        text(self.alternate_bases, "ALT")
+    }
+
+    /// QUAL column, None if it is missing or malformed
+    pub fn quality(&self) -> Option<f32> {
+        VcfRecord::parse_quality(text(self.quality, "QUAL").ok()?).ok().flatten()
+    }
+
+    pub fn filter_status(&self) -> Result<&'a str, VcfParseError> {
+        text(self.filter_status, "FILTER")
+    }
+
+    /// Raw, semicolon-separated INFO column
+    pub fn info(&self) -> Result<&'a str, VcfParseError> {
+        text(self.info, "INFO")
+    }
+
+    /// Raw FORMAT column, None if the file has no samples
+    pub fn format(&self) -> Result<Option<&'a str>, VcfParseError> {
+        self.format.map(|format| text(format, "FORMAT")).transpose()
+    }
+
+    /// Raw, tab-separated sample columns, None if the file has no samples
+    pub fn samples(&self) -> Result<Option<&'a str>, VcfParseError> {
+        self.samples.map(|samples| text(samples, "sample")).transpose()
+    }
+
+    /// The whole line without line terminator
+    pub fn bytes(&self) -> &'a [u8] {
+        self.line
+    }
+
+    /// Validate the whole line and parse it into an owned record, applying the parse options of the file.
+    pub fn to_record(&self) -> Result<VcfRecord, VcfParseError> {
+        let line = str::from_utf8(self.line).map_err(|e| {
+            let column = self.line[..e.valid_up_to()].iter().filter(|&&b| b == b'\t').count();
+            VcfParseError::InvalidUtf8(column_name(self.header, column))
+        })?;
+        VcfRecord::parse(line, self.header, self.options)
+    }
+}
+
+fn text<'a>(field: &'a [u8], column: &'static str) -> Result<&'a str, VcfParseError> {
+    str::from_utf8(field).map_err(|_| VcfParseError::InvalidUtf8(column))
+}
+
+/// Parse the POS column from its digits, without validating it as UTF-8 first.
+fn parse_position(field: &[u8]) -> Result<u32, VcfParseError> {
+    let malformed = || VcfParseError::MalformedPosition(String::from_utf8_lossy(field).into());
+    if field.is_empty() {
+        return Err(malformed());
+    }
+    field.iter().try_fold(0u32, |position, &b| {
+        if !b.is_ascii_digit() {
+            return Err(malformed());
+        }
+        position.checked_mul(10).and_then(|p| p.checked_add((b - b'0') as u32)).ok_or_else(malformed)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_byte_records() {
+        for path in [
+            "tests/data/sample_fields.vcf",
+            "tests/data/end_column.vcf",
+            "tests/data/format_without_samples.vcf",
+            "tests/data/crlf.vcf",
+        ] {
+            let vcf_file = VcfFile::parse(path, false).expect("failed to open VCF file");
+            let owned = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+
+            let mut records = vcf_file.byte_records().unwrap();
+            let mut count = 0;
+            while let Some(record) = records.next() {
+                let record = record.unwrap();
+                assert_eq!(record.chromosome().unwrap(), owned[count].chromosome);
+                assert_eq!(record.position, owned[count].position);
+                assert_eq!(record.reference_bases().unwrap(), owned[count].reference_bases);
+                assert_eq!(record.quality(), owned[count].quality);
+                assert_eq!(record.filter_status().unwrap(), owned[count].filter_status);
+                assert_eq!(format!("{:?}", record.to_record().unwrap()), format!("{:?}", owned[count]));
+                count += 1;
+            }
+            assert_eq!(count, owned.len());
+        }
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let path = std::env::temp_dir().join("vcfire_invalid_utf8.vcf");
+        let path = path.to_str().unwrap();
+        let mut file = std::fs::File::create(path).unwrap();
+        file.write_all(b"##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n").unwrap();
+        // a Latin-1 encoded free text entry
+        file.write_all(b"1\t100\t.\tA\tG\t30\tPASS\tNOTE=caf\xe9\n1\tx\t.\tA\tG\t30\tPASS\t.\n").unwrap();
+        drop(file);
+
+        let vcf_file = VcfFile::parse(path, false).unwrap();
+        assert!(vcf_file.records().unwrap().next().unwrap().is_err());
+
+        let mut records = vcf_file.byte_records().unwrap();
+        let record = records.next().unwrap().unwrap();
+        assert_eq!((record.chromosome(), record.position, record.quality()), (Ok("1"), 100, Some(30.0)));
+        assert_eq!(record.info(), Err(VcfParseError::InvalidUtf8("INFO")));
+        assert_eq!(record.to_record().unwrap_err(), VcfParseError::InvalidUtf8("INFO"));
+
+        let malformed = records.next().unwrap().err().unwrap();
+        assert_eq!(malformed.kind(), io::ErrorKind::InvalidData);
+        assert!(records.next().is_none());
+    }
+}
@@ -0,0 +1,173 @@
+use std::cmp::Ordering;
+use std::io;
+use std::iter::Peekable;
+
+use crate::{ContigOrder, RecordIterator, VcfFile, VcfRecord};
+
+/// Genotype concordance of a call set with a truth set, counted over the samples present in both files. Sites are
+/// matched by [`VcfRecord::variant_key`], so differently trimmed or ordered alleles of the same variant match, and
+/// genotypes are compared by the variants they call, ignoring phase.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Concordance {
+    /// Genotypes calling the same alleles in both files
+    pub matches: u64,
+    /// Genotypes calling different alleles
+    pub mismatches: u64,
+    /// Genotypes of shared sites that are absent, or missing at least one allele in either file
+    pub missing: u64,
+    /// Sites only in the call set
+    pub query_only: u64,
+    /// Sites only in the truth set
+    pub truth_only: u64,
+}
+
+impl Concordance {
+    /// Fraction of compared genotypes that match, or None if no genotype could be compared.
+    pub fn rate(&self) -> Option<f64> {
+        let compared = self.matches + self.mismatches;
+        (compared > 0).then(|| self.matches as f64 / compared as f64)
+    }
+
+    /// Count the genotype concordance of all sites at the same coordinate. Each record of the call set is matched
+    /// with the first unmatched record of the truth set sharing one of its variants.
+    fn add_sites(&mut self, query: &[VcfRecord], truth: &[VcfRecord], samples: &[(usize, usize)]) {
+        let mut unmatched = truth.iter().collect::<Vec<_>>();
+        for record in query {
+            let keys = variant_keys(record);
+            match unmatched.iter().position(|candidate| variant_keys(candidate).iter().any(|key| keys.contains(key))) {
+                Some(i) => {
+                    let candidate = unmatched.remove(i);
+                    self.add_genotypes(record, candidate, samples);
+                }
+                None => self.query_only += 1,
+            }
+        }
+        self.truth_only += unmatched.len() as u64;
+    }
+
+    fn add_genotypes(&mut self, query: &VcfRecord, truth: &VcfRecord, samples: &[(usize, usize)]) {
+        for &(query_sample, truth_sample) in samples {
+            match (called_variants(query, query_sample), called_variants(truth, truth_sample)) {
+                (Some(query), Some(truth)) if query == truth => self.matches += 1,
+                (Some(_), Some(_)) => self.mismatches += 1,
+                _ => self.missing += 1,
+            }
+        }
+    }
+}
+
+impl VcfFile {
+    /// Compare the genotypes of this call set with the truth set, streaming both files once. Both files must be
+    /// sorted by coordinate, with contigs in the order of the `##contig` lines of the truth set (see
+    /// [`ContigOrder`]). Samples are matched by name, samples missing from either file are ignored.
+    pub fn concordance(&self, truth: &VcfFile) -> io::Result<Concordance> {
+        let order = ContigOrder::from_header(&truth.header);
+        let samples = self
+            .header
+            .samples()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| Some((i, truth.header.sample_index(name)?)))
+            .collect::<Vec<_>>();
+
+        let mut query_records = self.records()?.peekable();
+        let mut truth_records = truth.records()?.peekable();
+        let mut query = next_site(&mut query_records)?;
+        let mut truth = next_site(&mut truth_records)?;
+
+        let mut concordance = Concordance::default();
+        loop {
+            let ordering = match (query.first(), truth.first()) {
+                (Some(query), Some(truth)) => order.compare(query, truth),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+
+            match ordering {
+                Ordering::Less => {
+                    concordance.query_only += query.len() as u64;
+                    query = next_site(&mut query_records)?;
+                }
+                Ordering::Greater => {
+                    concordance.truth_only += truth.len() as u64;
+                    truth = next_site(&mut truth_records)?;
+                }
+                Ordering::Equal => {
+                    concordance.add_sites(&query, &truth, &samples);
+                    query = next_site(&mut query_records)?;
+                    truth = next_site(&mut truth_records)?;
+                }
+            }
+        }
+
+        Ok(concordance)
+    }
+}
+
+/// Read all records at the coordinate of the next record. Returns an empty vector at the end of the file.
+fn next_site(records: &mut Peekable<RecordIterator<'_>>) -> io::Result<Vec<VcfRecord>> {
+    let mut site: Vec<VcfRecord> = Vec::new();
+    while let Some(record) = records.next_if(|record| match (record, site.first()) {
+        (Ok(record), Some(first)) => record.same_locus(first),
+        _ => true,
+    }) {
+        site.push(record?);
+    }
+    Ok(site)
+}
+
+/// The keys of all alternate alleles of the record, skipping missing alleles.
+fn variant_keys(record: &VcfRecord) -> Vec<String> {
+    (0..record.alternate_bases.len())
+        .filter(|&alt_index| record.alternate_bases[alt_index].is_some())
+        .map(|alt_index| record.variant_key(alt_index))
+        .collect()
+}
+
+/// The variants called by the genotype of a sample in sorted order, with an empty key for the reference allele.
+/// Returns None if the genotype is absent, or any allele is missing or out of range.
+fn called_variants(record: &VcfRecord, sample: usize) -> Option<Vec<String>> {
+    let sample = record.sample_info.as_ref()?.samples().nth(sample)?;
+    let mut variants = sample
+        .genotype()?
+        .alleles
+        .iter()
+        .map(|allele| match (*allele)? as usize {
+            0 => Some(String::new()),
+            allele if allele <= record.alternate_bases.len() => Some(record.variant_key(allele - 1)),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    variants.sort();
+    Some(variants)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concordance() {
+        let calls = VcfFile::parse("tests/data/concordance_calls.vcf", false).expect("failed to open VCF file");
+        let truth = VcfFile::parse("tests/data/concordance_truth.vcf", false).expect("failed to open VCF file");
+
+        let concordance = calls.concordance(&truth).unwrap();
+        assert_eq!(
+            concordance,
+            Concordance {
+                matches: 8,
+                mismatches: 1,
+                missing: 1,
+                query_only: 1,
+                truth_only: 1,
+            }
+        );
+        // the missing ALT allele at 1:400 doesn't shift the indices of the alleles after it
+        assert_eq!(concordance.rate(), Some(8.0 / 9.0));
+
+        let itself = truth.concordance(&truth).unwrap();
+        assert_eq!((itself.matches, itself.mismatches, itself.missing), (17, 0, 1));
+        assert_eq!(Concordance::default().rate(), None);
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+use std::io;
+
+use crate::VcfHeader;
+
+/// An error in the contents of a VCF file. While parsing, these are only reported in strict mode (see
+/// [`crate::ParseOptions::strict`]), except for records with missing columns or a malformed POS, and are surfaced
+/// as an [`io::Error`] of kind [`io::ErrorKind::InvalidData`] wrapping this type. Errors reported by the record
+/// iterators are wrapped in [`VcfParseError::AtLine`] with the location of the offending line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VcfParseError {
+    /// A record's position lies beyond the length declared for its contig in the header
+    PositionOutOfContig {
+        chromosome: String,
+        position: u32,
+        length: u64,
+    },
+    /// The POS column is not a non-negative integer
+    MalformedPosition(String),
+    /// A mandatory column (CHROM, REF or an ALT allele) is empty
+    EmptyField(&'static str),
+    /// A record has fewer tab-separated columns than the header requires. If the line contains spaces, it is
+    /// most likely delimited by spaces instead of tabs
+    MissingColumns {
+        expected: usize,
+        found: usize,
+        space_delimited: bool,
+    },
+    /// A column of a record read as bytes is not valid UTF-8, see [`crate::ByteRecord`]
+    InvalidUtf8(&'static str),
+    /// The ID column contains an empty identifier, e.g. `;rs1;`
+    MalformedId(String),
+    /// The QUAL column is neither a finite number nor the missing value
+    MalformedQuality(String),
+    /// The `END` INFO entry of a record with only sequence alleles disagrees with the end of its reference bases
+    InconsistentEnd {
+        end: u32,
+        reference_end: u32,
+    },
+    /// The read depths of the samples sum to more than the combined depth of the `DP` INFO entry
+    InconsistentDepth {
+        info_depth: u32,
+        sample_depth: u32,
+    },
+    /// An INFO entry that is empty, as in `DP=10;;AF=0.5`, or has no key, as in `=5`
+    MalformedInfo(String),
+    /// A GT field with whitespace or an allele that is neither an index nor `.`
+    MalformedGenotype(String),
+    /// A sample's genotype refers to an allele the record doesn't have. `allele_count` includes the reference
+    AlleleIndexOutOfRange {
+        sample: usize,
+        allele: u32,
+        allele_count: usize,
+    },
+    /// An error in the record at the 1-based `line` of the file, counting the header. `field` names the column of
+    /// the failure and `column` is the 0-based byte offset of that column in the line, where they are known
+    AtLine {
+        line: u64,
+        field: Option<&'static str>,
+        column: Option<usize>,
+        error: Box<VcfParseError>,
+    },
+}
+
+impl VcfParseError {
+    /// Line number of the record the error occurred in, if it was reported while iterating over a file.
+    pub fn line(&self) -> Option<u64> {
+        match self {
+            VcfParseError::AtLine { line, .. } => Some(*line),
+            _ => None,
+        }
+    }
+
+    /// The error without its location.
+    pub fn cause(&self) -> &VcfParseError {
+        match self {
+            VcfParseError::AtLine { error, .. } => error,
+            error => error,
+        }
+    }
+
+    /// Wrap the error in [`VcfParseError::AtLine`], locating its column in the record `text` read from `line`.
+    pub(crate) fn at_line(self, line: u64, text: &[u8], header: &VcfHeader) -> VcfParseError {
+        if let VcfParseError::AtLine { .. } = self {
+            return self;
+        }
+
+        let index = self.column_index(header);
+        let field = index.map(|index| column_name(header, index)).or(match self {
+            VcfParseError::InvalidUtf8(column) => Some(column),
+            VcfParseError::MalformedGenotype(_) => Some("sample"),
+            _ => None,
+        });
+        // the byte offset of a column is the length of all preceding columns and their tabs
+        let column = index.and_then(|index| {
+            let mut columns = text.split(|&b| b == b'\t');
+            let offset = columns.by_ref().take(index).map(|column| column.len() + 1).sum();
+            columns.next().map(|_| offset)
+        });
+
+        VcfParseError::AtLine {
+            line,
+            field,
+            column,
+            error: Box::new(self),
+        }
+    }
+
+    /// 0-based index of the column the error occurred in, if it is known.
+    fn column_index(&self, header: &VcfHeader) -> Option<usize> {
+        let end_column = header.has_end_column as usize;
+        match self {
+            VcfParseError::PositionOutOfContig { .. } | VcfParseError::MalformedPosition(_) => Some(1),
+            VcfParseError::MalformedId(_) => Some(2),
+            VcfParseError::MalformedQuality(_) => Some(5),
+            VcfParseError::InconsistentEnd { .. }
+            | VcfParseError::InconsistentDepth { .. }
+            | VcfParseError::MalformedInfo(_) => Some(7),
+            VcfParseError::EmptyField(column) | VcfParseError::InvalidUtf8(column) => match *column {
+                "END" => Some(8),
+                "FORMAT" => Some(8 + end_column),
+                column => COLUMNS.iter().position(|&name| name == column),
+            },
+            VcfParseError::AlleleIndexOutOfRange { sample, .. } => Some(9 + end_column + sample),
+            VcfParseError::MissingColumns { .. } | VcfParseError::MalformedGenotype(_) => None,
+            VcfParseError::AtLine { .. } => None,
+        }
+    }
+}
+
+const COLUMNS: [&str; 8] = ["CHROM", "POS", "ID", "REF", "ALT", "QUAL", "FILTER", "INFO"];
+
+/// Name of the 0-based column of a record, with all sample columns named `sample`.
+pub(crate) fn column_name(header: &VcfHeader, column: usize) -> &'static str {
+    match column.checked_sub(COLUMNS.len() + header.has_end_column as usize) {
+        None => COLUMNS.get(column).copied().unwrap_or("END"),
+        Some(0) => "FORMAT",
+        Some(_) => "sample",
+    }
+}
+
+impl fmt::Display for VcfParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VcfParseError::PositionOutOfContig {
+                chromosome,
+                position,
+                length,
+            } => write!(
+                f,
+                "position {} exceeds the declared length {} of contig {}",
+                position, length, chromosome
+            ),
+            VcfParseError::MalformedPosition(position) => write!(f, "POS {} is not a valid position", position),
+            VcfParseError::EmptyField(field) => write!(f, "mandatory field {} is empty", field),
+            VcfParseError::MalformedQuality(quality) => write!(f, "QUAL {} is not a number", quality),
+            VcfParseError::MissingColumns {
+                expected,
+                found,
+                space_delimited,
+            } => {
+                write!(f, "record has {} of {} tab-separated columns", found, expected)?;
+                if *space_delimited {
+                    write!(f, ", but contains spaces: columns must be separated by tabs, not spaces")?;
+                }
+                Ok(())
+            }
+            VcfParseError::MalformedId(id) => write!(f, "ID {:?} contains an empty identifier", id),
+            VcfParseError::InvalidUtf8(column) => write!(f, "column {} is not valid UTF-8", column),
+            VcfParseError::InconsistentEnd { end, reference_end } => write!(
+                f,
+                "END {} disagrees with the reference bases ending at {}",
+                end, reference_end
+            ),
+            VcfParseError::InconsistentDepth {
+                info_depth,
+                sample_depth,
+            } => write!(
+                f,
+                "sample depths sum to {}, more than the combined depth DP={}",
+                sample_depth, info_depth
+            ),
+            VcfParseError::MalformedInfo(entry) => write!(f, "malformed INFO entry {:?}", entry),
+            VcfParseError::MalformedGenotype(genotype) => write!(f, "malformed genotype {:?}", genotype),
+            VcfParseError::AlleleIndexOutOfRange {
+                sample,
+                allele,
+                allele_count,
+            } => write!(
+                f,
+                "genotype of sample {} refers to allele {}, but the record has {} alleles",
+                sample, allele, allele_count
+            ),
+            VcfParseError::AtLine {
+                line,
+                field,
+                column,
+                error,
+            } => {
+                write!(f, "line {}", line)?;
+                if let Some(field) = field {
+                    write!(f, ", column {}", field)?;
+                }
+                if let Some(column) = column {
+                    write!(f, " at byte {}", column)?;
+                }
+                write!(f, ": {}", error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VcfParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VcfParseError::AtLine { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<VcfParseError> for io::Error {
+    fn from(error: VcfParseError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
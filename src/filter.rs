@@ -0,0 +1,487 @@
+use std::collections::VecDeque;
+use std::io;
+
+use crate::{InfoEntry, VcfRecord};
+
+/// Where the allele frequency of a record is taken from when filtering by frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlleleFrequencySource {
+    /// Use the `AF` INFO entry only. Records without it are dropped.
+    Info,
+    /// Compute the frequency from the sample genotypes only, ignoring any `AF` INFO entry.
+    Genotypes,
+    /// Use the `AF` INFO entry if present, and compute the frequency from the genotypes otherwise.
+    InfoOrGenotypes,
+}
+
+/// Adapters for iterators over VCF records. Errors of the underlying iterator are always passed through.
+pub trait RecordFilters: Iterator<Item=io::Result<VcfRecord>> + Sized {
+    /// Keep only records whose highest alternate allele frequency is at least `threshold`. The frequency is taken
+    /// from the `AF` INFO entry, or computed from the genotypes if the record has none.
+    fn min_allele_frequency(self, threshold: f32) -> MinAlleleFrequency<Self> {
+        self.min_allele_frequency_from(threshold, AlleleFrequencySource::InfoOrGenotypes)
+    }
+
+    /// Keep only records whose highest alternate allele frequency is at least `threshold`, taking the frequency
+    /// from the given source. Records for which no frequency is available are dropped.
+    fn min_allele_frequency_from(
+        self,
+        threshold: f32,
+        source: AlleleFrequencySource,
+    ) -> MinAlleleFrequency<Self> {
+        MinAlleleFrequency {
+            inner: self,
+            threshold,
+            source,
+        }
+    }
+
+    /// Keep only records carrying the flag INFO entry `key`, e.g. `SOMATIC` or `DB`. The key is parsed like an INFO
+    /// entry, so reserved flags with a value are malformed and never match.
+    fn with_info_flag(self, key: &str) -> WithInfoFlag<Self> {
+        WithInfoFlag {
+            inner: self,
+            flag: InfoEntry::parse(key, 0, &[]),
+        }
+    }
+
+    /// Keep only records where at least `count` samples carry a non-reference genotype, i.e. a heterozygous or
+    /// homozygous alternate one (see [`crate::Genotype::is_het`] and [`crate::Genotype::is_hom_alt`]). Samples
+    /// without a genotype or with missing alleles don't count. Records without samples have no carriers, so they
+    /// are only kept for a `count` of 0, which keeps every record.
+    fn min_carriers(self, count: usize) -> MinCarriers<Self> {
+        MinCarriers { inner: self, count }
+    }
+
+    /// Split the reference blocks of a coordinate-sorted gVCF at the variants inside them, so no records overlap
+    /// a reference block (see [`VcfRecord::is_reference_block`]). Blocks overlapping an earlier block are trimmed
+    /// to start after it, and dropped if they are covered entirely. Variant records are passed through unchanged,
+    /// even if they overlap each other. The REF base of a block that starts at a new position is unknown, so it is
+    /// set to `N`.
+    fn split_reference_blocks(self) -> SplitReferenceBlocks<Self> {
+        SplitReferenceBlocks {
+            inner: self,
+            blocks: VecDeque::new(),
+            ready: VecDeque::new(),
+            chromosome: None,
+            covered: 0,
+            finished: false,
+        }
+    }
+
+    /// Group records of a coordinate-sorted file into consecutive windows of `size` bases per chromosome, starting
+    /// at position 1. Only windows containing at least one record are yielded. An error ends the current window,
+    /// which is yielded before the error, so no records are lost if the caller skips the error and continues.
+    ///
+    /// # Panics
+    /// If `size` is 0.
+    fn genomic_windows(self, size: u32) -> GenomicWindows<Self> {
+        assert!(size > 0, "window size must be positive");
+        GenomicWindows {
+            inner: self,
+            size,
+            pending: None,
+        }
+    }
+}
+
+impl<I: Iterator<Item=io::Result<VcfRecord>>> RecordFilters for I {}
+
+/// Iterator returned by [`RecordFilters::min_allele_frequency`].
+pub struct MinAlleleFrequency<I> {
+    inner: I,
+    threshold: f32,
+    source: AlleleFrequencySource,
+}
+
+impl<I> MinAlleleFrequency<I> {
+    fn max_frequency(&self, record: &VcfRecord) -> Option<f32> {
+        let from_info = || {
+            record
+                .info_allele_frequencies()
+                .and_then(|f| f.iter().copied().reduce(f32::max))
+        };
+        let from_genotypes = || {
+            record
+                .genotype_allele_frequencies()
+                .and_then(|f| f.into_iter().reduce(f32::max))
+        };
+
+        match self.source {
+            AlleleFrequencySource::Info => from_info(),
+            AlleleFrequencySource::Genotypes => from_genotypes(),
+            AlleleFrequencySource::InfoOrGenotypes => from_info().or_else(from_genotypes),
+        }
+    }
+}
+
+impl<I: Iterator<Item=io::Result<VcfRecord>>> Iterator for MinAlleleFrequency<I> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(record) => {
+                    if self
+                        .max_frequency(&record)
+                        .is_some_and(|frequency| frequency >= self.threshold)
+                    {
+                        return Some(Ok(record));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Iterator returned by [`RecordFilters::with_info_flag`].
+pub struct WithInfoFlag<I> {
+    inner: I,
+    flag: Option<InfoEntry>,
+}
+
+impl<I: Iterator<Item=io::Result<VcfRecord>>> Iterator for WithInfoFlag<I> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(record) => {
+                    // stops at the first matching entry
+                    if record.info.iter().flatten().any(|entry| Some(entry) == self.flag.as_ref()) {
+                        return Some(Ok(record));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Iterator returned by [`RecordFilters::min_carriers`].
+pub struct MinCarriers<I> {
+    inner: I,
+    count: usize,
+}
+
+impl<I: Iterator<Item=io::Result<VcfRecord>>> Iterator for MinCarriers<I> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(record) => {
+                    let carriers = match &record.sample_info {
+                        Some(sample_info) => sample_info
+                            .samples()
+                            .filter_map(|sample| sample.genotype())
+                            .filter(|genotype| genotype.is_het() || genotype.is_hom_alt())
+                            .take(self.count)
+                            .count(),
+                        None => 0,
+                    };
+                    if carriers >= self.count {
+                        return Some(Ok(record));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Iterator returned by [`RecordFilters::split_reference_blocks`].
+pub struct SplitReferenceBlocks<I> {
+    inner: I,
+    // sorted, disjoint reference blocks of the current contig, which a later variant may still split
+    blocks: VecDeque<VcfRecord>,
+    ready: VecDeque<VcfRecord>,
+    chromosome: Option<String>,
+    // last position covered by a record of the current contig
+    covered: u32,
+    finished: bool,
+}
+
+impl<I> SplitReferenceBlocks<I> {
+    fn add(&mut self, record: VcfRecord) {
+        if self.chromosome.as_deref() != Some(record.chromosome.as_str()) {
+            self.ready.extend(self.blocks.drain(..));
+            self.chromosome = Some(record.chromosome.clone());
+            self.covered = 0;
+        }
+        // blocks ending before the record can't be split by any later record
+        while self.blocks.front().is_some_and(|block| block.end_position() < record.position) {
+            self.ready.extend(self.blocks.pop_front());
+        }
+
+        let end = record.end_position();
+        if record.is_reference_block() {
+            let start = record.position.max(self.covered.saturating_add(1));
+            if start <= end {
+                self.blocks.push_back(resize_block(record, start, end));
+                self.covered = end;
+            }
+            return;
+        }
+
+        let mut remaining = VecDeque::new();
+        for block in self.blocks.drain(..) {
+            let (block_start, block_end) = (block.position, block.end_position());
+            if block_start < record.position {
+                self.ready.push_back(resize_block(block.clone(), block_start, record.position - 1));
+            }
+            if block_end > end {
+                remaining.push_back(resize_block(block, block_start.max(end + 1), block_end));
+            }
+        }
+        self.blocks = remaining;
+        self.covered = self.covered.max(end);
+        self.ready.push_back(record);
+    }
+}
+
+/// Move a reference block to cover `start..=end`.
+fn resize_block(mut block: VcfRecord, start: u32, end: u32) -> VcfRecord {
+    if start != block.position {
+        block.position = start;
+        block.reference_bases = "N".into();
+    }
+    for entry in block.info.iter_mut().flatten() {
+        if let InfoEntry::End(block_end) = entry {
+            *block_end = end;
+        }
+    }
+    if block.end.is_some() {
+        block.end = Some(end);
+    }
+    block
+}
+
+impl<I: Iterator<Item=io::Result<VcfRecord>>> Iterator for SplitReferenceBlocks<I> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.ready.pop_front() {
+                return Some(Ok(record));
+            }
+            if self.finished {
+                return None;
+            }
+            match self.inner.next() {
+                Some(Ok(record)) => self.add(record),
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.ready.extend(self.blocks.drain(..));
+                    self.finished = true;
+                }
+            }
+        }
+    }
+}
+
+/// A genomic window of records yielded by [`RecordFilters::genomic_windows`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WindowKey {
+    pub chromosome: String,
+    /// 1-based, inclusive start of the window
+    pub start: u32,
+    /// 1-based, inclusive end of the window
+    pub end: u32,
+}
+
+/// Iterator returned by [`RecordFilters::genomic_windows`].
+pub struct GenomicWindows<I> {
+    inner: I,
+    size: u32,
+    // first record of the next window or an error, read while finishing the previous window
+    pending: Option<io::Result<VcfRecord>>,
+}
+
+impl<I> GenomicWindows<I> {
+    fn key(&self, record: &VcfRecord) -> WindowKey {
+        let start = (record.position.saturating_sub(1) / self.size) * self.size + 1;
+        WindowKey {
+            chromosome: record.chromosome.clone(),
+            start,
+            end: start.saturating_add(self.size - 1),
+        }
+    }
+}
+
+impl<I: Iterator<Item=io::Result<VcfRecord>>> Iterator for GenomicWindows<I> {
+    type Item = io::Result<(WindowKey, Vec<VcfRecord>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = match self.pending.take().or_else(|| self.inner.next())? {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let key = self.key(&first);
+        let mut records = vec![first];
+        loop {
+            match self.inner.next() {
+                Some(Ok(record)) => {
+                    if record.chromosome == key.chromosome && record.position <= key.end {
+                        records.push(record);
+                    } else {
+                        self.pending = Some(Ok(record));
+                        break;
+                    }
+                }
+                Some(Err(e)) => {
+                    self.pending = Some(Err(e));
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        Some(Ok((key, records)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VcfFile;
+
+    fn positions(source: AlleleFrequencySource) -> Vec<u32> {
+        VcfFile::parse("tests/data/allele_frequency.vcf", false)
+            .expect("failed to open VCF file")
+            .records()
+            .expect("failed to open VCF file")
+            .min_allele_frequency_from(0.01, source)
+            .map(|rec| rec.expect("failed to parse VCF record").position)
+            .collect()
+    }
+
+    #[test]
+    fn test_with_info_flag() {
+        let flagged = |key: &str| {
+            VcfFile::parse("tests/data/info_flags.vcf", false)
+                .expect("failed to open VCF file")
+                .records()
+                .expect("failed to open VCF file")
+                .with_info_flag(key)
+                .map(|rec| rec.expect("failed to parse VCF record").position)
+                .collect::<Vec<_>>()
+        };
+
+        // DB=1 is a malformed flag
+        assert_eq!(flagged("DB"), vec![100, 300]);
+        assert_eq!(flagged("SOMATIC"), vec![200, 300]);
+        assert_eq!(flagged("NOVEL"), vec![400]);
+        assert_eq!(flagged("AF"), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_min_carriers() {
+        let carried_in = |path: &str, count: usize| {
+            VcfFile::parse(path, false)
+                .expect("failed to open VCF file")
+                .records()
+                .expect("failed to open VCF file")
+                .min_carriers(count)
+                .map(|rec| rec.expect("failed to parse VCF record").position)
+                .collect::<Vec<_>>()
+        };
+        let carried = |count: usize| carried_in("tests/data/carriers.vcf", count);
+
+        assert_eq!(carried(2), vec![100, 300, 500]);
+        assert_eq!(carried(3), vec![300]);
+        // the partially missing genotypes at 400 don't count
+        assert_eq!(carried(1), vec![100, 200, 300, 400, 500]);
+        assert_eq!(carried(0).len(), 6);
+
+        // records of a sites-only file have no carriers
+        assert_eq!(carried_in("tests/data/quality.vcf", 1), Vec::<u32>::new());
+        assert_eq!(carried_in("tests/data/quality.vcf", 0).len(), 6);
+    }
+
+    #[test]
+    fn test_split_reference_blocks() {
+        let records = VcfFile::parse("tests/data/gvcf_split.vcf", false)
+            .expect("failed to open VCF file")
+            .records()
+            .expect("failed to open VCF file")
+            .split_reference_blocks()
+            .map(|rec| rec.expect("failed to parse VCF record"))
+            .collect::<Vec<_>>();
+        let intervals = records
+            .iter()
+            .map(|rec| (rec.chromosome.as_str(), rec.position, rec.end_position(), rec.reference_bases.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            intervals,
+            vec![
+                // the block is split around the SNP and the deletion
+                ("1", 1, 39, "A"),
+                ("1", 40, 40, "G"),
+                ("1", 41, 59, "N"),
+                ("1", 60, 62, "GTC"),
+                ("1", 63, 100, "N"),
+                // the overlapping block is trimmed, and the block inside it dropped
+                ("1", 101, 120, "N"),
+                ("2", 5, 10, "A"),
+            ]
+        );
+        assert!(records[0].is_reference_block());
+        assert!(!records[1].is_reference_block());
+    }
+
+    #[test]
+    fn test_genomic_windows() {
+        let windows = VcfFile::parse("tests/data/windows.vcf", false)
+            .expect("failed to open VCF file")
+            .records()
+            .expect("failed to open VCF file")
+            .genomic_windows(1000)
+            .map(|window| {
+                let (key, records) = window.expect("failed to parse VCF record");
+                let positions = records.iter().map(|rec| rec.position).collect::<Vec<_>>();
+                (key.chromosome, key.start, key.end, positions)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            windows,
+            vec![
+                ("1".to_string(), 1, 1000, vec![1, 500, 1000]),
+                ("1".to_string(), 1001, 2000, vec![1001]),
+                ("1".to_string(), 5001, 6000, vec![5500]),
+                // the window is reset at the chromosome boundary, even at the same position
+                ("2".to_string(), 5001, 6000, vec![5600, 5999]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_genomic_windows_error() {
+        let vcf_file = VcfFile::parse("tests/data/windows.vcf", false).expect("failed to open VCF file");
+        let mut records = vcf_file.records().unwrap().collect::<Vec<_>>();
+        records.insert(2, Err(io::Error::new(io::ErrorKind::InvalidData, "malformed record")));
+
+        let mut windows = records.into_iter().genomic_windows(1000);
+        let positions = |window: Option<io::Result<(WindowKey, Vec<VcfRecord>)>>| {
+            let (_, records) = window.unwrap().unwrap();
+            records.iter().map(|rec| rec.position).collect::<Vec<_>>()
+        };
+        // the records before the error are yielded as a partial window
+        assert_eq!(positions(windows.next()), vec![1, 500]);
+        assert_eq!(windows.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(positions(windows.next()), vec![1000]);
+        assert_eq!(positions(windows.next()), vec![1001]);
+        assert_eq!(windows.count(), 2);
+    }
+
+    #[test]
+    fn test_min_allele_frequency() {
+        assert_eq!(positions(AlleleFrequencySource::InfoOrGenotypes), vec![200, 300, 500]);
+        assert_eq!(positions(AlleleFrequencySource::Info), vec![200, 300]);
+        assert_eq!(positions(AlleleFrequencySource::Genotypes), vec![100, 200, 500]);
+    }
+}
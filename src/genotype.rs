@@ -0,0 +1,169 @@
+use std::fmt;
+
+use crate::VcfParseError;
+
+/// A parsed GT field: the allele index of each chromosome copy, and whether the alleles are phased. Allele 0 is
+/// the reference allele, allele `i` is the `i`-th alternate allele.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Genotype {
+    /// Allele indices in the order of the GT field, None for missing (`.`) alleles
+    pub alleles: Vec<Option<u32>>,
+    /// Whether all alleles are separated by `|`. Haploid genotypes are never phased.
+    pub phased: bool,
+}
+
+/// Parse a GT field like `0/1`, `1|0`, `./.` or `1`. Whitespace around alleles and separators is tolerated, as in
+/// `0 | 1`. Alleles that are neither an index nor `.` are treated as missing.
+pub fn parse_genotype(gt: &str) -> Genotype {
+    let alleles = gt
+        .split(['/', '|'])
+        .map(|allele| allele.trim().parse().ok())
+        .collect::<Vec<_>>();
+    let phased = alleles.len() > 1 && !gt.contains('/');
+
+    Genotype { alleles, phased }
+}
+
+/// Parse a GT field like [`parse_genotype`], but reject whitespace and alleles that are neither an index nor `.`.
+/// This is how genotypes are checked in strict mode.
+pub fn parse_genotype_strict(gt: &str) -> Result<Genotype, VcfParseError> {
+    let malformed = || VcfParseError::MalformedGenotype(gt.into());
+    let alleles = gt
+        .split(['/', '|'])
+        .map(|allele| match allele {
+            "." => Ok(None),
+            allele if !allele.is_empty() && allele.bytes().all(|b| b.is_ascii_digit()) => {
+                allele.parse().map(Some).map_err(|_| malformed())
+            }
+            _ => Err(malformed()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let phased = alleles.len() > 1 && !gt.contains('/');
+
+    Ok(Genotype { alleles, phased })
+}
+
+impl Genotype {
+    /// Number of chromosome copies described by the genotype
+    pub fn ploidy(&self) -> usize {
+        self.alleles.len()
+    }
+
+    /// Whether all alleles are missing
+    pub fn is_missing(&self) -> bool {
+        self.alleles.iter().all(Option::is_none)
+    }
+
+    /// Whether all alleles are called and at least two of them differ, e.g. `0/1` or `1|2`
+    pub fn is_het(&self) -> bool {
+        match self.alleles.split_first() {
+            Some((Some(first), rest)) => {
+                rest.iter().all(Option::is_some) && rest.iter().any(|allele| *allele != Some(*first))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether all alleles are called and the same alternate allele, e.g. `1/1` or the haploid `2`
+    pub fn is_hom_alt(&self) -> bool {
+        match self.alleles.split_first() {
+            Some((Some(first), rest)) => *first != 0 && rest.iter().all(|allele| *allele == Some(*first)),
+            _ => false,
+        }
+    }
+}
+
+/// Serializes the genotype as a GT field, separating the alleles with `|` if phased and `/` otherwise. Genotypes
+/// with mixed separators like `0/1|2` are parsed as unphased, so they are written with `/` only.
+impl fmt::Display for Genotype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.phased { '|' } else { '/' };
+        for (i, allele) in self.alleles.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", separator)?;
+            }
+            match allele {
+                Some(allele) => write!(f, "{}", allele)?,
+                None => write!(f, ".")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_genotype() {
+        assert_eq!(
+            parse_genotype("0|1"),
+            Genotype {
+                alleles: vec![Some(0), Some(1)],
+                phased: true
+            }
+        );
+        assert_eq!(
+            parse_genotype("0/1"),
+            Genotype {
+                alleles: vec![Some(0), Some(1)],
+                phased: false
+            }
+        );
+
+        let missing = parse_genotype("./.");
+        assert_eq!(missing.alleles, vec![None, None]);
+        assert!(missing.is_missing());
+
+        let multiallelic = parse_genotype("1/2");
+        assert_eq!(multiallelic.alleles, vec![Some(1), Some(2)]);
+        assert!(!multiallelic.is_missing());
+
+        let haploid = parse_genotype("1");
+        assert_eq!(haploid.ploidy(), 1);
+        assert!(!haploid.phased);
+    }
+
+    #[test]
+    fn test_zygosity() {
+        let zygosity = |gt: &str| {
+            let genotype = parse_genotype(gt);
+            (genotype.is_het(), genotype.is_hom_alt())
+        };
+        assert_eq!(zygosity("0/1"), (true, false));
+        assert_eq!(zygosity("2|1"), (true, false));
+        assert_eq!(zygosity("1/1"), (false, true));
+        assert_eq!(zygosity("2"), (false, true));
+        assert_eq!(zygosity("0/0"), (false, false));
+        assert_eq!(zygosity("0"), (false, false));
+        // partially missing genotypes are neither
+        assert_eq!(zygosity("./1"), (false, false));
+        assert_eq!(zygosity("1/."), (false, false));
+        assert_eq!(zygosity("./."), (false, false));
+    }
+
+    #[test]
+    fn test_padded_genotype() {
+        let phased = Genotype {
+            alleles: vec![Some(0), Some(1)],
+            phased: true,
+        };
+        assert_eq!(parse_genotype("0 | 1"), phased);
+        assert_eq!(parse_genotype(" 0|1 "), phased);
+        assert_eq!(parse_genotype_strict("0|1"), Ok(phased));
+
+        assert_eq!(parse_genotype_strict("0 | 1"), Err(VcfParseError::MalformedGenotype("0 | 1".into())));
+        assert!(parse_genotype_strict("0/x").is_err());
+        assert!(parse_genotype_strict("0/").is_err());
+        assert_eq!(parse_genotype_strict("./1").unwrap().alleles, vec![None, Some(1)]);
+    }
+
+    #[test]
+    fn test_genotype_round_trip() {
+        for gt in ["0|1", "1|0", "0/1", "./.", ".|.", "1", ".", "1/2", "0/0/1", "2|.|1", "12/3"] {
+            assert_eq!(parse_genotype(gt).to_string(), gt);
+        }
+        assert_eq!(parse_genotype("0/1|2").to_string(), "0/1/2");
+    }
+}
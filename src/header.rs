@@ -0,0 +1,719 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::VcfHeader;
+
+/// A contig declared by a `##contig=<ID=...>` meta line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContigDefinition {
+    pub id: String,
+    pub length: Option<u64>,
+}
+
+impl ContigDefinition {
+    fn parse(value: &str) -> Option<ContigDefinition> {
+        let attributes = parse_structured_meta(value)?;
+        let attribute = |key: &str| attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+        Some(ContigDefinition {
+            id: attribute("ID")?.into(),
+            length: attribute("length").and_then(|length| length.parse().ok()),
+        })
+    }
+}
+
+/// A field declared by a `##INFO=<ID=...,Number=...,Type=...>` meta line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfoDefinition {
+    pub id: String,
+    pub number: Number,
+    /// Type of the values: `Integer`, `Float`, `Flag`, `Character` or `String`
+    pub value_type: String,
+    pub description: Option<String>,
+}
+
+impl InfoDefinition {
+    fn parse(value: &str) -> Option<InfoDefinition> {
+        let attributes = parse_structured_meta(value)?;
+        Some(InfoDefinition {
+            id: attribute(&attributes, "ID")?.into(),
+            number: Number::parse(attribute(&attributes, "Number")?)?,
+            value_type: attribute(&attributes, "Type")?.into(),
+            description: attribute(&attributes, "Description").map(String::from),
+        })
+    }
+
+    /// Whether the field is a single string, whose value may contain commas that don't separate values
+    pub fn is_single_string(&self) -> bool {
+        self.value_type == "String" && self.number == Number::Count(1)
+    }
+}
+
+/// The `Number` attribute of an INFO or FORMAT definition, declaring how many values the field has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Number {
+    /// A fixed number of values
+    Count(usize),
+    /// One value per alternate allele (`A`)
+    PerAltAllele,
+    /// One value per allele, including the reference allele (`R`)
+    PerAllele,
+    /// One value per possible genotype (`G`)
+    PerGenotype,
+    /// An unknown or varying number of values (`.`)
+    Unknown,
+}
+
+impl Number {
+    /// Parse an integer, `A`, `R`, `G` or `.`. Returns None for any other value.
+    pub fn parse(value: &str) -> Option<Number> {
+        match value {
+            "A" => Some(Number::PerAltAllele),
+            "R" => Some(Number::PerAllele),
+            "G" => Some(Number::PerGenotype),
+            "." => Some(Number::Unknown),
+            count => count.parse().ok().map(Number::Count),
+        }
+    }
+
+    /// Number of values a field has in a record with `alt_count` alternate alleles, where genotypes consist of
+    /// `ploidy` alleles. Returns None if the number is unknown.
+    pub fn expected_count(&self, alt_count: usize, ploidy: usize) -> Option<usize> {
+        match self {
+            Number::Count(count) => Some(*count),
+            Number::PerAltAllele => Some(alt_count),
+            Number::PerAllele => Some(alt_count + 1),
+            // unordered genotypes of `ploidy` alleles drawn from `alt_count + 1` alleles
+            Number::PerGenotype => Some((1..=ploidy).fold(1, |count, i| count * (alt_count + i) / i)),
+            Number::Unknown => None,
+        }
+    }
+}
+
+/// Serializes the number as it appears in the `Number` attribute of a definition.
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Count(count) => write!(f, "{}", count),
+            Number::PerAltAllele => write!(f, "A"),
+            Number::PerAllele => write!(f, "R"),
+            Number::PerGenotype => write!(f, "G"),
+            Number::Unknown => write!(f, "."),
+        }
+    }
+}
+
+/// A `##PEDIGREE=<...>` meta line, relating a sample to its parents or to the sample it was derived from, e.g.
+/// `<ID=Child,Father=Dad,Mother=Mom>` or `<ID=Tumor,Original=Germline>`. Older files name the sample `Child`
+/// instead of `ID`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PedigreeDefinition {
+    pub attributes: Vec<(String, String)>,
+}
+
+/// A `##SAMPLE=<ID=...>` meta line describing a sample, e.g. the genomes and mixture of a tumor sample.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleDefinition {
+    pub id: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl PedigreeDefinition {
+    /// Get the value of an attribute by its key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        attribute(&self.attributes, key)
+    }
+
+    /// The sample described by this line, from its `ID` or `Child` attribute
+    pub fn id(&self) -> Option<&str> {
+        self.get("ID").or_else(|| self.get("Child"))
+    }
+
+    pub fn father(&self) -> Option<&str> {
+        self.get("Father")
+    }
+
+    pub fn mother(&self) -> Option<&str> {
+        self.get("Mother")
+    }
+
+    /// The sample this sample was derived from, e.g. the germline sample of a tumor
+    pub fn original(&self) -> Option<&str> {
+        self.get("Original")
+    }
+}
+
+impl SampleDefinition {
+    fn parse(value: &str) -> Option<SampleDefinition> {
+        let attributes = parse_structured_meta(value)?;
+        Some(SampleDefinition {
+            id: attribute(&attributes, "ID")?.into(),
+            attributes,
+        })
+    }
+
+    /// Get the value of an attribute by its key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        attribute(&self.attributes, key)
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.get("Description")
+    }
+}
+
+impl VcfHeader {
+    /// Create a header from its meta lines, which are parsed into the typed definitions like those of a file. The
+    /// size and line count are those of the serialized header.
+    pub(crate) fn new(
+        file_format: String,
+        values: Vec<(String, String)>,
+        sample_names: Option<Vec<String>>,
+        has_end_column: bool,
+    ) -> VcfHeader {
+        let contigs = parse_contigs(&values);
+        let mut header = VcfHeader {
+            file_format,
+            has_end_column,
+            sample_names,
+            contig_indices: index_contigs(&contigs),
+            contigs,
+            info_definitions: parse_info_definitions(&values),
+            pedigrees: parse_pedigrees(&values),
+            sample_definitions: parse_sample_definitions(&values),
+            // the fileformat line and the column header line surround the meta lines
+            lines: values.len() as u64 + 2,
+            values,
+            size: 0,
+            sample_indices: OnceLock::new(),
+        };
+        header.size = header.to_string().len();
+        header
+    }
+
+    /// The names of the sample columns, which are empty if the file has no FORMAT column.
+    pub fn samples(&self) -> &[String] {
+        self.sample_names.as_deref().unwrap_or_default()
+    }
+
+    /// Get the index of the sample column with the given name, e.g. to find its entry in
+    /// [`crate::SampleInfo::samples`].
+    pub fn sample_index(&self, name: &str) -> Option<usize> {
+        // collected in reverse, so the first of duplicate names wins
+        self.sample_indices
+            .get_or_init(|| self.samples().iter().enumerate().map(|(i, name)| (name.clone(), i)).rev().collect())
+            .get(name)
+            .copied()
+    }
+
+    /// Get the value of a single-value meta line like `##fileDate=20090805`. Returns the first value if the key is
+    /// repeated, and None for structured lines like `##INFO=<...>`.
+    pub fn meta(&self, key: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(k, value)| k == key && !value.starts_with('<'))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the `##INFO` line declaring the given key.
+    pub fn info_definition(&self, id: &str) -> Option<&InfoDefinition> {
+        self.info_definitions.iter().find(|definition| definition.id == id)
+    }
+
+    /// Get the pedigree line describing the given sample.
+    pub fn pedigree(&self, sample: &str) -> Option<&PedigreeDefinition> {
+        self.pedigrees.iter().find(|pedigree| pedigree.id() == Some(sample))
+    }
+
+    /// Get the `##SAMPLE` line describing the given sample.
+    pub fn sample_definition(&self, sample: &str) -> Option<&SampleDefinition> {
+        self.sample_definitions.iter().find(|definition| definition.id == sample)
+    }
+
+    /// Get the reference sequence the file was called against, from the `##reference` meta line.
+    pub fn reference(&self) -> Option<&str> {
+        self.meta("reference")
+    }
+
+    /// Get the genome assembly, e.g. `GRCh38`, from the `##assembly` meta line.
+    pub fn assembly(&self) -> Option<&str> {
+        self.meta("assembly")
+    }
+
+    /// Create the header of a file with only the given samples, in the given order, skipping names that are not in
+    /// the header and repeated names. `##SAMPLE` lines of dropped samples are removed, and `##PEDIGREE` lines no
+    /// longer reference dropped samples: lines describing a dropped sample are removed, as are lines whose relations
+    /// all referenced dropped samples. Records are subset with [`crate::VcfRecord::select_samples`] and the indices
+    /// of the retained samples in this header.
+    pub fn subset_samples(&self, names: &[&str]) -> VcfHeader {
+        let mut retained = Vec::<String>::new();
+        for name in names {
+            if self.sample_index(name).is_some() && !retained.iter().any(|kept| kept == name) {
+                retained.push(name.to_string());
+            }
+        }
+        let dropped = |name: &str| self.sample_index(name).is_some() && !retained.iter().any(|kept| kept == name);
+
+        let values = self
+            .values
+            .iter()
+            .filter_map(|(key, value)| {
+                let value = match key.as_str() {
+                    "SAMPLE" => match SampleDefinition::parse(value) {
+                        Some(definition) if dropped(&definition.id) => return None,
+                        _ => value.clone(),
+                    },
+                    "PEDIGREE" => match parse_structured_meta(value) {
+                        Some(attributes) => subset_pedigree(value, attributes, dropped)?,
+                        None => value.clone(),
+                    },
+                    _ => value.clone(),
+                };
+                Some((key.clone(), value))
+            })
+            .collect::<Vec<_>>();
+
+        let sample_names = self.sample_names.as_ref().map(|_| retained);
+        VcfHeader {
+            // contig names may have been normalized after parsing
+            contigs: self.contigs.clone(),
+            contig_indices: self.contig_indices.clone(),
+            ..VcfHeader::new(self.file_format.clone(), values, sample_names, self.has_end_column)
+        }
+    }
+
+    /// Get the index of a contig in the order of the `##contig` declarations, e.g. to compare coordinates on
+    /// different contigs without searching the declarations. Returns None for undeclared contigs.
+    pub fn contig_index(&self, id: &str) -> Option<usize> {
+        self.contig_indices.get(id).copied()
+    }
+
+    /// Get the declared length of a contig, if the header declares the contig with a `length` attribute.
+    pub fn contig_length(&self, id: &str) -> Option<u64> {
+        self.contigs
+            .iter()
+            .find(|contig| contig.id == id)
+            .and_then(|contig| contig.length)
+    }
+}
+
+/// Builder for a [`VcfHeader`] of a new file, e.g. to write records with a [`crate::VcfWriter`] without reading an
+/// input file. Meta lines are written in the order they are added. Without samples, the header describes a
+/// sites-only file without FORMAT column.
+#[derive(Debug, Clone)]
+pub struct VcfHeaderBuilder {
+    file_format: String,
+    values: Vec<(String, String)>,
+    sample_names: Option<Vec<String>>,
+}
+
+impl Default for VcfHeaderBuilder {
+    fn default() -> Self {
+        VcfHeaderBuilder {
+            file_format: "VCFv4.2".into(),
+            values: Vec::new(),
+            sample_names: None,
+        }
+    }
+}
+
+impl VcfHeaderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the version of the `##fileformat` line, `VCFv4.2` by default.
+    pub fn file_format(mut self, version: &str) -> Self {
+        self.file_format = version.into();
+        self
+    }
+
+    /// Add an unstructured meta line like `##source=myProgram`.
+    pub fn meta(mut self, key: &str, value: &str) -> Self {
+        self.values.push((key.into(), value.into()));
+        self
+    }
+
+    /// Add a `##contig` line with an optional length.
+    pub fn contig(mut self, id: &str, length: Option<u64>) -> Self {
+        let value = match length {
+            Some(length) => format!("<ID={},length={}>", id, length),
+            None => format!("<ID={}>", id),
+        };
+        self.values.push(("contig".into(), value));
+        self
+    }
+
+    /// Add an `##INFO` line declaring a field of the INFO column.
+    pub fn info(self, id: &str, number: Number, value_type: &str, description: &str) -> Self {
+        self.field_definition("INFO", id, number, value_type, description)
+    }
+
+    /// Add a `##FORMAT` line declaring a field of the sample columns.
+    pub fn format(self, id: &str, number: Number, value_type: &str, description: &str) -> Self {
+        self.field_definition("FORMAT", id, number, value_type, description)
+    }
+
+    /// Add a `##FILTER` line declaring a filter of the FILTER column.
+    pub fn filter(mut self, id: &str, description: &str) -> Self {
+        self.values.push(("FILTER".into(), format!("<ID={},Description={}>", id, quoted(description))));
+        self
+    }
+
+    /// Set the names of the sample columns, which adds a FORMAT column to the header.
+    pub fn samples(mut self, names: &[&str]) -> Self {
+        self.sample_names = Some(names.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    fn field_definition(mut self, key: &str, id: &str, number: Number, value_type: &str, description: &str) -> Self {
+        let value = format!("<ID={},Number={},Type={},Description={}>", id, number, value_type, quoted(description));
+        self.values.push((key.into(), value));
+        self
+    }
+
+    /// Build the header. Its meta lines are parsed like those of a file, so the typed definitions like
+    /// [`VcfHeader::contigs`] are available as well.
+    pub fn build(self) -> VcfHeader {
+        VcfHeader::new(self.file_format, self.values, self.sample_names, false)
+    }
+}
+
+/// Remove the relations to dropped samples from a `##PEDIGREE` line. Returns None if the line describes a dropped
+/// sample or has no relations left, and the unchanged value if no relation was removed.
+fn subset_pedigree(value: &str, attributes: Vec<(String, String)>, dropped: impl Fn(&str) -> bool) -> Option<String> {
+    let pedigree = PedigreeDefinition { attributes };
+    if pedigree.id().is_some_and(&dropped) {
+        return None;
+    }
+
+    let is_id = |key: &str| key == "ID" || key == "Child";
+    let count = pedigree.attributes.len();
+    let attributes = pedigree
+        .attributes
+        .into_iter()
+        .filter(|(key, value)| is_id(key) || !dropped(value))
+        .collect::<Vec<_>>();
+    if attributes.len() == count {
+        return Some(value.into());
+    }
+    if attributes.iter().all(|(key, _)| is_id(key)) {
+        return None;
+    }
+
+    let attributes = attributes
+        .iter()
+        .map(|(key, value)| {
+            if value.contains([',', '"', '<', '>', '=', ' ']) {
+                format!("{}={}", key, quoted(value))
+            } else {
+                format!("{}={}", key, value)
+            }
+        })
+        .collect::<Vec<_>>();
+    Some(format!("<{}>", attributes.join(",")))
+}
+
+/// Quote the value of a `Description` attribute, escaping quotes and backslashes within it.
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Parse all `##contig` meta lines of the header.
+pub(crate) fn parse_contigs(header_lines: &[(String, String)]) -> Vec<ContigDefinition> {
+    header_lines
+        .iter()
+        .filter(|(key, _)| key == "contig")
+        .filter_map(|(_, value)| ContigDefinition::parse(value))
+        .collect()
+}
+
+/// Index contigs in the order of their declarations. Of duplicate declarations, the first one wins.
+pub(crate) fn index_contigs(contigs: &[ContigDefinition]) -> HashMap<String, usize> {
+    contigs.iter().enumerate().map(|(i, contig)| (contig.id.clone(), i)).rev().collect()
+}
+
+/// Parse all `##INFO` meta lines of the header.
+pub(crate) fn parse_info_definitions(header_lines: &[(String, String)]) -> Vec<InfoDefinition> {
+    header_lines
+        .iter()
+        .filter(|(key, _)| key == "INFO")
+        .filter_map(|(_, value)| InfoDefinition::parse(value))
+        .collect()
+}
+
+/// Parse all `##PEDIGREE` meta lines of the header.
+pub(crate) fn parse_pedigrees(header_lines: &[(String, String)]) -> Vec<PedigreeDefinition> {
+    header_lines
+        .iter()
+        .filter(|(key, _)| key == "PEDIGREE")
+        .filter_map(|(_, value)| parse_structured_meta(value))
+        .map(|attributes| PedigreeDefinition { attributes })
+        .collect()
+}
+
+/// Parse all `##SAMPLE` meta lines of the header.
+pub(crate) fn parse_sample_definitions(header_lines: &[(String, String)]) -> Vec<SampleDefinition> {
+    header_lines
+        .iter()
+        .filter(|(key, _)| key == "SAMPLE")
+        .filter_map(|(_, value)| SampleDefinition::parse(value))
+        .collect()
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+/// Split the value of a structured meta line like `<ID=DP,Number=1,Description="Total, combined depth">` into its
+/// key-value pairs. Quotes around values are removed, and commas within quotes don't separate entries. Returns None
+/// if the value isn't enclosed in angle brackets.
+pub(crate) fn parse_structured_meta(value: &str) -> Option<Vec<(String, String)>> {
+    let content = value.strip_prefix('<')?.strip_suffix('>')?;
+
+    let mut attributes = Vec::new();
+    let mut key = String::new();
+    let mut value = String::new();
+    let mut in_value = false;
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for c in content.chars() {
+        match c {
+            _ if escaped => {
+                value.push(c);
+                escaped = false;
+            }
+            '\\' if in_quotes => escaped = true,
+            '"' if in_value => in_quotes = !in_quotes,
+            '=' if !in_value => in_value = true,
+            ',' if !in_quotes => {
+                attributes.push((std::mem::take(&mut key), std::mem::take(&mut value)));
+                in_value = false;
+            }
+            _ if in_value => value.push(c),
+            _ => key.push(c),
+        }
+    }
+    if !key.is_empty() {
+        attributes.push((key, value));
+    }
+
+    Some(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParseOptions, VcfFile, VcfRecord, VcfWriter};
+
+    #[test]
+    fn test_sample_index() {
+        let vcf_file = VcfFile::parse("tests/data/trio.vcf", false).expect("failed to open VCF file");
+        assert_eq!(vcf_file.header.samples(), ["NA12878", "NA12891", "NA12892"]);
+        assert_eq!(vcf_file.header.sample_index("NA12891"), Some(1));
+        assert_eq!(vcf_file.header.sample_index("NA12878"), Some(0));
+        assert_eq!(vcf_file.header.sample_index("unknown"), None);
+
+        let sites_only = VcfFile::parse("tests/data/quality.vcf", false).unwrap();
+        assert!(sites_only.header.samples().is_empty());
+        assert_eq!(sites_only.header.sample_index("S1"), None);
+    }
+
+    #[test]
+    fn test_share_between_threads() {
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<VcfHeader>();
+        assert_sync::<VcfFile>();
+
+        let vcf_file = VcfFile::parse("tests/data/trio.vcf", false).unwrap();
+        let indices = std::thread::scope(|scope| {
+            let threads = ["NA12892", "NA12878"].map(|name| scope.spawn(|| vcf_file.header.sample_index(name)));
+            threads.map(|thread| thread.join().unwrap())
+        });
+        assert_eq!(indices, [Some(2), Some(0)]);
+    }
+
+    #[test]
+    fn test_contig_index() {
+        let vcf_file = VcfFile::parse("tests/data/contig_length.vcf", false).expect("failed to open VCF file");
+        let indices = ["chr1", "chr2", "chrUn", "chr3"].map(|id| vcf_file.header.contig_index(id));
+        assert_eq!(indices, [Some(0), Some(1), Some(2), None]);
+
+        let options = ParseOptions::new().contig_normalizer(|id| id.trim_start_matches("chr").into());
+        let normalized = VcfFile::parse_with_options("tests/data/contig_length.vcf", false, options).unwrap();
+        assert_eq!(normalized.header.contig_index("2"), Some(1));
+        assert_eq!(normalized.header.contig_index("chr2"), None);
+
+        // reading records doesn't index undeclared contigs
+        let undeclared = VcfFile::parse("tests/data/windows.vcf", false).unwrap();
+        assert_eq!(undeclared.records().unwrap().count(), 7);
+        assert_eq!(undeclared.header.contig_index("1"), None);
+    }
+
+    #[test]
+    fn test_meta() {
+        let vcf_file = VcfFile::parse("tests/data/meta_lines.vcf", false).expect("failed to open VCF file");
+        assert_eq!(vcf_file.header.meta("fileDate"), Some("20090805"));
+        assert_eq!(vcf_file.header.meta("source"), Some("myImputationProgramV3.1"));
+        assert_eq!(vcf_file.header.meta("commandline"), Some("bcftools view -i DP=10"));
+        assert_eq!(vcf_file.header.meta("INFO"), None);
+        assert_eq!(vcf_file.header.meta("assembly"), None);
+    }
+
+    #[test]
+    fn test_header_builder() {
+        let header = VcfHeaderBuilder::new()
+            .meta("source", "vcfire")
+            .contig("1", Some(1000))
+            .contig("2", None)
+            .info("DP", Number::Count(1), "Integer", "Combined depth")
+            .info("AF", Number::PerAltAllele, "Float", "Allele \"frequency\", estimated")
+            .format("GT", Number::Count(1), "String", "Genotype")
+            .filter("q10", "Quality below 10")
+            .samples(&["S1", "S2"])
+            .build();
+        assert_eq!(header.contig_length("1"), Some(1000));
+        let description = header.info_definition("AF").unwrap().description.as_deref();
+        assert_eq!(description, Some("Allele \"frequency\", estimated"));
+
+        let path = std::env::temp_dir().join("vcfire_built_header.vcf");
+        let path = path.to_str().unwrap();
+        let line = "1\t100\t.\tA\tG\t30\tPASS\tDP=10;AF=0.5\tGT\t0/1\t1/1";
+        let record = VcfRecord::parse(line, &header, &ParseOptions::default()).unwrap();
+        let mut writer = VcfWriter::new(std::fs::File::create(path).unwrap(), &header).unwrap();
+        writer.write_record(&record).unwrap();
+        writer.into_inner().unwrap();
+
+        let vcf_file = VcfFile::parse(path, false).expect("failed to open written file");
+        assert_eq!(vcf_file.header.file_format, "VCFv4.2");
+        assert_eq!(vcf_file.header.values, header.values);
+        assert_eq!(vcf_file.header.samples(), ["S1", "S2"]);
+        assert_eq!(vcf_file.header.contigs, header.contigs);
+        assert_eq!(vcf_file.header.info_definitions, header.info_definitions);
+        assert_eq!((vcf_file.header.size, vcf_file.header.lines), (header.size, header.lines));
+        assert_eq!(vcf_file.header.meta("source"), Some("vcfire"));
+
+        let records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].info, record.info);
+        assert_eq!(records[0].sample_info.as_ref().unwrap().samples().count(), 2);
+
+        let sites_only = VcfHeaderBuilder::new().file_format("VCFv4.3").build();
+        assert_eq!(sites_only.to_string(), "##fileformat=VCFv4.3\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n");
+    }
+
+    #[test]
+    fn test_subset_samples() {
+        let vcf_file = VcfFile::parse("tests/data/trio.vcf", false).expect("failed to open VCF file");
+        let header = &vcf_file.header;
+
+        let parents = header.subset_samples(&["NA12892", "NA12891", "unknown", "NA12892"]);
+        assert_eq!(parents.samples(), ["NA12892", "NA12891"]);
+        assert!(parents.sample_definitions.is_empty());
+        // the tumor line only related the tumor to the dropped daughter
+        assert!(parents.pedigrees.is_empty());
+        assert!(!parents.to_string().contains("NA12878"));
+
+        let without_father = header.subset_samples(&["NA12878", "NA12892"]);
+        assert_eq!(without_father.sample_definition("NA12878"), header.sample_definition("NA12878"));
+        let child = without_father.pedigree("NA12878").unwrap();
+        assert_eq!((child.father(), child.mother()), (None, Some("NA12892")));
+        assert_eq!(without_father.pedigree("Tumor"), header.pedigree("Tumor"));
+        assert!(!without_father.to_string().contains("NA12891"));
+
+        let indices = ["NA12892", "NA12891"].map(|name| header.sample_index(name).unwrap());
+        let mut writer = VcfWriter::new(Vec::new(), &parents).unwrap();
+        for record in vcf_file.records().unwrap() {
+            let mut record = record.unwrap();
+            record.select_samples(&indices);
+            writer.write_record(&record).unwrap();
+        }
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        let reparsed = VcfFile::parse_header(&mut written.as_bytes()).unwrap();
+        assert_eq!(reparsed.samples(), ["NA12892", "NA12891"]);
+        assert_eq!(reparsed.size, parents.size);
+        assert!(written.ends_with("GT\t1/1\t0/0\n"));
+    }
+
+    #[test]
+    fn test_number() {
+        let numbers = ["0", "1", "A", "R", "G", "."].map(|number| Number::parse(number).unwrap());
+        assert_eq!(
+            numbers,
+            [
+                Number::Count(0),
+                Number::Count(1),
+                Number::PerAltAllele,
+                Number::PerAllele,
+                Number::PerGenotype,
+                Number::Unknown
+            ]
+        );
+        assert_eq!(Number::parse("X"), None);
+        assert_eq!(Number::parse("-1"), None);
+
+        let counts = numbers.map(|number| number.expected_count(2, 2));
+        assert_eq!(counts, [Some(0), Some(1), Some(2), Some(3), Some(6), None]);
+        assert_eq!(Number::PerGenotype.expected_count(1, 2), Some(3));
+        assert_eq!(Number::PerGenotype.expected_count(1, 1), Some(2));
+        assert_eq!(Number::PerGenotype.expected_count(2, 3), Some(10));
+
+        let vcf_file = VcfFile::parse("tests/data/string_info.vcf", false).expect("failed to open VCF file");
+        assert_eq!(vcf_file.header.info_definition("NOTE").unwrap().number, Number::Count(1));
+        assert_eq!(vcf_file.header.info_definition("TAGS").unwrap().number, Number::Count(2));
+    }
+
+    #[test]
+    fn test_reference_and_assembly() {
+        let vcf_file = VcfFile::parse("tests/data/sample_fields.vcf", false).expect("failed to open VCF file");
+        assert_eq!(vcf_file.header.reference(), Some("file:///seq/references/GRCh38.fasta"));
+        assert_eq!(vcf_file.header.assembly(), Some("GRCh38"));
+
+        let vcf_file = VcfFile::parse("tests/data/quality.vcf", false).expect("failed to open VCF file");
+        assert_eq!(vcf_file.header.reference(), None);
+        assert_eq!(vcf_file.header.assembly(), None);
+    }
+
+    #[test]
+    fn test_pedigree() {
+        let vcf_file = VcfFile::parse("tests/data/trio.vcf", false).expect("failed to open VCF file");
+        let header = &vcf_file.header;
+        assert_eq!(header.pedigrees.len(), 2);
+
+        let child = header.pedigree("NA12878").unwrap();
+        assert_eq!((child.father(), child.mother()), (Some("NA12891"), Some("NA12892")));
+        assert_eq!(header.pedigree("NA12891"), None);
+
+        // the pre-4.2 form names the sample Child
+        let tumor = header.pedigree("Tumor").unwrap();
+        assert_eq!(tumor.original(), Some("NA12878"));
+        assert_eq!(tumor.father(), None);
+
+        let sample = header.sample_definition("NA12878").unwrap();
+        assert_eq!(sample.description(), Some("Daughter, sequenced twice"));
+        assert_eq!(sample.get("Assay"), Some("WGS"));
+        assert!(header.sample_definition("Tumor").is_none());
+    }
+
+    #[test]
+    fn test_parse_structured_meta() {
+        assert_eq!(
+            parse_structured_meta(r#"<ID=DP,Number=1,Description="Total, \"combined\" depth">"#),
+            Some(vec![
+                ("ID".into(), "DP".into()),
+                ("Number".into(), "1".into()),
+                ("Description".into(), r#"Total, "combined" depth"#.into()),
+            ])
+        );
+        assert_eq!(parse_structured_meta("20240101"), None);
+        assert_eq!(
+            ContigDefinition::parse("<ID=chr1,length=248956422,assembly=GRCh38>"),
+            Some(ContigDefinition {
+                id: "chr1".into(),
+                length: Some(248956422)
+            })
+        );
+    }
+}
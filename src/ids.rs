@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::{RecordIds, VcfFile, VcfRecord};
+
+impl VcfFile {
+    /// Scan the file once and keep the position of every record by its identifiers in memory, so that
+    /// [`VcfFile::get_by_id`] can jump to a record instead of scanning the file. Records with multiple IDs like
+    /// `rs1;COSV1` can be looked up by each of them, and if an ID appears in several records, the first one is kept.
+    /// Does nothing if the index was built before.
+    ///
+    /// The records don't have to be sorted, and compressed files are supported, but for them, a lookup still
+    /// decompresses everything before the record.
+    pub fn build_id_index(&self) -> io::Result<()> {
+        if self.id_index.get().is_some() {
+            return Ok(());
+        }
+
+        let mut index = HashMap::new();
+        let mut records = self.records()?;
+        let mut checkpoint = records.checkpoint();
+        while records.read_line()? {
+            let ids = records.buffer.split('\t').nth(2).and_then(RecordIds::parse);
+            for id in ids.iter().flat_map(RecordIds::iter) {
+                index.entry(id.to_string()).or_insert(checkpoint);
+            }
+            checkpoint = records.checkpoint();
+        }
+
+        let _ = self.id_index.set(index);
+        Ok(())
+    }
+
+    /// Get the first record with the given identifier in its ID column, e.g. to look up the variants of a list of
+    /// rsIDs. Without an index built by [`VcfFile::build_id_index`], the file is scanned up to the record.
+    pub fn get_by_id(&self, id: &str) -> io::Result<Option<VcfRecord>> {
+        match self.id_index.get() {
+            Some(index) => match index.get(id) {
+                Some(checkpoint) => self.records_from(checkpoint)?.next().transpose(),
+                None => Ok(None),
+            },
+            None => {
+                for record in self.records()? {
+                    let record = record?;
+                    if record.id.as_ref().is_some_and(|ids| ids.iter().any(|record_id| record_id == id)) {
+                        return Ok(Some(record));
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::VcfFile;
+
+    #[test]
+    fn test_get_by_id() {
+        for (path, compressed) in [("tests/data/ids.vcf", false), ("tests/data/ids.vcf.gz", true)] {
+            let vcf_file = VcfFile::parse(path, compressed).expect("failed to open VCF file");
+            let site = |id: &str| {
+                let record = vcf_file.get_by_id(id).unwrap();
+                record.map(|record| (record.chromosome, record.position))
+            };
+
+            let scanned = ["rs100", "rs300", "COSV300", "rs2100", "rs404"].map(site);
+            vcf_file.build_id_index().unwrap();
+            assert_eq!(vcf_file.id_index.get().unwrap().len(), 4);
+            let indexed = ["rs100", "rs300", "COSV300", "rs2100", "rs404"].map(site);
+            assert_eq!(indexed, scanned);
+            assert_eq!(
+                indexed,
+                [
+                    Some(("1".into(), 100)),
+                    Some(("1".into(), 300)),
+                    Some(("1".into(), 300)),
+                    Some(("2".into(), 100)),
+                    None
+                ]
+            );
+            assert_eq!(site("."), None);
+        }
+    }
+}
@@ -0,0 +1,752 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read, Write};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::bgzf::{BgzfWriter, VirtualOffset};
+
+/// Magic bytes at the start of a decompressed `.tbi` file
+const TABIX_MAGIC: &[u8; 4] = b"TBI\x01";
+
+/// Magic bytes at the start of a decompressed `.csi` file
+const CSI_MAGIC: &[u8; 4] = b"CSI\x01";
+
+/// Tabix preset for VCF files: format, sequence column, begin column, end column, meta character, skipped lines
+const VCF_PRESET: [i32; 6] = [2, 1, 2, 0, b'#' as i32, 0];
+
+/// Binning scheme of tabix indices, which is a CSI scheme with fixed parameters
+const TABIX_SCHEME: BinningScheme = BinningScheme {
+    min_shift: 14,
+    depth: 5,
+};
+
+/// A contiguous range of the BGZF file that contains records overlapping a bin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunk {
+    pub begin: VirtualOffset,
+    pub end: VirtualOffset,
+}
+
+/// The hierarchical binning scheme shared by tabix and CSI indices. Bins at the lowest level span
+/// `2^min_shift` bases, each level above spans eight times as much, and the root bin spans
+/// `2^(min_shift + 3 * depth)` bases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BinningScheme {
+    min_shift: u32,
+    depth: u32,
+}
+
+impl BinningScheme {
+    /// Check that the root bin fits into 64 bit coordinates.
+    fn is_valid(&self) -> bool {
+        self.depth.checked_mul(3).and_then(|bits| bits.checked_add(self.min_shift)).is_some_and(|bits| bits <= 63)
+    }
+
+    /// Calculate the smallest bin fully containing the 0-based, half-open region `begin..end`.
+    fn region_to_bin(&self, begin: u64, end: u64) -> u32 {
+        let end = end - 1;
+        let mut shift = self.min_shift;
+        let mut level_offset = ((1u64 << (3 * self.depth)) - 1) / 7;
+        for level in (1..=self.depth).rev() {
+            if begin >> shift == end >> shift {
+                return (level_offset + (begin >> shift)) as u32;
+            }
+            shift += 3;
+            level_offset -= 1 << (3 * (level - 1));
+        }
+        0
+    }
+
+    /// Calculate all bins that may contain records overlapping the 0-based, half-open region `begin..end`.
+    fn region_to_bins(&self, begin: u64, end: u64) -> Vec<u32> {
+        let end = end - 1;
+        let mut bins = Vec::new();
+        let mut shift = self.min_shift + 3 * self.depth;
+        let mut level_offset = 0;
+        for level in 0..=self.depth {
+            bins.extend((level_offset + (begin >> shift))..=(level_offset + (end >> shift)));
+            shift -= 3;
+            level_offset += 1 << (3 * level);
+        }
+        bins.into_iter().map(|bin| bin as u32).collect()
+    }
+
+    /// Get the first base position covered by a bin.
+    fn bin_start(&self, bin: u32) -> u64 {
+        let mut level_offset = 0u64;
+        for level in 0..=self.depth {
+            let level_size = 1 << (3 * level);
+            if (bin as u64) < level_offset + level_size {
+                let shift = self.min_shift + 3 * (self.depth - level);
+                return (bin as u64 - level_offset) << shift;
+            }
+            level_offset += level_size;
+        }
+        0
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Bin {
+    // smallest virtual offset of records overlapping the start of the bin, only stored in CSI indices
+    loffset: VirtualOffset,
+    chunks: Vec<Chunk>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ReferenceIndex {
+    bins: BTreeMap<u32, Bin>,
+    // smallest virtual offset of all records overlapping each window of the lowest bin level. Only stored in
+    // tabix indices
+    linear: Vec<VirtualOffset>,
+}
+
+/// Contents shared by tabix and CSI indices
+#[derive(Debug, PartialEq)]
+struct BinningIndex {
+    scheme: BinningScheme,
+    preset: [i32; 6],
+    names: Vec<String>,
+    references: Vec<ReferenceIndex>,
+}
+
+impl BinningIndex {
+    fn chunks(&self, chromosome: &str, start: u32, end: u32) -> Vec<Chunk> {
+        let Some(reference) = self
+            .names
+            .iter()
+            .position(|name| name == chromosome)
+            .map(|i| &self.references[i])
+        else {
+            return Vec::new();
+        };
+
+        // indices use 0-based, half-open intervals
+        let begin = start.saturating_sub(1) as u64;
+        let end = (end as u64).max(begin + 1);
+
+        // records ending before this offset cannot overlap the region
+        let min_offset = if reference.linear.is_empty() {
+            let mut bin = self.scheme.region_to_bin(begin, begin + 1);
+            loop {
+                if let Some(bin) = reference.bins.get(&bin) {
+                    break bin.loffset;
+                }
+                if bin == 0 {
+                    break VirtualOffset(0);
+                }
+                bin = (bin - 1) >> 3;
+            }
+        } else {
+            reference
+                .linear
+                .get((begin >> self.scheme.min_shift) as usize)
+                .or(reference.linear.last())
+                .copied()
+                .unwrap_or_default()
+        };
+
+        let mut chunks = self
+            .scheme
+            .region_to_bins(begin, end)
+            .into_iter()
+            .filter_map(|bin| reference.bins.get(&bin))
+            .flat_map(|bin| &bin.chunks)
+            .filter(|chunk| chunk.end > min_offset)
+            .copied()
+            .collect::<Vec<_>>();
+        chunks.sort_by_key(|chunk| chunk.begin);
+        chunks
+    }
+
+    /// Parse the tabix header fields and sequence names, which are stored in the same layout in tabix indices
+    /// and in the auxiliary data of CSI indices.
+    fn read_preset(data: &mut &[u8]) -> io::Result<([i32; 6], Vec<String>)> {
+        let mut preset = [0; 6];
+        for value in preset.iter_mut() {
+            *value = read_i32(data)?;
+        }
+
+        let mut names = vec![0; read_count(data)?];
+        data.read_exact(&mut names)?;
+        let names = names
+            .split(|&b| b == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect();
+        Ok((preset, names))
+    }
+
+    fn write_preset<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for value in self.preset {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+
+        writer.write_all(&(self.name_block_length() as i32).to_le_bytes())?;
+        for name in &self.names {
+            writer.write_all(name.as_bytes())?;
+            writer.write_all(&[0])?;
+        }
+        Ok(())
+    }
+
+    fn name_block_length(&self) -> usize {
+        self.names.iter().map(|name| name.len() + 1).sum()
+    }
+
+    fn read_references(data: &mut &[u8], count: usize, csi: bool) -> io::Result<Vec<ReferenceIndex>> {
+        let mut references = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut reference = ReferenceIndex::default();
+            for _ in 0..read_count(data)? {
+                let number = read_u32(data)?;
+                let loffset = if csi {
+                    VirtualOffset(read_u64(data)?)
+                } else {
+                    VirtualOffset(0)
+                };
+                let chunks = (0..read_count(data)?)
+                    .map(|_| {
+                        Ok(Chunk {
+                            begin: VirtualOffset(read_u64(data)?),
+                            end: VirtualOffset(read_u64(data)?),
+                        })
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+                reference.bins.insert(number, Bin { loffset, chunks });
+            }
+            if !csi {
+                reference.linear = (0..read_count(data)?)
+                    .map(|_| read_u64(data).map(VirtualOffset))
+                    .collect::<io::Result<Vec<_>>>()?;
+            }
+            references.push(reference);
+        }
+        Ok(references)
+    }
+
+    fn write_references<W: Write>(&self, writer: &mut W, csi: bool) -> io::Result<()> {
+        for reference in &self.references {
+            writer.write_all(&(reference.bins.len() as i32).to_le_bytes())?;
+            for (number, bin) in &reference.bins {
+                writer.write_all(&number.to_le_bytes())?;
+                if csi {
+                    writer.write_all(&bin.loffset.0.to_le_bytes())?;
+                }
+                writer.write_all(&(bin.chunks.len() as i32).to_le_bytes())?;
+                for chunk in &bin.chunks {
+                    writer.write_all(&chunk.begin.0.to_le_bytes())?;
+                    writer.write_all(&chunk.end.0.to_le_bytes())?;
+                }
+            }
+            if !csi {
+                writer.write_all(&(reference.linear.len() as i32).to_le_bytes())?;
+                for offset in &reference.linear {
+                    writer.write_all(&offset.0.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A tabix (`.tbi`) index over a BGZF compressed VCF file. It maps genomic regions to chunks of the compressed
+/// file, so region queries only need to decompress the blocks that contain overlapping records.
+#[derive(Debug, PartialEq)]
+pub struct TabixIndex(BinningIndex);
+
+impl TabixIndex {
+    /// Load a `.tbi` index file.
+    pub fn from_file(path: &str) -> io::Result<TabixIndex> {
+        Self::from_bytes(&read_compressed(path)?)
+    }
+
+    /// Parse a decompressed tabix index.
+    fn from_bytes(mut data: &[u8]) -> io::Result<TabixIndex> {
+        if read_magic(&mut data)? != *TABIX_MAGIC {
+            return Err(invalid_index("missing TBI magic bytes"));
+        }
+
+        let reference_count = read_count(&mut data)?;
+        let (preset, names) = BinningIndex::read_preset(&mut data)?;
+        if names.len() != reference_count {
+            return Err(invalid_index("sequence name count does not match reference count"));
+        }
+
+        Ok(TabixIndex(BinningIndex {
+            scheme: TABIX_SCHEME,
+            preset,
+            names,
+            references: BinningIndex::read_references(&mut data, reference_count, false)?,
+        }))
+    }
+
+    /// Write the index in `.tbi` format, BGZF compressed.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = BgzfWriter::new(writer);
+        writer.write_all(TABIX_MAGIC)?;
+        writer.write_all(&(self.0.names.len() as i32).to_le_bytes())?;
+        self.0.write_preset(&mut writer)?;
+        self.0.write_references(&mut writer, false)?;
+
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Get all chunks of the file that may contain records overlapping the 1-based, inclusive region
+    /// `start..=end` on the given chromosome. The chunks are sorted by their start offset.
+    pub fn chunks(&self, chromosome: &str, start: u32, end: u32) -> Vec<Chunk> {
+        self.0.chunks(chromosome, start, end)
+    }
+}
+
+/// A coordinate-sorted index (`.csi`) over a BGZF compressed VCF file. It generalizes the tabix index with a
+/// configurable binning scheme, supporting contigs longer than 2^29 bases.
+#[derive(Debug, PartialEq)]
+pub struct CsiIndex(BinningIndex);
+
+impl CsiIndex {
+    /// Load a `.csi` index file.
+    pub fn from_file(path: &str) -> io::Result<CsiIndex> {
+        Self::from_bytes(&read_compressed(path)?)
+    }
+
+    /// Parse a decompressed CSI index.
+    fn from_bytes(mut data: &[u8]) -> io::Result<CsiIndex> {
+        if read_magic(&mut data)? != *CSI_MAGIC {
+            return Err(invalid_index("missing CSI magic bytes"));
+        }
+
+        let scheme = BinningScheme {
+            min_shift: read_count(&mut data)? as u32,
+            depth: read_count(&mut data)? as u32,
+        };
+        if !scheme.is_valid() {
+            return Err(invalid_index("binning scheme exceeds 64 bit coordinates"));
+        }
+
+        let aux_length = read_count(&mut data)?;
+        if aux_length == 0 {
+            return Err(invalid_index("CSI index without sequence names is not supported"));
+        }
+        let mut aux = data.get(..aux_length).ok_or_else(|| invalid_index("truncated auxiliary data"))?;
+        let (preset, names) = BinningIndex::read_preset(&mut aux)?;
+        data = &data[aux_length..];
+
+        let reference_count = read_count(&mut data)?;
+        if names.len() != reference_count {
+            return Err(invalid_index("sequence name count does not match reference count"));
+        }
+
+        Ok(CsiIndex(BinningIndex {
+            scheme,
+            preset,
+            names,
+            references: BinningIndex::read_references(&mut data, reference_count, true)?,
+        }))
+    }
+
+    /// Write the index in `.csi` format, BGZF compressed.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = BgzfWriter::new(writer);
+        writer.write_all(CSI_MAGIC)?;
+        writer.write_all(&(self.0.scheme.min_shift as i32).to_le_bytes())?;
+        writer.write_all(&(self.0.scheme.depth as i32).to_le_bytes())?;
+
+        let aux_length = 4 * self.0.preset.len() + 4 + self.0.name_block_length();
+        writer.write_all(&(aux_length as i32).to_le_bytes())?;
+        self.0.write_preset(&mut writer)?;
+        writer.write_all(&(self.0.references.len() as i32).to_le_bytes())?;
+        self.0.write_references(&mut writer, true)?;
+
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Bit width of the smallest bins
+    pub fn min_shift(&self) -> u32 {
+        self.0.scheme.min_shift
+    }
+
+    /// Number of bin levels below the root bin
+    pub fn depth(&self) -> u32 {
+        self.0.scheme.depth
+    }
+
+    /// Get all chunks of the file that may contain records overlapping the 1-based, inclusive region
+    /// `start..=end` on the given chromosome. The chunks are sorted by their start offset.
+    pub fn chunks(&self, chromosome: &str, start: u32, end: u32) -> Vec<Chunk> {
+        self.0.chunks(chromosome, start, end)
+    }
+}
+
+/// An in-memory index over an uncompressed VCF file, storing the byte offset of every record. It is built by
+/// scanning the file once with [`crate::VcfFile::build_index`].
+#[derive(Debug, Default, PartialEq)]
+pub struct PositionIndex {
+    names: Vec<String>,
+    references: Vec<Vec<PositionEntry>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct PositionEntry {
+    position: u32,
+    // largest end position of this and all preceding records of the contig, which is non-decreasing and can
+    // therefore be binary searched
+    max_end: u32,
+    offset: u64,
+}
+
+impl PositionIndex {
+    /// Add a record spanning the 1-based, inclusive region `start..=end`, beginning at the given byte offset of the
+    /// file. Records must be added in coordinate-sorted order.
+    pub(crate) fn add(&mut self, chromosome: &str, start: u32, end: u32, offset: u64) -> io::Result<()> {
+        if self.names.last().map(String::as_str) != Some(chromosome) {
+            if self.names.iter().any(|name| name == chromosome) {
+                return Err(unsorted_input());
+            }
+            self.names.push(chromosome.into());
+            self.references.push(Vec::new());
+        }
+
+        let entries = self.references.last_mut().unwrap();
+        let max_end = match entries.last() {
+            Some(last) if start < last.position => return Err(unsorted_input()),
+            Some(last) => last.max_end.max(end),
+            None => end,
+        };
+        entries.push(PositionEntry {
+            position: start,
+            max_end,
+            offset,
+        });
+        Ok(())
+    }
+
+    /// Get the byte offset of the first record that may overlap the 1-based, inclusive region `start..=end` on the
+    /// given chromosome, or `None` if no record overlaps it.
+    pub fn offset(&self, chromosome: &str, start: u32, end: u32) -> Option<u64> {
+        let reference = self.names.iter().position(|name| name == chromosome)?;
+        let entries = &self.references[reference];
+        let first = entries.partition_point(|entry| entry.max_end < start);
+        entries.get(first).filter(|entry| entry.position <= end).map(|entry| entry.offset)
+    }
+}
+
+/// Any index supported by [`crate::VcfFile::query`].
+#[derive(Debug, PartialEq)]
+pub enum Index {
+    Tabix(TabixIndex),
+    Csi(CsiIndex),
+    Positions(PositionIndex),
+}
+
+impl Index {
+    /// Load a `.tbi` or `.csi` index file, detecting the format by its magic bytes.
+    pub fn from_file(path: &str) -> io::Result<Index> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a compressed `.tbi` or `.csi` index from any reader, e.g. a download.
+    pub fn from_reader<R: Read>(reader: R) -> io::Result<Index> {
+        let mut data = Vec::new();
+        MultiGzDecoder::new(reader).read_to_end(&mut data)?;
+        match data.get(..4) {
+            Some(magic) if magic == TABIX_MAGIC => TabixIndex::from_bytes(&data).map(Index::Tabix),
+            Some(magic) if magic == CSI_MAGIC => CsiIndex::from_bytes(&data).map(Index::Csi),
+            _ => Err(invalid_index("unknown index format")),
+        }
+    }
+
+    /// Names of all indexed sequences, in the order of the file
+    pub fn sequence_names(&self) -> &[String] {
+        match self {
+            Index::Tabix(index) => &index.0.names,
+            Index::Csi(index) => &index.0.names,
+            Index::Positions(index) => &index.names,
+        }
+    }
+
+    /// Get all chunks of the file that may contain records overlapping the 1-based, inclusive region
+    /// `start..=end` on the given chromosome. The chunks are sorted by their start offset. A [`PositionIndex`]
+    /// refers to an uncompressed file and has no chunks, use [`PositionIndex::offset`] instead.
+    pub fn chunks(&self, chromosome: &str, start: u32, end: u32) -> Vec<Chunk> {
+        match self {
+            Index::Tabix(index) => index.chunks(chromosome, start, end),
+            Index::Csi(index) => index.chunks(chromosome, start, end),
+            Index::Positions(_) => Vec::new(),
+        }
+    }
+}
+
+/// Incrementally builds a binning index while records are written to a BGZF file. Records must be added in
+/// coordinate-sorted order.
+struct BinningIndexBuilder {
+    scheme: BinningScheme,
+    names: Vec<String>,
+    references: Vec<ReferenceIndex>,
+    last_start: u32,
+}
+
+impl BinningIndexBuilder {
+    fn new(scheme: BinningScheme) -> Self {
+        BinningIndexBuilder {
+            scheme,
+            names: Vec::new(),
+            references: Vec::new(),
+            last_start: 0,
+        }
+    }
+
+    fn add(&mut self, chromosome: &str, start: u32, end: u32, chunk: Chunk) -> io::Result<()> {
+        if self.names.last().map(String::as_str) != Some(chromosome) {
+            if self.names.iter().any(|name| name == chromosome) {
+                return Err(unsorted_input());
+            }
+            self.names.push(chromosome.into());
+            self.references.push(ReferenceIndex::default());
+        } else if start < self.last_start {
+            return Err(unsorted_input());
+        }
+        self.last_start = start;
+
+        let begin = start.saturating_sub(1) as u64;
+        let end = (end as u64).max(begin + 1);
+        let reference = self.references.last_mut().unwrap();
+
+        let chunks = &mut reference
+            .bins
+            .entry(self.scheme.region_to_bin(begin, end))
+            .or_default()
+            .chunks;
+        match chunks.last_mut() {
+            Some(last) if last.end == chunk.begin => last.end = chunk.end,
+            _ => chunks.push(chunk),
+        }
+
+        let last_window = ((end - 1) >> self.scheme.min_shift) as usize;
+        if reference.linear.len() <= last_window {
+            reference.linear.resize(last_window + 1, VirtualOffset(0));
+        }
+        for offset in &mut reference.linear[(begin >> self.scheme.min_shift) as usize..=last_window] {
+            if offset.0 == 0 {
+                *offset = chunk.begin;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(mut self, csi: bool) -> BinningIndex {
+        for reference in &mut self.references {
+            // windows without records inherit the offset of the previous window
+            for i in 1..reference.linear.len() {
+                if reference.linear[i].0 == 0 {
+                    reference.linear[i] = reference.linear[i - 1];
+                }
+            }
+
+            // CSI indices replace the linear index with the offset at the start of each bin
+            if csi {
+                for (&number, bin) in reference.bins.iter_mut() {
+                    let window = (self.scheme.bin_start(number) >> self.scheme.min_shift) as usize;
+                    bin.loffset = reference
+                        .linear
+                        .get(window)
+                        .copied()
+                        .unwrap_or(bin.chunks[0].begin)
+                        .min(bin.chunks[0].begin);
+                }
+                reference.linear.clear();
+            }
+        }
+
+        BinningIndex {
+            scheme: self.scheme,
+            preset: VCF_PRESET,
+            names: self.names,
+            references: self.references,
+        }
+    }
+}
+
+/// Incrementally builds a [`TabixIndex`] while records are written to a BGZF file. Records must be added in
+/// coordinate-sorted order.
+pub struct TabixIndexBuilder(BinningIndexBuilder);
+
+impl Default for TabixIndexBuilder {
+    fn default() -> Self {
+        TabixIndexBuilder(BinningIndexBuilder::new(TABIX_SCHEME))
+    }
+}
+
+impl TabixIndexBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a record covering the 1-based, inclusive region `start..=end` on a chromosome, that has been
+    /// written to the range `chunk` of the BGZF file.
+    pub fn add(&mut self, chromosome: &str, start: u32, end: u32, chunk: Chunk) -> io::Result<()> {
+        self.0.add(chromosome, start, end, chunk)
+    }
+
+    pub fn finish(self) -> TabixIndex {
+        TabixIndex(self.0.finish(false))
+    }
+}
+
+/// Incrementally builds a [`CsiIndex`] while records are written to a BGZF file. Records must be added in
+/// coordinate-sorted order.
+pub struct CsiIndexBuilder(BinningIndexBuilder);
+
+impl CsiIndexBuilder {
+    /// Create a builder for an index whose smallest bins span `2^min_shift` bases, with `depth` levels of bins
+    /// below the root. htslib defaults to a min-shift of 14 and a depth of 5. Fails with
+    /// [`io::ErrorKind::InvalidInput`] if the root bin would span more than `2^63` bases.
+    pub fn new(min_shift: u32, depth: u32) -> io::Result<Self> {
+        let scheme = BinningScheme { min_shift, depth };
+        if !scheme.is_valid() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "binning scheme exceeds 64 bit coordinates"));
+        }
+        Ok(CsiIndexBuilder(BinningIndexBuilder::new(scheme)))
+    }
+
+    /// Register a record covering the 1-based, inclusive region `start..=end` on a chromosome, that has been
+    /// written to the range `chunk` of the BGZF file.
+    pub fn add(&mut self, chromosome: &str, start: u32, end: u32, chunk: Chunk) -> io::Result<()> {
+        self.0.add(chromosome, start, end, chunk)
+    }
+
+    pub fn finish(self) -> CsiIndex {
+        CsiIndex(self.0.finish(true))
+    }
+}
+
+fn read_compressed(path: &str) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    MultiGzDecoder::new(BufReader::new(File::open(path)?)).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn invalid_index(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("malformed index: {}", message))
+}
+
+fn unsorted_input() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "records must be indexed in coordinate-sorted order")
+}
+
+fn read_magic(reader: &mut &[u8]) -> io::Result<[u8; 4]> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_i32(reader: &mut &[u8]) -> io::Result<i32> {
+    read_magic(reader).map(i32::from_le_bytes)
+}
+
+fn read_u32(reader: &mut &[u8]) -> io::Result<u32> {
+    read_magic(reader).map(u32::from_le_bytes)
+}
+
+fn read_u64(reader: &mut &[u8]) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_count(reader: &mut &[u8]) -> io::Result<usize> {
+    usize::try_from(read_i32(reader)?).map_err(|_| invalid_index("negative count"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(begin: u64, end: u64) -> Chunk {
+        Chunk {
+            begin: VirtualOffset(begin),
+            end: VirtualOffset(end),
+        }
+    }
+
+    #[test]
+    fn test_bins() {
+        assert_eq!(TABIX_SCHEME.region_to_bin(0, 1), 4681);
+        assert_eq!(TABIX_SCHEME.region_to_bin(16383, 16385), 585);
+        assert_eq!(TABIX_SCHEME.region_to_bin(0, 1 << 29), 0);
+        assert!(TABIX_SCHEME.region_to_bins(100, 200).contains(&TABIX_SCHEME.region_to_bin(100, 200)));
+        assert_eq!(TABIX_SCHEME.bin_start(4681 + 3), 3 << 14);
+        assert_eq!(TABIX_SCHEME.bin_start(585 + 1), 1 << 17);
+
+        // a deeper CSI scheme can address positions beyond 2^29
+        let scheme = BinningScheme { min_shift: 14, depth: 6 };
+        let bin = scheme.region_to_bin(1 << 30, (1 << 30) + 1);
+        assert!(scheme.region_to_bins(1 << 30, (1 << 30) + 10).contains(&bin));
+        assert_eq!(scheme.bin_start(bin), 1 << 30);
+    }
+
+    #[test]
+    fn test_index_round_trip() {
+        let mut builder = TabixIndexBuilder::new();
+        builder.add("1", 100, 100, chunk(10, 20)).unwrap();
+        builder.add("1", 200, 300, chunk(20, 30)).unwrap();
+        builder.add("2", 50, 50, chunk(30, 40)).unwrap();
+        assert!(builder.add("1", 400, 400, chunk(40, 50)).is_err());
+        let index = builder.finish();
+
+        let mut data = Vec::new();
+        index.write(&mut data).unwrap();
+        let mut decompressed = Vec::new();
+        MultiGzDecoder::new(data.as_slice()).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(TabixIndex::from_bytes(&decompressed).unwrap(), index);
+
+        // both records of chromosome 1 are in the same bin and stored as one merged chunk
+        assert_eq!(index.chunks("1", 250, 260), vec![chunk(10, 30)]);
+        assert!(index.chunks("3", 1, 100).is_empty());
+    }
+
+    #[test]
+    fn test_position_index() {
+        let mut index = PositionIndex::default();
+        index.add("1", 100, 100, 10).unwrap();
+        index.add("1", 150, 400, 20).unwrap();
+        index.add("1", 200, 200, 30).unwrap();
+        index.add("2", 50, 50, 40).unwrap();
+        assert!(index.add("2", 10, 10, 50).is_err());
+        assert!(index.add("1", 500, 500, 50).is_err());
+
+        assert_eq!(index.offset("1", 1, 100), Some(10));
+        assert_eq!(index.offset("1", 101, 120), None);
+        assert_eq!(index.offset("1", 101, 150), Some(20));
+        // the deletion starting at 150 overlaps the region and is read first
+        assert_eq!(index.offset("1", 300, 300), Some(20));
+        assert_eq!(index.offset("1", 401, 500), None);
+        assert_eq!(index.offset("2", 1, 49), None);
+        assert_eq!(index.offset("3", 1, 100), None);
+    }
+
+    #[test]
+    fn test_csi_round_trip() {
+        assert_eq!(CsiIndexBuilder::new(14, 17).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidInput));
+        assert!(CsiIndexBuilder::new(0, u32::MAX).is_err());
+        let mut builder = CsiIndexBuilder::new(12, 6).unwrap();
+        builder.add("1", 100, 100, chunk(10, 20)).unwrap();
+        builder.add("1", 50_000, 50_000, chunk(20, 30)).unwrap();
+        let index = builder.finish();
+        assert_eq!((index.min_shift(), index.depth()), (12, 6));
+
+        let mut data = Vec::new();
+        index.write(&mut data).unwrap();
+        let mut decompressed = Vec::new();
+        MultiGzDecoder::new(data.as_slice()).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(CsiIndex::from_bytes(&decompressed).unwrap(), index);
+
+        assert_eq!(index.chunks("1", 40_000, 60_000), vec![chunk(20, 30)]);
+        assert_eq!(index.chunks("1", 1, 60_000), vec![chunk(10, 20), chunk(20, 30)]);
+    }
+}
@@ -0,0 +1,156 @@
+use std::cell::OnceCell;
+use std::io;
+
+use crate::{
+    InfoEntry, ParseOptions, RecordIterator, SampleInfo, VcfFile, VcfHeader, VcfParseError, VcfRecord, VcfRecordRef,
+};
+
+/// A record of which only CHROM, POS and QUAL are parsed up front. The INFO column and the sample columns are kept
+/// in the raw line, and are only parsed when they are first accessed, so scans that only look at coordinates and
+/// qualities skip most of the parsing work. Unlike [`VcfRecordRef`], the record owns its line, so it can be kept.
+///
+/// Strict validation is not applied, use [`LazyRecord::to_record`] to get a fully parsed and validated record.
+pub struct LazyRecord<'a> {
+    pub chromosome: String,
+    pub position: u32,
+    pub quality: Option<f32>,
+
+    line: String,
+    header: &'a VcfHeader,
+    options: &'a ParseOptions,
+    info: OnceCell<Vec<Option<InfoEntry>>>,
+    sample_info: OnceCell<Option<SampleInfo>>,
+}
+
+/// Iterator over the records of a [`VcfFile`] as [`LazyRecord`]s.
+pub struct LazyRecords<'a> {
+    records: RecordIterator<'a>,
+}
+
+#[cfg(test)]
+thread_local! {
+    // number of INFO columns parsed by lazy records, to test that parsing is deferred
+    static INFO_PARSES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+impl VcfFile {
+    /// Open the VCF file and get a lazy iterator over all records, which defer parsing INFO and sample columns
+    /// until they are accessed.
+    pub fn lazy_records(&self) -> io::Result<LazyRecords<'_>> {
+        Ok(LazyRecords {
+            records: self.records()?,
+        })
+    }
+}
+
+impl<'a> Iterator for LazyRecords<'a> {
+    type Item = io::Result<LazyRecord<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.records.read_line() {
+            Ok(true) => Some(
+                LazyRecord::parse(self.records.buffer.clone(), self.records.header, self.records.options)
+                    .map_err(|e| self.records.located(e, self.records.buffer.as_bytes())),
+            ),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'a> LazyRecord<'a> {
+    fn parse(
+        line: String,
+        header: &'a VcfHeader,
+        options: &'a ParseOptions,
+    ) -> Result<LazyRecord<'a>, VcfParseError> {
+        // fails for missing columns and a malformed POS, like parsing an owned record
+        let raw = VcfRecordRef::parse(&line, header, options)?;
+
+        Ok(LazyRecord {
+            chromosome: options.normalize_contig(raw.chromosome),
+            position: raw.position,
+            quality: raw.quality,
+            line,
+            header,
+            options,
+            info: OnceCell::new(),
+            sample_info: OnceCell::new(),
+        })
+    }
+
+    /// Get the entries of the INFO column, which are parsed on the first call.
+    pub fn get_info(&self) -> &[Option<InfoEntry>] {
+        self.info.get_or_init(|| {
+            #[cfg(test)]
+            INFO_PARSES.with(|count| count.set(count.get() + 1));
+
+            // malformed entries are only rejected in strict mode, which lazy records don't apply
+            let raw = self.raw();
+            VcfRecord::parse_info(self.options.missing(raw.info), raw.alternate_alleles().count(), self.header, false)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Get the FORMAT keys and sample columns, which are split on the first call. Sites-only records have none.
+    pub fn sample_info(&self) -> Option<&SampleInfo> {
+        self.sample_info
+            .get_or_init(|| {
+                let raw = self.raw();
+                Some(SampleInfo::new(raw.format?, raw.samples?))
+            })
+            .as_ref()
+    }
+
+    /// The raw line of the record, without line terminator.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Parse the whole line into an owned record, applying the parse options of the file.
+    pub fn to_record(&self) -> Result<VcfRecord, VcfParseError> {
+        VcfRecord::parse(&self.line, self.header, self.options)
+    }
+
+    fn raw(&self) -> VcfRecordRef<'_> {
+        VcfRecordRef::parse(&self.line, self.header, self.options).expect("lazy records are checked when they are read")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_records() {
+        let vcf_file = VcfFile::parse("tests/data/allele_frequency.vcf", false).expect("failed to open VCF file");
+        let owned = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+
+        let before = INFO_PARSES.with(|count| count.get());
+        let records = vcf_file.lazy_records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        let positions = records.iter().map(|rec| rec.position).collect::<Vec<_>>();
+        assert_eq!(positions, owned.iter().map(|rec| rec.position).collect::<Vec<_>>());
+        assert_eq!(INFO_PARSES.with(|count| count.get()), before);
+
+        // INFO is parsed once on first access, and memoized afterwards
+        assert_eq!(records[2].get_info(), owned[2].info.as_slice());
+        assert_eq!(records[2].get_info().len(), 1);
+        assert_eq!(INFO_PARSES.with(|count| count.get()), before + 1);
+
+        let samples = records[0].sample_info().unwrap();
+        assert_eq!(samples.format, vec!["GT"]);
+        assert_eq!(samples.samples().count(), 4);
+        assert_eq!(format!("{:?}", records[4].to_record().unwrap()), format!("{:?}", owned[4]));
+    }
+
+    #[test]
+    fn test_malformed_lazy_records() {
+        let vcf_file = VcfFile::parse("tests/data/malformed_records.vcf", false).expect("failed to open VCF file");
+        let records = vcf_file.lazy_records().unwrap().collect::<Vec<_>>();
+        let positions = records.iter().map(|rec| rec.as_ref().map(|rec| rec.position).ok()).collect::<Vec<_>>();
+        assert_eq!(positions, vec![Some(100), None, Some(300), Some(400), None]);
+
+        let error = records[1].as_ref().err().and_then(|e| e.get_ref()?.downcast_ref::<VcfParseError>());
+        assert_eq!(error.map(VcfParseError::cause), Some(&VcfParseError::MalformedPosition("2x0".into())));
+    }
+}
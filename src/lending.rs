@@ -0,0 +1,212 @@
+use std::io;
+use std::ops::ControlFlow;
+
+use crate::{ParseOptions, RecordIterator, VcfFile, VcfHeader, VcfParseError, VcfRecord};
+
+/// A record borrowed from the line buffer of a [`LendingRecords`] iterator. Only POS and QUAL are parsed, all other
+/// columns are raw slices of the line, so creating it never allocates. Neither contig normalization nor strict
+/// validation is applied, use [`VcfRecordRef::to_record`] to get a fully parsed record.
+pub struct VcfRecordRef<'a> {
+    pub chromosome: &'a str,
+    pub position: u32,
+    /// Raw ID column, `.` if the record has no identifiers
+    pub id: &'a str,
+    pub reference_bases: &'a str,
+    /// Raw, comma-separated ALT column
+    pub alternate_bases: &'a str,
+    pub quality: Option<f32>,
+    pub filter_status: &'a str,
+    /// Raw, semicolon-separated INFO column
+    pub info: &'a str,
+    /// Raw FORMAT column, if the file has samples
+    pub format: Option<&'a str>,
+    /// Raw, tab-separated sample columns, if the file has samples
+    pub samples: Option<&'a str>,
+
+    line: &'a str,
+    header: &'a VcfHeader,
+    options: &'a ParseOptions,
+}
+
+/// Iterator over the records of a [`VcfFile`] that lends each record from its internal line buffer, instead of
+/// allocating an owned [`VcfRecord`]. It can't implement [`Iterator`], because each record borrows from the
+/// iterator itself, so use `while let Some(record) = records.next()` instead.
+pub struct LendingRecords<'a> {
+    records: RecordIterator<'a>,
+}
+
+impl VcfFile {
+    /// Open the VCF file and get a lazy iterator lending borrowed views of all records. This is the fastest way to
+    /// read records, since the line buffer is reused and no record allocates.
+    pub fn lending_records(&self) -> io::Result<LendingRecords<'_>> {
+        Ok(LendingRecords {
+            records: self.records()?,
+        })
+    }
+
+    /// Call the closure with a borrowed view of each record until it returns [`ControlFlow::Break`]. Like
+    /// [`VcfFile::lending_records`], the line buffer is reused and no record allocates.
+    pub fn for_each_record<F: FnMut(&VcfRecordRef) -> ControlFlow<()>>(&self, mut f: F) -> io::Result<()> {
+        let mut records = self.lending_records()?;
+        while let Some(record) = records.next() {
+            if f(&record?).is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> LendingRecords<'a> {
+    /// Get the next record. Records with missing columns or a malformed POS are reported as errors of kind
+    /// [`io::ErrorKind::InvalidData`], as in strict mode.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<io::Result<VcfRecordRef<'_>>> {
+        match self.records.read_line() {
+            Ok(true) => Some(
+                VcfRecordRef::parse(&self.records.buffer, self.records.header, self.records.options)
+                    .map_err(|e| self.records.located(e, self.records.buffer.as_bytes())),
+            ),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'a> VcfRecordRef<'a> {
+    /// Split a line into its columns and parse POS and QUAL. Fails for lines with missing columns or a malformed POS.
+    pub(crate) fn parse(
+        line: &'a str,
+        header: &'a VcfHeader,
+        options: &'a ParseOptions,
+    ) -> Result<VcfRecordRef<'a>, VcfParseError> {
+        VcfRecord::check_columns(line, header)?;
+        let fields_without_samples = 8 + header.has_end_column as usize + header.sample_names.is_some() as usize;
+        let mut fields = line.splitn(fields_without_samples + 1, '\t');
+
+        // all columns are present, as checked above
+        let [chromosome, position, id, reference_bases, alternate_bases, quality, filter_status, info] =
+            [(); 8].map(|_| fields.next().unwrap_or_default());
+        let position = VcfRecord::parse_position(position)?;
+        let quality = VcfRecord::parse_quality(options.missing(quality)).ok().flatten();
+        if header.has_end_column {
+            fields.next();
+        }
+        let (format, samples) = if header.sample_names.is_some() {
+            // a FORMAT column without any samples is followed by no sample column
+            (fields.next(), Some(fields.next().unwrap_or_default()))
+        } else {
+            (None, None)
+        };
+
+        Ok(VcfRecordRef {
+            chromosome,
+            position,
+            id,
+            reference_bases,
+            alternate_bases,
+            quality,
+            filter_status,
+            info,
+            format,
+            samples,
+            line,
+            header,
+            options,
+        })
+    }
+
+    /// Iterate over the ALT alleles, where None is a missing allele (`.`).
+    pub fn alternate_alleles(&self) -> impl Iterator<Item=Option<&'a str>> {
+        self.alternate_bases.split(',').map(|allele| match allele {
+            "." => None,
+            allele => Some(allele),
+        })
+    }
+
+    /// Iterate over the raw `KEY=VALUE` and flag entries of the INFO column.
+    pub fn info_entries(&self) -> impl Iterator<Item=&'a str> {
+        let info = if self.info == "." { "" } else { self.info };
+        info.split(';').filter(|entry| !entry.is_empty())
+    }
+
+    /// Parse the whole line into an owned record, applying the parse options of the file.
+    pub fn to_record(&self) -> Result<VcfRecord, VcfParseError> {
+        VcfRecord::parse(self.line, self.header, self.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lending_records() {
+        let vcf_file = VcfFile::parse("tests/data/sample_fields.vcf", false).expect("failed to open VCF file");
+        let owned = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+
+        let mut records = vcf_file.lending_records().unwrap();
+        let mut positions = [0; 2];
+        let mut count = 0;
+        while let Some(record) = records.next() {
+            let record = record.unwrap();
+            positions[count] = record.position;
+            assert_eq!(record.chromosome, owned[count].chromosome);
+            assert_eq!(record.alternate_alleles().next().unwrap(), owned[count].alternate_bases[0].as_deref());
+            assert_eq!(record.info_entries().count(), 0);
+            count += 1;
+        }
+        assert_eq!(positions, [100, 200]);
+
+        let mut records = vcf_file.lending_records().unwrap();
+        let record = records.next().unwrap().unwrap();
+        assert_eq!(record.format, Some("GT:DP:GQ:HQ"));
+        assert_eq!(format!("{:?}", record.to_record().unwrap()), format!("{:?}", owned[0]));
+    }
+
+    #[test]
+    fn test_malformed_lending_records() {
+        let vcf_file = VcfFile::parse("tests/data/malformed_records.vcf", false).expect("failed to open VCF file");
+        let mut records = vcf_file.lending_records().unwrap();
+        let mut results = Vec::new();
+        while let Some(record) = records.next() {
+            results.push(record.map(|record| record.position).map_err(|e| e.kind()));
+        }
+        // the malformed POS and the truncated last line are errors instead of panics
+        let invalid = Err(io::ErrorKind::InvalidData);
+        assert_eq!(results, vec![Ok(100), invalid, Ok(300), Ok(400), invalid]);
+
+        let header = &vcf_file.header;
+        let options = ParseOptions::default();
+        let error = VcfRecordRef::parse("1\t2x0\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\t0/1", header, &options).err();
+        assert_eq!(error, Some(VcfParseError::MalformedPosition("2x0".into())));
+        let truncated = VcfRecordRef::parse("1\t100\t.\tA", header, &options).err();
+        assert!(matches!(truncated, Some(VcfParseError::MissingColumns { found: 4, .. })));
+    }
+
+    #[test]
+    fn test_for_each_record() {
+        let vcf_file = VcfFile::parse("tests/data/quality.vcf", false).expect("failed to open VCF file");
+        let mut sum = 0;
+        vcf_file
+            .for_each_record(|record| {
+                sum += record.position;
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(sum, 2100);
+
+        let mut visited = Vec::new();
+        vcf_file
+            .for_each_record(|record| {
+                visited.push(record.position);
+                if record.position >= 300 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        assert_eq!(visited, vec![100, 200, 300]);
+    }
+}
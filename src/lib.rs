@@ -2,16 +2,96 @@
 #![feature(slice_internals)]
 
 use core::slice::memchr::memchr;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::rc::Rc;
+use std::sync::{Mutex, OnceLock, PoisonError};
 
 use flate2::read::MultiGzDecoder;
 
+use validate::SortCheck;
+
+pub use bed::BedIntersection;
+pub use bgzf::{BgzfWriter, VirtualOffset};
+pub use bytes::{ByteRecord, ByteRecords};
+pub use concordance::Concordance;
+pub use error::VcfParseError;
+pub use filter::{
+    AlleleFrequencySource, GenomicWindows, MinAlleleFrequency, MinCarriers, RecordFilters, SplitReferenceBlocks,
+    WindowKey, WithInfoFlag,
+};
+pub use genotype::{parse_genotype, parse_genotype_strict, Genotype};
+pub use header::{ContigDefinition, InfoDefinition, Number, PedigreeDefinition, SampleDefinition, VcfHeaderBuilder};
+pub use index::{Chunk, CsiIndex, CsiIndexBuilder, Index, PositionIndex, TabixIndex, TabixIndexBuilder};
+pub use lazy::{LazyRecord, LazyRecords};
+pub use lending::{LendingRecords, VcfRecordRef};
+pub use matrix::{GenotypeMatrix, PackedDosages};
+#[cfg(feature = "mmap")]
+pub use mmap::MappedVcf;
+pub use order::{ContigOrder, OrderedRecord};
+pub use reader::VcfReader;
+pub use region::parse_region;
+#[cfg(feature = "remote")]
+pub use remote::RemoteVcf;
+pub use stats::{AlleleFrequencySpectrum, CoverageTracker, QualSummary};
+pub use validate::{IssueKind, ValidationIssue};
+pub use writer::VcfWriter;
+
+mod bed;
+mod bgzf;
+mod bytes;
+mod concordance;
+mod error;
+mod filter;
+mod genotype;
+mod header;
+mod ids;
+mod index;
+mod lazy;
+mod lending;
+mod matrix;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod order;
+mod reader;
+mod region;
+#[cfg(feature = "remote")]
+mod remote;
+mod stats;
+mod validate;
+mod writer;
+
 pub struct VcfFile {
-    path: String,
+    source: Source,
     compressed: bool,
     pub header: VcfHeader,
+    index: Option<Index>,
+    // checkpoints of the records by ID, see `VcfFile::build_id_index`
+    id_index: OnceLock<HashMap<String, Checkpoint>>,
+    options: ParseOptions,
+}
+
+/// Where the data of a [`VcfFile`] comes from. Files are reopened for every iteration, while other sources like
+/// pipes are streamed, so their records can only be read once. Seekable sources that should be iterated multiple
+/// times are read by a [`VcfReader`] instead.
+enum Source {
+    Path(String),
+    // the data following the header, taken by the first iteration
+    Reader(Mutex<Option<Box<dyn BufRead + Send>>>),
+}
+
+/// Options controlling how records are parsed.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    strict: bool,
+    contig_normalizer: Option<fn(&str) -> String>,
+    loose_contig_matching: bool,
+    buffer_size: Option<usize>,
+    missing_values: Vec<String>,
 }
 
 pub struct VcfHeader {
@@ -19,119 +99,640 @@ pub struct VcfHeader {
     pub has_end_column: bool,
     pub sample_names: Option<Vec<String>>,
     pub values: Vec<(String, String)>,
+    pub contigs: Vec<ContigDefinition>,
+    pub info_definitions: Vec<InfoDefinition>,
+    pub pedigrees: Vec<PedigreeDefinition>,
+    pub sample_definitions: Vec<SampleDefinition>,
 
     // size of the entire header in bytes
     size: usize,
+    // number of lines of the header
+    lines: u64,
+    // index of each sample name, built on the first lookup
+    sample_indices: OnceLock<HashMap<String, usize>>,
+    // index of each contig in declaration order
+    contig_indices: HashMap<String, usize>,
 }
 
-#[derive(Debug)]
+/// A single entry of the INFO column. Reserved keys of the VCF specification are parsed into their
+/// dedicated variants, all other keys are kept as `NonStandard` entries.
+#[derive(Debug, Clone, PartialEq)]
 pub enum InfoEntry {
-    AncestralAllele(String),
+    /// `AA`: the ancestral allele, normalized to uppercase since lowercase bases only mark low-confidence calls.
+    /// `None` if the ancestral allele is unknown (`.`), while `-` marks an allele that was absent in the ancestor.
+    AncestralAllele(Option<String>),
     AlleleCount(Vec<u32>),
+    /// `AD`: total read depth for each allele, including the reference allele (Number=R)
     TotalAlleleReadDepth(Vec<u32>),
+    /// `ADF`: read depth for each allele on the forward strand, including the reference allele (Number=R)
     ForwardAlleleReadDepth(Vec<u32>),
+    /// `ADR`: read depth for each allele on the reverse strand, including the reference allele (Number=R)
     ReverseAlleleReadDepth(Vec<u32>),
+    /// `AF`: frequency of each alternate allele (Number=A)
     AlleleFrequency(Vec<f32>),
     AlleleNumber(u32),
     RmsBaseQuality(f32),
     Cigar(Vec<String>),
+    /// `DB`: flag for membership in dbSNP
     SNPDatabaseMembership,
     CombinedDepth(u32),
+    /// `END`: end position of the longest variant described by the record, used for symbolic alleles and gVCF
+    /// reference blocks
     End(u32),
+    /// `H2`: flag for membership in HapMap2
     HapMap2,
+    /// `H3`: flag for membership in HapMap3
     HapMap3,
     RmsMappingQuality(f32),
     MapQReads(u32),
     SamplesWithData(u32),
     StrandBias(u32, u32, u32, u32),
+    /// `SOMATIC`: flag for somatic mutations, in cancer genomics
     Somatic,
+    /// `VALIDATED`: flag for variants validated by follow-up experiments
     Validated,
+    /// `1000G`: flag for membership in the 1000 Genomes project
     Flag1000G,
-    NonStandard(NonStandardInfoValue),
+    /// `SVTYPE`: type of a structural variant, e.g. `DEL`, `INS` or `BND`
+    StructuralVariantType(String),
+    /// `SVLEN`: signed length difference between each alternate allele and the reference (Number=A)
+    StructuralVariantLength(Vec<i32>),
+    /// `CIPOS`: confidence interval around POS for imprecise variants, as offsets relative to POS
+    PositionConfidenceInterval(i32, i32),
+    /// `CIEND`: confidence interval around END for imprecise variants, as offsets relative to END
+    EndConfidenceInterval(i32, i32),
+    /// Any key not reserved by the specification (e.g. samtools' `DP4`), together with its raw value(s)
+    NonStandard(String, NonStandardInfoValue),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum NonStandardInfoValue {
     NoValue,
     SingleValue(String),
     ValueList(Vec<String>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct VcfRecord {
     pub chromosome: String,
     pub position: u32,
-    pub id: Option<Vec<String>>,
+    pub id: Option<RecordIds>,
     pub reference_bases: String,
     pub alternate_bases: Vec<Option<String>>,
     pub quality: Option<f32>,
-    pub filter_status: String,
+    /// Raw FILTER column. The common values `PASS` and `.` are borrowed from static strings instead of allocated
+    pub filter_status: Cow<'static, str>,
     pub info: Vec<Option<InfoEntry>>,
+    /// END column, if the header declares one. It may disagree with the `END` INFO entry, see
+    /// [`VcfRecord::conflicting_end`]
     pub end: Option<u32>,
     pub sample_info: Option<SampleInfo>,
 }
 
-#[derive(Debug)]
+/// The identifiers of a record from its ID column. Most annotated records carry exactly one identifier, which is
+/// stored without the allocation of a list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordIds {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
 pub struct SampleInfo {
     pub format: Vec<String>,
     unparsed_info: String,
 }
 
-#[derive(Debug)]
+/// The data of a single sample of a record. It borrows the FORMAT keys of its record, so fields can be resolved by
+/// name without copying any data.
 pub struct Sample<'a> {
+    format: &'a [String],
     unparsed_info: &'a str,
 }
 
-struct SampleIterator<'a> {
-    reader: Box<dyn BufRead>,
+/// Lazy iterator over the records of a VCF file.
+pub struct RecordIterator<'a> {
+    reader: Box<dyn BufRead + 'a>,
     header: &'a VcfHeader,
+    options: &'a ParseOptions,
     buffer: String,
+
+    // decompressed byte offset of the next line, and number of lines read so far
+    offset: u64,
+    line: u64,
+}
+
+/// Iterator over all records of a [`VcfFile`] created by `for record in &vcf_file`. If the file cannot be opened,
+/// the error is yielded as the only item.
+pub struct IntoRecords<'a>(Result<RecordIterator<'a>, Option<io::Error>>);
+
+/// A snapshot of the position of a [`RecordIterator`], from which iteration can be resumed with
+/// [`VcfFile::records_from`]. For compressed files, the offset refers to the decompressed data, so resuming has to
+/// decompress (but not parse) everything before the checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Byte offset of the next record, relative to the start of the (decompressed) file
+    pub offset: u64,
+    /// Number of lines preceding the next record, including the header
+    pub line: u64,
+}
+
+/// Iterator over the records of [`VcfFile::query_regions`]. For each chromosome, the regions are sorted by start,
+/// together with the index of the first region that may still overlap a record.
+struct MultiRegionIterator<'a> {
+    records: RecordIterator<'a>,
+    contigs: HashMap<String, (Vec<(u32, u32)>, usize)>,
+    // number of regions that the scan didn't pass yet
+    remaining: usize,
+}
+
+/// Iterator over every nth record, created by [`RecordIterator::step_by_record`].
+pub struct SteppedRecords<'a> {
+    records: RecordIterator<'a>,
+    step: usize,
+    first_taken: bool,
+}
+
+/// Iterator over the records of a region query. Reads sequentially from the first candidate chunk, skipping
+/// records that don't overlap the region, until it passes the end of the region.
+struct RegionIterator<'a> {
+    records: Option<RecordIterator<'a>>,
+    chromosome: String,
+    start: u32,
+    end: u32,
+}
+
+/// Iterator over the chromosomes of a sorted file created by [`VcfFile::split_by_chromosome`]. All groups read
+/// from the same underlying iterator, so advancing to the next chromosome skips the unread records of the previous
+/// group.
+pub struct ChromosomeGroups<'a> {
+    records: Rc<RefCell<GroupedRecords<'a>>>,
+}
+
+/// The records of a single chromosome. It ends as soon as the next chromosome begins, or when
+/// [`ChromosomeGroups`] was advanced past this chromosome.
+pub struct ChromosomeRecords<'a> {
+    records: Rc<RefCell<GroupedRecords<'a>>>,
+    chromosome: String,
+}
+
+// state shared by a ChromosomeGroups and its groups: the first record of the next group is read by the previous
+// group, so it is kept until the next group starts
+struct GroupedRecords<'a> {
+    records: RecordIterator<'a>,
+    peeked: Option<io::Result<VcfRecord>>,
+    chromosome: Option<String>,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// In strict mode, records are validated against the header and violations are reported as
+    /// [`VcfParseError`]. By default, the parser trusts its input.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Apply a function to the CHROM column of every record, e.g. to strip `chr` prefixes. The contig IDs declared
+    /// in the header are normalized the same way, and region queries expect normalized chromosome names.
+    pub fn contig_normalizer(mut self, normalizer: fn(&str) -> String) -> Self {
+        self.contig_normalizer = Some(normalizer);
+        self
+    }
+
+    /// Match the chromosome of region queries case-insensitively and regardless of a `chr` prefix, so that querying
+    /// `1` finds the records of `chr1` or `Chr1`. By default, the chromosome must match exactly.
+    pub fn loose_contig_matching(mut self, loose: bool) -> Self {
+        self.loose_contig_matching = loose;
+        self
+    }
+
+    /// Set the capacity of the read buffer and preallocate the line buffer with the same size. Larger buffers need
+    /// fewer read calls, which helps on network filesystems. By default, 8 KiB are read at once.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = Some(size);
+        self
+    }
+
+    /// Treat the given tokens like the missing value `.` in the ID, ALT, QUAL and INFO columns, e.g. `-` or `NA`
+    /// written by tools that don't follow the specification. ALT alleles are compared one by one, the other columns
+    /// as a whole.
+    pub fn missing_values(mut self, tokens: &[&str]) -> Self {
+        self.missing_values = tokens.iter().map(|token| token.to_string()).collect();
+        self
+    }
+
+    /// Replace a column or allele by `.` if it is one of the configured missing values.
+    pub(crate) fn missing<'a>(&self, field: &'a str) -> &'a str {
+        if self.missing_values.iter().any(|token| token == field) {
+            "."
+        } else {
+            field
+        }
+    }
+
+    pub(crate) fn read_capacity(&self) -> usize {
+        // an empty read buffer would report the end of the file immediately
+        self.buffer_size.unwrap_or(8 * 1024).max(1)
+    }
+
+    pub(crate) fn line_capacity(&self) -> usize {
+        self.buffer_size.unwrap_or(1024)
+    }
+
+    fn normalize_contig(&self, chromosome: &str) -> String {
+        match self.contig_normalizer {
+            Some(normalizer) => normalizer(chromosome),
+            None => chromosome.into(),
+        }
+    }
+
+    /// Find the name under which a normalized chromosome is stored in the index, which stores chromosome names as
+    /// they appear in the file.
+    pub(crate) fn indexed_chromosome<'a>(&self, index: &'a Index, chromosome: &'a str) -> &'a str {
+        let names = index.sequence_names();
+        let exact = match self.contig_normalizer {
+            Some(normalizer) => names.iter().find(|name| normalizer(name) == chromosome),
+            None => names.iter().find(|name| *name == chromosome),
+        };
+
+        match exact {
+            Some(name) => name,
+            None if self.loose_contig_matching => names
+                .iter()
+                .find(|name| self.contig_matches(&self.normalize_contig(name), chromosome))
+                .map_or(chromosome, String::as_str),
+            None => chromosome,
+        }
+    }
+
+    /// Whether the chromosome of a record matches the chromosome of a region query.
+    pub(crate) fn contig_matches(&self, chromosome: &str, queried: &str) -> bool {
+        let loose_match = || strip_chr_prefix(chromosome).eq_ignore_ascii_case(strip_chr_prefix(queried));
+        chromosome == queried || self.loose_contig_matching && loose_match()
+    }
+
+    /// Normalize the contig IDs declared in the header, so they match the normalized CHROM column of records.
+    pub(crate) fn normalize_header(&self, header: &mut VcfHeader) {
+        if self.contig_normalizer.is_some() {
+            for contig in &mut header.contigs {
+                contig.id = self.normalize_contig(&contig.id);
+            }
+            header.contig_indices = header::index_contigs(&header.contigs);
+        }
+    }
 }
 
 impl VcfFile {
     /// Parse the header of a VCF file. The file handle will be closed after the header is parsed.
     /// Accessing records will open new file handles.
     pub fn parse(path: &str, compressed: bool) -> io::Result<VcfFile> {
-        let header = if compressed {
+        Self::parse_with_options(path, compressed, ParseOptions::default())
+    }
+
+    /// Parse the header of a VCF file like [`VcfFile::parse`], and use the given options for parsing records.
+    pub fn parse_with_options(path: &str, compressed: bool, options: ParseOptions) -> io::Result<VcfFile> {
+        let mut header = if compressed {
             Self::parse_header(&mut BufReader::new(MultiGzDecoder::new(File::open(path)?)))?
         } else {
             Self::parse_header(&mut BufReader::new(File::open(path)?))?
         };
+        options.normalize_header(&mut header);
+
+        Ok(VcfFile {
+            source: Source::Path(String::from(path)),
+            compressed,
+            header,
+            index: None,
+            id_index: OnceLock::new(),
+            options,
+        })
+    }
+
+    /// Parse the header of a VCF file read from a reader that can't be reopened, e.g. standard input. The records
+    /// are streamed from the reader, so they can only be iterated once, and operations that reread the file, like
+    /// resuming from checkpoints, building indices or estimating the record count, fail with an error of kind
+    /// [`io::ErrorKind::InvalidInput`]. Use a [`VcfReader`] to iterate seekable sources multiple times.
+    pub fn from_reader<R: Read + Send + 'static>(reader: R, compressed: bool) -> io::Result<VcfFile> {
+        Self::from_reader_with_options(reader, compressed, ParseOptions::default())
+    }
+
+    /// Read a VCF file from a reader like [`VcfFile::from_reader`], and use the given options for parsing records.
+    pub fn from_reader_with_options<R: Read + Send + 'static>(
+        reader: R,
+        compressed: bool,
+        options: ParseOptions,
+    ) -> io::Result<VcfFile> {
+        let mut reader: Box<dyn BufRead + Send> = if compressed {
+            Box::new(BufReader::with_capacity(options.read_capacity(), MultiGzDecoder::new(reader)))
+        } else {
+            Box::new(BufReader::with_capacity(options.read_capacity(), reader))
+        };
+        let mut header = Self::parse_header(&mut reader)?;
+        options.normalize_header(&mut header);
 
         Ok(VcfFile {
-            path: String::from(path),
+            source: Source::Reader(Mutex::new(Some(reader))),
             compressed,
             header,
+            index: None,
+            id_index: OnceLock::new(),
+            options,
+        })
+    }
+
+    /// The path of the file, or None if it was read from a reader.
+    pub fn path(&self) -> Option<&str> {
+        match &self.source {
+            Source::Path(path) => Some(path),
+            Source::Reader(_) => None,
+        }
+    }
+
+    /// Load the index of a BGZF compressed file, enabling region queries via [`VcfFile::query`]. The tabix index
+    /// `<path>.tbi` is preferred, and the CSI index `<path>.csi` is used if no tabix index exists.
+    pub fn load_index(&mut self) -> io::Result<()> {
+        let path = self.path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "index files can only be loaded for files read from a path")
+        })?;
+        let tabix_path = format!("{}.tbi", path);
+        let index = if std::path::Path::new(&tabix_path).exists() {
+            Index::from_file(&tabix_path)?
+        } else {
+            Index::from_file(&format!("{}.csi", path))?
+        };
+        self.index = Some(index);
+        Ok(())
+    }
+
+    /// Scan an uncompressed file once and keep the byte offset of every record in memory, enabling region queries
+    /// for files without an index file. Replaces a previously loaded index. The records must be sorted.
+    pub fn build_index(&mut self) -> io::Result<()> {
+        if self.path().is_none() {
+            return Err(streamed());
+        }
+        if self.compressed {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "position indices are only supported for uncompressed files",
+            ));
+        }
+
+        let mut index = PositionIndex::default();
+        let mut records = self.records()?;
+        let mut offset = records.checkpoint().offset;
+        while let Some(record) = records.next() {
+            let record = record?;
+            index.add(&record.chromosome, record.position, record.end_position(), offset)?;
+            offset = records.checkpoint().offset;
+        }
+        drop(records);
+
+        self.index = Some(Index::Positions(index));
+        Ok(())
+    }
+
+    /// Get a lazy iterator over all records overlapping the 1-based, inclusive region `start..=end` on the given
+    /// chromosome. Requires an index loaded with [`VcfFile::load_index`] or built with [`VcfFile::build_index`].
+    pub fn query(
+        &self,
+        chromosome: &str,
+        start: u32,
+        end: u32,
+    ) -> io::Result<impl Iterator<Item=io::Result<VcfRecord>> + '_> {
+        let index = self.index.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "region queries require a loaded index")
+        })?;
+
+        let indexed_chromosome = self.options.indexed_chromosome(index, chromosome);
+
+        // chunks are sorted and records are sorted within the file, so reading sequentially from the first chunk
+        // visits all candidate records
+        let reader: Option<Box<dyn BufRead>> = match index {
+            Index::Positions(index) => match index.offset(indexed_chromosome, start, end) {
+                Some(offset) => {
+                    Some(Box::new(BufReader::with_capacity(self.options.read_capacity(), self.open_raw(offset)?)))
+                }
+                None => None,
+            },
+            // index files are only loaded for files read from a path
+            _ => match (index.chunks(indexed_chromosome, start, end).first(), &self.source) {
+                (Some(chunk), Source::Path(path)) => {
+                    Some(Box::new(bgzf::open_at(path, chunk.begin, self.options.read_capacity())?))
+                }
+                _ => None,
+            },
+        };
+
+        Ok(RegionIterator {
+            // the file offset of the chunk is unknown in decompressed bytes, so no checkpoints are possible
+            records: reader.map(|reader| self.record_iterator(reader, 0, 0)),
+            chromosome: chromosome.into(),
+            start,
+            end,
         })
     }
 
+    /// Whether any record starts at the given position of the chromosome. Records spanning the position, like
+    /// deletions starting before it, don't count. Uses the index if one is loaded, and scans the file otherwise,
+    /// stopping at the first record found.
+    pub fn has_variant_at(&self, chromosome: &str, position: u32) -> io::Result<bool> {
+        let records: Box<dyn Iterator<Item=io::Result<VcfRecord>>> = if self.index.is_some() {
+            Box::new(self.query(chromosome, position, position)?)
+        } else {
+            Box::new(self.records()?)
+        };
+        for record in records {
+            let record = record?;
+            if record.position == position && self.options.contig_matches(&record.chromosome, chromosome) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     // Open the VCF file and get a sequential lazy iterator over all samples
-    pub fn records(
+    pub fn records(&self) -> io::Result<RecordIterator<'_>> {
+        if let Source::Reader(stream) = &self.source {
+            let reader = stream.lock().unwrap_or_else(PoisonError::into_inner).take().ok_or_else(streamed)?;
+            return Ok(self.record_iterator(reader, self.header.size as u64, self.header.lines));
+        }
+        let mut reader = self.open()?;
+
+        // GzReader can't seek, so the header is decompressed again and discarded without buffering all of it
+        let header_size = self.header.size as u64;
+        if io::copy(&mut (&mut reader).take(header_size), &mut io::sink())? < header_size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file is shorter than its header"));
+        }
+
+        Ok(self.record_iterator(reader, self.header.size as u64, self.header.lines))
+    }
+
+    /// Get a sequential lazy iterator over all records, each bundled with the header of the file, e.g. to look up
+    /// samples by name without passing the header around separately.
+    pub fn records_with_header(&self) -> io::Result<impl Iterator<Item=(&VcfHeader, io::Result<VcfRecord>)> + '_> {
+        Ok(self.records()?.map(move |record| (&self.header, record)))
+    }
+
+    /// Get a lazy iterator over all records overlapping any of the 1-based, inclusive regions
+    /// `(chromosome, start, end)`, in a single sequential scan of the file. Each record is yielded once, even if it
+    /// overlaps multiple regions. The records must be sorted by position within each contig, while the regions
+    /// may be given in any order. The scan stops once the file passed all regions.
+    pub fn query_regions(
         &self,
+        regions: &[(String, u32, u32)],
     ) -> io::Result<impl Iterator<Item=io::Result<VcfRecord>> + '_> {
-        let mut reader: Box<dyn BufRead> = if self.compressed {
-            Box::new(BufReader::new(MultiGzDecoder::new(File::open(&self.path)?)))
+        let mut contigs: HashMap<String, (Vec<(u32, u32)>, usize)> = HashMap::new();
+        for (chromosome, start, end) in regions {
+            contigs.entry(chromosome.clone()).or_default().0.push((*start, *end));
+        }
+        for (regions, _) in contigs.values_mut() {
+            regions.sort_unstable();
+        }
+
+        Ok(MultiRegionIterator {
+            records: self.records()?,
+            remaining: regions.len(),
+            contigs,
+        })
+    }
+
+    /// Get an iterator over groups of records that share the same chromosome, so each contig can be processed on
+    /// its own. The records must be grouped by chromosome, as they are in sorted files.
+    pub fn split_by_chromosome(&self) -> io::Result<ChromosomeGroups<'_>> {
+        Ok(ChromosomeGroups {
+            records: Rc::new(RefCell::new(GroupedRecords {
+                records: self.records()?,
+                peeked: None,
+                chromosome: None,
+            })),
+        })
+    }
+
+    /// Check whether the records are sorted by contig and position. Contigs must appear in the order of the
+    /// `##contig` declarations of the header, and each contig must form one contiguous block of records.
+    /// Undeclared contigs only need to be contiguous. Stops at the first record out of order.
+    pub fn is_sorted(&self) -> io::Result<bool> {
+        let mut order = SortCheck::default();
+        for record in self.records()? {
+            let record = record?;
+            if !order.check(&self.header, &record.chromosome, record.position) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Open the VCF file and resume iteration at a checkpoint previously taken with
+    /// [`RecordIterator::checkpoint`] on this file.
+    pub fn records_from(&self, checkpoint: &Checkpoint) -> io::Result<RecordIterator<'_>> {
+        let mut reader = self.open()?;
+        let skipped = io::copy(&mut (&mut reader).take(checkpoint.offset), &mut io::sink())?;
+        if skipped < checkpoint.offset {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "checkpoint lies beyond the end of the file"));
+        }
+
+        Ok(self.record_iterator(reader, checkpoint.offset, checkpoint.line))
+    }
+
+    /// Estimate the number of records from the file size, e.g. to show the progress of a long scan. The average
+    /// size of the first 10000 records is extrapolated to the rest of the file. For compressed files, the
+    /// decompressed size of the file is extrapolated from the compression ratio of these records as well. If the
+    /// file has no more records than that, the exact count is returned.
+    ///
+    /// This is only an estimate, and can be far off if the records at the start of the file are not representative.
+    pub fn estimate_record_count(&self) -> io::Result<u64> {
+        const SAMPLE_RECORDS: u64 = 10_000;
+
+        let file_size = std::fs::metadata(self.path().ok_or_else(streamed)?)?.len();
+        let file = self.open_raw(0)?;
+        let compressed_bytes = Rc::new(Cell::new(0));
+        let reader: Box<dyn BufRead> = if self.compressed {
+            let counting = CountingReader {
+                inner: BufReader::new(file),
+                count: Rc::clone(&compressed_bytes),
+            };
+            let decoder = flate2::bufread::MultiGzDecoder::new(counting);
+            Box::new(BufReader::with_capacity(self.options.read_capacity(), decoder))
         } else {
-            Box::new(BufReader::new(File::open(&self.path)?))
+            Box::new(BufReader::with_capacity(self.options.read_capacity(), file))
         };
 
-        // we need to skip the header, and sadly GzReader doesnt provide functionality to read without
-        // allocation
-        let mut buf = vec![0; self.header.size];
-        reader.read_exact(&mut buf)?;
+        let mut records = self.record_iterator(reader, 0, 0);
+        io::copy(&mut (&mut records.reader).take(self.header.size as u64), &mut io::sink())?;
+        let mut count = 0;
+        while count < SAMPLE_RECORDS {
+            if !records.read_line()? {
+                return Ok(count);
+            }
+            count += 1;
+        }
 
-        Ok(SampleIterator {
-            reader,
-            header: &self.header,
-            buffer: String::with_capacity(1024),
+        // the offset of the iterator starts at 0, so it includes the header
+        let sampled_size = records.checkpoint().offset;
+        let total_size = if self.compressed {
+            file_size as f64 * sampled_size as f64 / compressed_bytes.get().max(1) as f64
+        } else {
+            file_size as f64
+        };
+        let record_size = (sampled_size - self.header.size as u64) as f64 / count as f64;
+        Ok(count.max(((total_size - self.header.size as f64) / record_size).round() as u64))
+    }
+
+    /// Count the records without parsing them.
+    pub fn count_records(&self) -> io::Result<u64> {
+        let mut records = self.records()?;
+        let mut count = 0;
+        while records.read_line()? {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Parse all records into a vector, preallocated with [`VcfFile::estimate_record_count`] for files read from a
+    /// path. Stops at the first record that fails to parse. Only sensible for small files, larger files should be
+    /// streamed.
+    pub fn collect_records(&self) -> io::Result<Vec<VcfRecord>> {
+        let capacity = if self.path().is_some() { self.estimate_record_count()? } else { 0 };
+        let mut records = Vec::with_capacity(capacity as usize);
+        for record in self.records()? {
+            records.push(record?);
+        }
+        Ok(records)
+    }
+
+    fn open(&self) -> io::Result<Box<dyn BufRead + '_>> {
+        Ok(if self.compressed {
+            Box::new(BufReader::with_capacity(self.options.read_capacity(), MultiGzDecoder::new(self.open_raw(0)?)))
+        } else {
+            Box::new(BufReader::with_capacity(self.options.read_capacity(), self.open_raw(0)?))
         })
     }
 
+    /// Open the raw, possibly compressed data of the file at the given byte offset. Streamed sources can't be
+    /// reopened.
+    fn open_raw(&self, offset: u64) -> io::Result<File> {
+        let mut file = File::open(self.path().ok_or_else(streamed)?)?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(file)
+    }
+
+    /// Create an iterator parsing records from a reader positioned at the start of a record, which is located at
+    /// the given byte offset and preceded by `line` lines.
+    fn record_iterator<'a>(&'a self, reader: Box<dyn BufRead + 'a>, offset: u64, line: u64) -> RecordIterator<'a> {
+        RecordIterator::new(reader, &self.header, &self.options, offset, line)
+    }
+
     /// Parse all header and meta information in the VCF file in the reader, and return a header
     /// instance
-    fn parse_header<R: BufRead>(reader: &mut R) -> io::Result<VcfHeader> {
+    pub(crate) fn parse_header<R: BufRead>(reader: &mut R) -> io::Result<VcfHeader> {
         let mut file_version = String::with_capacity(32);
         let mut header_size = 0;
+        let mut header_line_count = 1;
 
         header_size += reader.read_line(&mut file_version)?;
         assert!(
@@ -152,6 +753,7 @@ impl VcfFile {
         loop {
             buf.clear();
             header_size += reader.read_line(&mut buf)?;
+            header_line_count += 1;
 
             if !buf.starts_with("##") {
                 break;
@@ -163,14 +765,15 @@ impl VcfFile {
 
         // parse header line
         assert!(buf.starts_with("#"), "VCF file misses header line");
-        let mut head = buf.split('\t');
+        // some tools emit column names in lower case or padded with whitespace
+        let mut head = buf.trim_end_matches(['\n', '\r']).split('\t').map(str::trim);
 
         let mut optional_column = head.nth(8);
         let mut end_column_present = false;
         let mut sample_column_names = None;
 
         if let Some(column_name) = optional_column {
-            if column_name.starts_with("E") {
+            if column_name.eq_ignore_ascii_case("END") {
                 end_column_present = true;
 
                 // check next column
@@ -179,165 +782,2599 @@ impl VcfFile {
         }
 
         if let Some(column_name) = optional_column {
-            if column_name.starts_with("F") {
-                sample_column_names = Some(head.map(|s| String::from(s)).collect::<Vec<String>>())
+            if column_name.eq_ignore_ascii_case("FORMAT") {
+                sample_column_names = Some(head.map(String::from).collect::<Vec<String>>())
             }
         }
 
-        Ok(VcfHeader {
-            size: header_size,
-            file_format: file_version,
-            has_end_column: end_column_present,
-            sample_names: sample_column_names,
-            values: header_lines,
-        })
+        let mut header = VcfHeader::new(file_version, header_lines, sample_column_names, end_column_present);
+        // the file may differ from the serialized header, e.g. in its line terminators or padded column names
+        header.size = header_size;
+        header.lines = header_line_count;
+        Ok(header)
     }
 }
 
-impl<'a> SampleIterator<'a> {
-    pub(crate) fn parse_current_record(&self, header: &VcfHeader) -> VcfRecord {
-        let fields_without_samples =
-            8 + header.has_end_column as usize + header.sample_names.is_some() as usize;
+impl VcfRecord {
+    /// The ALT allele `*` of a spanning deletion, i.e. a deletion of an upstream record overlapping the position.
+    /// It is kept as an allele of [`VcfRecord::alternate_bases`], but is neither missing nor a sequence of bases.
+    pub const SPANNING_DELETION: &'static str = "*";
 
-        let mut fields = self.buffer.splitn(fields_without_samples + 1, '\t');
+    /// The 1-based, inclusive end position of the record. The `END` INFO entry takes precedence over the END
+    /// column (see [`VcfHeader::has_end_column`]), since it is the one defined by the specification. Without
+    /// either, this is the last position covered by the reference bases.
+    pub fn end_position(&self) -> u32 {
+        self.info_end().or(self.end).unwrap_or_else(|| self.reference_end())
+    }
 
-        VcfRecord {
-            chromosome: fields.next().expect("VCF record empty").into(),
-            position: fields
-                .next()
-                .expect("VCF record misses POS entry")
-                .parse()
-                .expect("VCF record has malformed POS entry"),
-            id: match fields.next().expect("VCF record misses ID entry") {
-                "." => None,
-                s => Some(s.split(';').map(|s| s.into()).collect()),
-            },
-            reference_bases: fields.next().expect("VCF record misses REF entry").into(),
-            alternate_bases: fields.next().expect("VCF record misses ALT entry").split(',')
-                .map(|s| match s {
-                    "." => None,
-                    s => Some(s.into()),
-                })
-                .collect(),
-            quality: fields
-                .next()
-                .expect("VCF record misses QUAL entry")
-                .parse()
-                .ok(),
-            filter_status: fields
-                .next()
-                .expect("VCF record misses FILTER entry")
-                .into(),
-            info: fields
-                .next()
-                .expect("VCF record misses INFO entry")
-                .split(';')
-                .map(|info| match info {
-                    "." => None,
-                    _info => None, // todo parse info entries
-                })
-                .collect(),
-            end: if header.has_end_column {
-                fields.next().expect("");
-                None // todo parse end column
-            } else {
-                None
-            },
-            sample_info: if header.sample_names.is_some() {
-                Some(SampleInfo {
-                    format: fields.next().expect("VCF record misses FORMAT entry").split(':').map(|s| s.into()).collect(),
-                    unparsed_info: fields
-                        .next()
-                        .expect("VCF record misses sample info entries")
-                        .trim()
-                        .into(),
-                })
-            } else {
-                None
-            },
-        }
+    /// Whether the record has both an `END` INFO entry and an END column, and they disagree. The INFO entry wins
+    /// in [`VcfRecord::end_position`].
+    pub fn conflicting_end(&self) -> bool {
+        matches!((self.info_end(), self.end), (Some(info_end), Some(end)) if info_end != end)
     }
-}
 
-impl<'a> Iterator for SampleIterator<'a> {
-    type Item = io::Result<VcfRecord>;
+    fn info_end(&self) -> Option<u32> {
+        self.info.iter().find_map(|entry| match entry {
+            Some(InfoEntry::End(end)) => Some(*end),
+            _ => None,
+        })
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.buffer.clear();
-        let result = self.reader.read_line(&mut self.buffer);
-        match result {
-            Ok(size) => {
-                if size == 0 {
-                    return None;
-                }
+    /// Check that an `END` INFO entry agrees with the last position covered by the reference bases. Only records
+    /// whose alternate alleles are all sequences are checked, since `END` is meant for symbolic alleles and
+    /// reference blocks, where it may extend beyond REF. This is also checked in strict mode.
+    pub fn consistent_end(&self) -> bool {
+        let sequence_alleles = self
+            .alternate_bases
+            .iter()
+            .all(|alt| alt.as_deref().is_some_and(|alt| !is_symbolic_allele(alt)));
+        !sequence_alleles || self.end_position() == self.reference_end()
+    }
 
-                Some(Ok(self.parse_current_record(&self.header)))
-            }
-            Err(e) => Some(Err(e)),
-        }
+    /// The last position covered by the reference bases.
+    fn reference_end(&self) -> u32 {
+        self.position + (self.reference_bases.len() as u32).max(1) - 1
     }
-}
 
-impl SampleInfo {
-    pub fn samples(&self) -> impl Iterator<Item=Sample<'_>> {
-        fast_split(&self.unparsed_info, '\t' as u8)
-            .map(|s| Self::parse_sample(s))
+    /// Get the distinct filters of the FILTER column, which is `PASS` if the record passed all filters. Repeated
+    /// filters are only contained once, and a missing FILTER (`.`) gives an empty set.
+    pub fn filter_set(&self) -> HashSet<&str> {
+        self.filters().collect()
     }
 
-    fn parse_sample(text: &str) -> Sample<'_> {
-        Sample {
-            unparsed_info: text,
+    /// Whether the FILTER column contains the given filter, e.g. `q10`.
+    pub fn has_filter(&self, name: &str) -> bool {
+        self.filters().any(|filter| filter == name)
+    }
+
+    fn filters(&self) -> impl Iterator<Item=&str> {
+        self.filter_status.split(';').filter(|filter| !filter.is_empty() && *filter != ".")
+    }
+
+    /// Estimate the number of bytes the record owns on the heap, which is the capacity of its strings and vectors
+    /// without the size of the record itself. Multiplied by the number of records, this estimates the memory needed
+    /// to collect a file instead of streaming it.
+    pub fn heap_size(&self) -> usize {
+        let id = match &self.id {
+            Some(RecordIds::One(id)) => id.capacity(),
+            Some(RecordIds::Many(ids)) => strings_heap_size(ids),
+            None => 0,
+        };
+        let filter_status = match &self.filter_status {
+            Cow::Owned(filter_status) => filter_status.capacity(),
+            Cow::Borrowed(_) => 0,
+        };
+        let sample_info = self
+            .sample_info
+            .as_ref()
+            .map_or(0, |info| strings_heap_size(&info.format) + info.unparsed_info.capacity());
+
+        self.chromosome.capacity()
+            + id
+            + self.reference_bases.capacity()
+            + vec_heap_size(&self.alternate_bases)
+            + self.alternate_bases.iter().flatten().map(String::capacity).sum::<usize>()
+            + filter_status
+            + vec_heap_size(&self.info)
+            + self.info.iter().flatten().map(InfoEntry::heap_size).sum::<usize>()
+            + sample_info
+    }
+
+    /// Number of ALT alleles, not counting missing alleles. A record without alternate alleles (`.`) has none.
+    pub fn alt_count(&self) -> usize {
+        self.alternate_bases.iter().filter(|alt| alt.is_some()).count()
+    }
+
+    /// Whether one of the ALT alleles is the spanning deletion `*`, see [`VcfRecord::SPANNING_DELETION`].
+    pub fn has_spanning_deletion(&self) -> bool {
+        self.alternate_bases.iter().any(|alt| alt.as_deref() == Some(Self::SPANNING_DELETION))
+    }
+
+    /// Whether the record is a gVCF reference block: it has an `END` INFO entry, and its only ALT alleles are the
+    /// unspecified alleles `<NON_REF>` or `<*>`, if any.
+    pub fn is_reference_block(&self) -> bool {
+        self.info_end().is_some()
+            && self.alternate_bases.iter().flatten().all(|alt| alt == "<NON_REF>" || alt == "<*>")
+    }
+
+    /// Whether the record has more than one ALT allele.
+    pub fn is_multiallelic(&self) -> bool {
+        self.alt_count() > 1
+    }
+
+    /// Whether the record has a FORMAT column and sample data, as opposed to a sites-only record.
+    pub fn has_samples(&self) -> bool {
+        self.sample_info.is_some()
+    }
+
+    /// Whether the FORMAT column of the record declares the given key. Sites-only records have no FORMAT keys.
+    pub fn has_format_field(&self, key: &str) -> bool {
+        self.sample_info.as_ref().is_some_and(|info| info.format.iter().any(|k| k == key))
+    }
+
+    /// Number of samples of the record, as declared by the header of its file. Sites-only records have no samples.
+    pub fn sample_count(&self, header: &VcfHeader) -> usize {
+        match (&self.sample_info, &header.sample_names) {
+            (Some(_), Some(names)) => names.len(),
+            _ => 0,
         }
     }
-}
 
-impl<'a> Sample<'a> {
-    /// Get an iterator over all entries in the sample info field. The order of the entries is
-    /// defined by the FORMAT column.
-    pub fn entries(&self) -> impl Iterator<Item=&'_ str> {
-        fast_split(&self.unparsed_info, ':' as u8)
+    /// Whether both records start at the same CHROM and POS, regardless of their alleles, e.g. to group the lines of
+    /// a multiallelic site that was split into biallelic records.
+    pub fn same_locus(&self, other: &VcfRecord) -> bool {
+        self.position == other.position && self.chromosome == other.chromosome
     }
 
-    /// Extract the genotype information if present. If the sample has no genotype information, None
-    /// is returned.
-    pub fn get_genotype(&self) -> Option<&'_ str> {
-        if self.unparsed_info.len() > 0 {
-            self.entries().nth(0)
-        } else {
-            None
+    /// Get a canonical `chrom:pos:ref:alt` key of the variant described by the alternate allele at `alt_index`,
+    /// suitable for matching variants between call sets. Bases shared by both alleles are trimmed, so that
+    /// different representations of the same variant produce the same key. Symbolic and missing alleles are not
+    /// normalized. Bases are converted to upper case.
+    ///
+    /// # Panics
+    /// If `alt_index` is not an index into `alternate_bases`.
+    pub fn variant_key(&self, alt_index: usize) -> String {
+        let (position, reference, alternate) = match &self.alternate_bases[alt_index] {
+            Some(alt) if !is_symbolic_allele(alt) => trim_alleles(self.position, &self.reference_bases, alt),
+            Some(alt) => (self.position, self.reference_bases.as_str(), alt.as_str()),
+            None => (self.position, self.reference_bases.as_str(), "."),
+        };
+
+        format!(
+            "{}:{}:{}:{}",
+            self.chromosome,
+            position,
+            reference.to_ascii_uppercase(),
+            alternate.to_ascii_uppercase()
+        )
+    }
+
+    /// Resolve an allele index of a genotype to the bases of the allele: 0 is the reference, and `1..=N` are the
+    /// alternate alleles. Returns None for out-of-range indices, and for missing and symbolic alleles, which have
+    /// no sequence. The spanning deletion resolves to the sentinel [`VcfRecord::SPANNING_DELETION`].
+    pub fn allele_base(&self, index: u32) -> Option<&str> {
+        if index == 0 {
+            return Some(&self.reference_bases);
+        }
+        match self.alternate_bases.get(index as usize - 1)? {
+            Some(alt) if alt == Self::SPANNING_DELETION => Some(Self::SPANNING_DELETION),
+            Some(alt) if !is_symbolic_allele(alt) => Some(alt),
+            _ => None,
         }
     }
 
-    // TODO implement the rest of the sample info fields. Those aren't at fixed positions, and thus their position must
-    //  be determined by the FORMAT column
-}
+    /// Decompose a multi-nucleotide polymorphism (MNP) like `REF=AC ALT=GT`, i.e. a single ALT allele of the same
+    /// length as REF that differs at more than one base, into one SNP record per differing base. The SNPs keep all
+    /// other columns of the MNP, except for END, since each covers a single base. Any other record, including
+    /// indels and multiallelic records, is returned unchanged as the only element.
+    pub fn decompose_mnp(&self) -> Vec<VcfRecord> {
+        let (reference, alternate) = match self.alternate_bases.as_slice() {
+            [Some(alt)] if !is_symbolic_allele(alt) && alt.len() == self.reference_bases.len() => {
+                (self.reference_bases.as_bytes(), alt.as_bytes())
+            }
+            _ => return vec![self.clone()],
+        };
+        let differing = (0..reference.len())
+            .filter(|&i| !reference[i].eq_ignore_ascii_case(&alternate[i]))
+            .collect::<Vec<_>>();
+        if differing.len() < 2 {
+            return vec![self.clone()];
+        }
 
-#[cfg(test)]
-mod tests {
-    use std::time::Instant;
+        let info = self
+            .info
+            .iter()
+            .filter(|entry| !matches!(entry, Some(InfoEntry::End(_))))
+            .cloned()
+            .collect::<Vec<_>>();
+        differing
+            .into_iter()
+            .map(|i| VcfRecord {
+                position: self.position + i as u32,
+                reference_bases: (reference[i] as char).to_string(),
+                alternate_bases: vec![Some((alternate[i] as char).to_string())],
+                info: info.clone(),
+                end: None,
+                ..self.clone()
+            })
+            .collect()
+    }
 
-    use super::*;
+    /// Rewrite the allele indices of all genotypes after the alleles of the record were reordered, e.g. to merge
+    /// records with different ALT columns. `mapping[new]` is the old index of the allele at index `new`, where
+    /// index 0 is the reference allele. Alleles without a new index become missing (`.`). Only the GT fields are
+    /// rewritten, the ALT column has to be reordered by the caller.
+    pub fn remap_genotypes(&mut self, mapping: &[u32]) {
+        let Some(sample_info) = &mut self.sample_info else {
+            return;
+        };
+        let Some(gt_index) = sample_info.format.iter().position(|key| key == "GT") else {
+            return;
+        };
 
-    #[test]
-    fn test() {
-        let vcf_file = VcfFile::parse("run/example.vcf.gz", true).expect("failed to open VCF file");
+        let remap = |entry: &str| {
+            let mut genotype = parse_genotype(entry);
+            for allele in &mut genotype.alleles {
+                *allele = allele.and_then(|old| mapping.iter().position(|&index| index == old).map(|new| new as u32));
+            }
+            genotype.to_string()
+        };
+        let samples = sample_info
+            .samples()
+            .map(|sample| {
+                sample
+                    .entries()
+                    .enumerate()
+                    .map(|(i, entry)| if i == gt_index { Cow::Owned(remap(entry)) } else { Cow::Borrowed(entry) })
+                    .collect::<Vec<_>>()
+                    .join(":")
+            })
+            .collect::<Vec<_>>();
+        sample_info.unparsed_info = samples.join("\t");
+    }
 
-        let start = Instant::now();
-        let mut cells = 0;
+    /// Keep only the sample columns at the given indices, in the given order, e.g. to write the samples retained by
+    /// [`VcfHeader::subset_samples`]. Indices without a sample column become missing samples (`.`).
+    pub fn select_samples(&mut self, indices: &[usize]) {
+        let Some(sample_info) = &mut self.sample_info else {
+            return;
+        };
 
-        vcf_file
-            .records()
-            .expect("failed to open VCF file")
-            .take(40000)
-            .for_each(|rec| {
-                cells += &rec
-                    .expect("failed to parse VCF record")
-                    .sample_info
-                    .expect("")
-                    .samples()
-                    .count()
-            });
-        println!("read {} cells in {:?}", cells, start.elapsed());
+        let samples = sample_info.samples().map(|sample| sample.unparsed_info).collect::<Vec<_>>();
+        let selected = indices.iter().map(|&i| samples.get(i).copied().unwrap_or(".")).collect::<Vec<_>>().join("\t");
+        sample_info.unparsed_info = selected;
+    }
+
+    /// Get the alternate allele frequencies declared by the `AF` INFO entry, if present.
+    pub fn info_allele_frequencies(&self) -> Option<&[f32]> {
+        self.info.iter().find_map(|entry| match entry {
+            Some(InfoEntry::AlleleFrequency(frequencies)) => Some(frequencies.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Compute the alternate allele frequencies from the genotypes of all samples. Missing alleles are not
+    /// counted. Returns None if the record has no genotype information or no called alleles.
+    pub fn genotype_allele_frequencies(&self) -> Option<Vec<f32>> {
+        let sample_info = self.sample_info.as_ref()?;
+
+        let mut counts = vec![0u32; self.alternate_bases.len()];
+        let mut called = 0u32;
+        for sample in sample_info.samples() {
+            let Some(genotype) = sample.genotype() else {
+                continue;
+            };
+
+            for allele in genotype.alleles.into_iter().flatten() {
+                called += 1;
+                if allele > 0 && allele as usize <= counts.len() {
+                    counts[allele as usize - 1] += 1;
+                }
+            }
+        }
+
+        if called == 0 {
+            None
+        } else {
+            Some(counts.into_iter().map(|c| c as f32 / called as f32).collect())
+        }
+    }
+
+    /// Sum the allele depths (`AD`) of all samples, giving the read depth of each allele across the cohort,
+    /// including the reference allele. Samples with missing or malformed AD, or with a number of values other than
+    /// the number of alleles, are skipped. Returns None if no sample has allele depths.
+    pub fn total_allele_depths(&self) -> Option<Vec<u32>> {
+        let allele_count = self.alternate_bases.len() + 1;
+        let mut totals: Option<Vec<u32>> = None;
+        for depths in self.sample_info.as_ref()?.samples().filter_map(|sample| sample.allele_depths()) {
+            if depths.len() != allele_count {
+                continue;
+            }
+            let totals = totals.get_or_insert_with(|| vec![0; allele_count]);
+            for (total, depth) in totals.iter_mut().zip(depths) {
+                *total += depth;
+            }
+        }
+        totals
+    }
+
+    /// Get the combined read depth across all samples from the `DP` INFO entry. Returns None if it is absent or
+    /// malformed.
+    pub fn info_depth(&self) -> Option<u32> {
+        self.info.iter().find_map(|entry| match entry {
+            Some(InfoEntry::CombinedDepth(depth)) => Some(*depth),
+            Some(InfoEntry::NonStandard(key, NonStandardInfoValue::SingleValue(value))) if key == "DP" => {
+                value.parse().ok()
+            }
+            _ => None,
+        })
+    }
+
+    /// Get the ancestral allele from the `AA` INFO entry, normalized to uppercase. Returns None if it is absent or
+    /// unknown (`.`).
+    pub fn ancestral_allele(&self) -> Option<&str> {
+        self.info.iter().find_map(|entry| match entry {
+            Some(InfoEntry::AncestralAllele(allele)) => allele.as_deref(),
+            _ => None,
+        })
+    }
+
+    /// Sum the read depths (`DP`) of all samples. Samples with missing or malformed DP are skipped. Returns None if
+    /// no sample has a depth.
+    pub fn sum_sample_depths(&self) -> Option<u32> {
+        self.sample_info
+            .as_ref()?
+            .samples()
+            .filter_map(|sample| sample.depth())
+            .reduce(u32::saturating_add)
+    }
+
+    /// Check that the read depths of the samples don't sum to more than the combined depth of the `DP` INFO entry.
+    /// The INFO depth usually counts all reads, whereas the sample depths only count reads passing filters, so a
+    /// larger sum points to a miscomputed annotation. Records without either depth are consistent. This is also
+    /// checked in strict mode.
+    pub fn consistent_depth(&self) -> bool {
+        self.validate_depth().is_ok()
+    }
+
+    /// Check that the genotypes of all samples are well-formed (see [`parse_genotype_strict`]) and only refer to
+    /// alleles of the record, i.e. that each allele index is at most the number of alternate alleles. Samples are
+    /// numbered in the order of the header. This is also checked in strict mode.
+    pub fn validate_genotypes(&self) -> Result<(), VcfParseError> {
+        let Some(sample_info) = &self.sample_info else {
+            return Ok(());
+        };
+
+        // a missing ALT (`.`) doesn't count as an allele
+        let allele_count = self.alternate_bases.iter().flatten().count() + 1;
+        for (sample, genotype) in sample_info.samples().enumerate() {
+            let Some(genotype) = genotype.get_genotype().map(parse_genotype_strict).transpose()? else {
+                continue;
+            };
+
+            if let Some(&allele) = genotype.alleles.iter().flatten().find(|&&allele| allele as usize >= allele_count) {
+                return Err(VcfParseError::AlleleIndexOutOfRange {
+                    sample,
+                    allele,
+                    allele_count,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl RecordIds {
+    /// Parse the ID column of a record. Returns None for the missing value `.`.
+    /// Empty identifiers of a malformed column like `;rs1;` are dropped.
+    fn parse(field: &str) -> Option<RecordIds> {
+        match field {
+            "." | "" => None,
+            field if field.contains(';') => {
+                let mut ids = field.split(';').filter(|id| !id.is_empty()).map(String::from).collect::<Vec<_>>();
+                match ids.len() {
+                    0 => None,
+                    1 => ids.pop().map(RecordIds::One),
+                    _ => Some(RecordIds::Many(ids)),
+                }
+            }
+            field => Some(RecordIds::One(field.into())),
+        }
+    }
+
+    /// Check that the ID column has no empty identifiers, as in `;rs1;` or `rs1;;rs2`. This is checked in strict
+    /// mode.
+    pub(crate) fn validate(field: &str) -> Result<(), VcfParseError> {
+        if field.split(';').any(str::is_empty) {
+            Err(VcfParseError::MalformedId(field.into()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Iterate over all identifiers in the order of the ID column.
+    pub fn iter(&self) -> impl Iterator<Item=&str> {
+        let ids: &[String] = match self {
+            RecordIds::One(id) => std::slice::from_ref(id),
+            RecordIds::Many(ids) => ids,
+        };
+        ids.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            RecordIds::One(_) => 1,
+            RecordIds::Many(ids) => ids.len(),
+        }
+    }
+
+    /// Always false, since records without identifiers have no `RecordIds` at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.iter().any(|i| i == id)
+    }
+}
+
+impl<'a> RecordIterator<'a> {
+    pub(crate) fn new(
+        reader: Box<dyn BufRead + 'a>,
+        header: &'a VcfHeader,
+        options: &'a ParseOptions,
+        offset: u64,
+        line: u64,
+    ) -> Self {
+        RecordIterator {
+            reader,
+            header,
+            options,
+            buffer: String::with_capacity(options.line_capacity()),
+            offset,
+            line,
+        }
+    }
+
+    /// Take a snapshot of the iterator position. Resuming from it with [`VcfFile::records_from`] continues with the
+    /// record that the next call to `next` would return.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            offset: self.offset,
+            line: self.line,
+        }
+    }
+
+    /// Yield the first record and then every `step`th record, like [`Iterator::step_by`]. The records in between
+    /// are skipped without parsing them, which is much faster for previews of large files.
+    ///
+    /// # Panics
+    /// If `step` is 0.
+    pub fn step_by_record(self, step: usize) -> SteppedRecords<'a> {
+        assert!(step != 0, "step must be positive");
+        SteppedRecords {
+            records: self,
+            step,
+            first_taken: false,
+        }
+    }
+
+    /// Read the next line into the buffer without its line terminator. Returns false at the end of the file.
+    pub(crate) fn read_line(&mut self) -> io::Result<bool> {
+        self.buffer.clear();
+        let size = self.reader.read_line(&mut self.buffer)?;
+        if size == 0 {
+            return Ok(false);
+        }
+        self.offset += size as u64;
+        self.line += 1;
+
+        // the last column must not include the line terminator, which is `\r\n` in files written on Windows
+        if self.buffer.ends_with('\n') {
+            self.buffer.pop();
+            if self.buffer.ends_with('\r') {
+                self.buffer.pop();
+            }
+        }
+        Ok(true)
+    }
+
+    /// Read the next line into the byte buffer without its line terminator, like [`RecordIterator::read_line`]
+    /// but without validating UTF-8. Returns false at the end of the file.
+    pub(crate) fn read_line_bytes(&mut self, buffer: &mut Vec<u8>) -> io::Result<bool> {
+        buffer.clear();
+        let size = self.reader.read_until(b'\n', buffer)?;
+        if size == 0 {
+            return Ok(false);
+        }
+        self.offset += size as u64;
+        self.line += 1;
+
+        if buffer.ends_with(b"\n") {
+            buffer.pop();
+            if buffer.ends_with(b"\r") {
+                buffer.pop();
+            }
+        }
+        Ok(true)
+    }
+
+    pub(crate) fn parse_current_record(&self, header: &VcfHeader) -> Result<VcfRecord, VcfParseError> {
+        VcfRecord::parse(&self.buffer, header, self.options)
+    }
+
+    /// Convert an error in the line read last, whose contents are `text`, into an [`io::Error`] with its location.
+    pub(crate) fn located(&self, error: VcfParseError, text: &[u8]) -> io::Error {
+        error.at_line(self.line, text, self.header).into()
+    }
+}
+
+impl VcfRecord {
+    /// Parse a record from a single line without line terminator.
+    pub(crate) fn parse(line: &str, header: &VcfHeader, options: &ParseOptions) -> Result<VcfRecord, VcfParseError> {
+        let fields_without_samples =
+            8 + header.has_end_column as usize + header.sample_names.is_some() as usize;
+
+        Self::check_columns(line, header)?;
+        let mut fields = line.splitn(fields_without_samples + 1, '\t');
+
+        let chromosome = Self::parse_chromosome(fields.next().expect("VCF record empty"), options)?;
+        let position = Self::parse_position(fields.next().expect("VCF record misses POS entry"))?;
+        let id = Self::parse_id(fields.next().expect("VCF record misses ID entry"), options)?;
+        let reference_bases = fields.next().expect("VCF record misses REF entry").into();
+        let alternate_bases = Self::parse_alts(fields.next().expect("VCF record misses ALT entry"), options)?;
+        let quality = match Self::parse_quality(options.missing(fields.next().expect("VCF record misses QUAL entry"))) {
+            Ok(quality) => quality,
+            Err(e) if options.strict => return Err(e),
+            Err(_) => None,
+        };
+        let filter_status = match fields.next().expect("VCF record misses FILTER entry") {
+            "PASS" => Cow::Borrowed("PASS"),
+            "." => Cow::Borrowed("."),
+            filter => Cow::Owned(filter.into()),
+        };
+        let info = Self::parse_info(
+            options.missing(fields.next().expect("VCF record misses INFO entry")),
+            alternate_bases.len(),
+            header,
+            options.strict,
+        )?;
+
+        let record = VcfRecord {
+            chromosome,
+            position,
+            id,
+            reference_bases,
+            alternate_bases,
+            quality,
+            filter_status,
+            info,
+            end: if header.has_end_column {
+                fields.next().expect("VCF record misses END entry").parse().ok()
+            } else {
+                None
+            },
+            sample_info: if header.sample_names.is_some() {
+                Some(Self::parse_samples(
+                    fields.next().expect("VCF record misses FORMAT entry"),
+                    fields.next().unwrap_or_default(),
+                    options.strict,
+                )?)
+            } else {
+                None
+            },
+        };
+
+        if options.strict {
+            Self::validate_record(&record, header)?;
+        }
+
+        Ok(record)
+    }
+
+    /// Check that a line has all columns required by the header. A line with too few columns would panic while
+    /// parsing, which most likely means it is delimited by spaces.
+    pub(crate) fn check_columns(line: &str, header: &VcfHeader) -> Result<(), VcfParseError> {
+        let fields_without_samples = 8 + header.has_end_column as usize + header.sample_names.is_some() as usize;
+        // a FORMAT column without any samples is followed by no sample column
+        let required = fields_without_samples + !header.samples().is_empty() as usize;
+        let found = line.splitn(required, '\t').count();
+        if found < required {
+            return Err(VcfParseError::MissingColumns {
+                expected: required,
+                found,
+                space_delimited: line.contains(' '),
+            });
+        }
+        Ok(())
+    }
+
+    /// Parse the CHROM column, applying the contig normalizer of the options. In strict mode, it must not be empty.
+    fn parse_chromosome(field: &str, options: &ParseOptions) -> Result<String, VcfParseError> {
+        if options.strict && field.is_empty() {
+            return Err(VcfParseError::EmptyField("CHROM"));
+        }
+        Ok(options.normalize_contig(field))
+    }
+
+    /// Parse the POS column, which must be a non-negative integer.
+    fn parse_position(field: &str) -> Result<u32, VcfParseError> {
+        field.parse().map_err(|_| VcfParseError::MalformedPosition(field.into()))
+    }
+
+    /// Parse the ID column, where `.` and the missing values of the options mean no identifiers. In strict mode, it
+    /// must not contain empty identifiers.
+    fn parse_id(field: &str, options: &ParseOptions) -> Result<Option<RecordIds>, VcfParseError> {
+        let field = options.missing(field);
+        if options.strict {
+            RecordIds::validate(field)?;
+        }
+        Ok(RecordIds::parse(field))
+    }
+
+    /// Parse the comma-separated ALT column, where `.` and the missing values of the options are missing alleles.
+    /// In strict mode, alleles must not be empty.
+    fn parse_alts(field: &str, options: &ParseOptions) -> Result<Vec<Option<String>>, VcfParseError> {
+        field
+            .split(',')
+            .map(|allele| match options.missing(allele) {
+                "." => Ok(None),
+                "" if options.strict => Err(VcfParseError::EmptyField("ALT")),
+                allele => Ok(Some(allele.into())),
+            })
+            .collect()
+    }
+
+    /// Parse the FORMAT column and keep the sample columns for lazy parsing. In strict mode, the FORMAT column must
+    /// not be empty.
+    fn parse_samples(format: &str, samples: &str, strict: bool) -> Result<SampleInfo, VcfParseError> {
+        if strict && format.is_empty() {
+            return Err(VcfParseError::EmptyField("FORMAT"));
+        }
+        Ok(SampleInfo::new(format, samples))
+    }
+
+    /// Parse the INFO column of a record with `alt_count` alternate alleles. In strict mode, entries must not be
+    /// empty or lack a key, as in `DP=10;;=5`.
+    pub(crate) fn parse_info(
+        info: &str,
+        alt_count: usize,
+        header: &VcfHeader,
+        strict: bool,
+    ) -> Result<Vec<Option<InfoEntry>>, VcfParseError> {
+        match info {
+            // a missing INFO column has no entries at all, whereas None entries are entries that failed to parse
+            "." => Ok(Vec::new()),
+            info => info
+                .split(';')
+                .map(|entry| match entry {
+                    "." => Ok(None),
+                    entry if strict && (entry.is_empty() || entry.starts_with('=')) => {
+                        Err(VcfParseError::MalformedInfo(entry.into()))
+                    }
+                    entry => Ok(InfoEntry::parse(entry, alt_count, &header.info_definitions)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Parse the QUAL column, which is an integer or a floating point number, optionally in scientific notation.
+    /// An empty QUAL column is treated like the missing value `.`. Infinity and NaN are malformed.
+    pub(crate) fn parse_quality(field: &str) -> Result<Option<f32>, VcfParseError> {
+        match field {
+            "." | "" => Ok(None),
+            field => match field.parse::<f32>() {
+                Ok(quality) if quality.is_finite() => Ok(Some(quality)),
+                _ => Err(VcfParseError::MalformedQuality(field.into())),
+            },
+        }
+    }
+
+    /// Validate a parsed record against the header. Only called in strict mode.
+    fn validate_record(record: &VcfRecord, header: &VcfHeader) -> Result<(), VcfParseError> {
+        record.validate_mandatory_fields()?;
+        record.validate_contig_length(header)?;
+        record.validate_end()?;
+        record.validate_depth()?;
+        record.validate_genotypes()
+    }
+
+    /// Report sample depths exceeding the INFO depth, see [`VcfRecord::consistent_depth`].
+    pub(crate) fn validate_depth(&self) -> Result<(), VcfParseError> {
+        match (self.info_depth(), self.sum_sample_depths()) {
+            (Some(info_depth), Some(sample_depth)) if sample_depth > info_depth => {
+                Err(VcfParseError::InconsistentDepth {
+                    info_depth,
+                    sample_depth,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Report an `END` INFO entry that disagrees with the reference bases, see [`VcfRecord::consistent_end`].
+    pub(crate) fn validate_end(&self) -> Result<(), VcfParseError> {
+        if self.consistent_end() {
+            Ok(())
+        } else {
+            Err(VcfParseError::InconsistentEnd {
+                end: self.end_position(),
+                reference_end: self.reference_end(),
+            })
+        }
+    }
+
+    /// Check that none of the mandatory columns CHROM, REF and ALT is empty.
+    pub(crate) fn validate_mandatory_fields(&self) -> Result<(), VcfParseError> {
+        if self.chromosome.is_empty() {
+            return Err(VcfParseError::EmptyField("CHROM"));
+        }
+        if self.reference_bases.is_empty() {
+            return Err(VcfParseError::EmptyField("REF"));
+        }
+        if self.alternate_bases.iter().any(|alt| alt.as_deref() == Some("")) {
+            return Err(VcfParseError::EmptyField("ALT"));
+        }
+        Ok(())
+    }
+
+    /// Check that the position lies within the length declared for the contig, if the header declares one.
+    pub(crate) fn validate_contig_length(&self, header: &VcfHeader) -> Result<(), VcfParseError> {
+        match header.contig_length(&self.chromosome) {
+            Some(length) if self.position as u64 > length => Err(VcfParseError::PositionOutOfContig {
+                chromosome: self.chromosome.clone(),
+                position: self.position,
+                length,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl InfoEntry {
+    /// Number of bytes the entry owns on the heap, see [`VcfRecord::heap_size`].
+    fn heap_size(&self) -> usize {
+        match self {
+            InfoEntry::AncestralAllele(allele) => allele.as_ref().map_or(0, String::capacity),
+            InfoEntry::AlleleCount(values)
+            | InfoEntry::TotalAlleleReadDepth(values)
+            | InfoEntry::ForwardAlleleReadDepth(values)
+            | InfoEntry::ReverseAlleleReadDepth(values) => vec_heap_size(values),
+            InfoEntry::AlleleFrequency(values) => vec_heap_size(values),
+            InfoEntry::Cigar(values) => strings_heap_size(values),
+            InfoEntry::StructuralVariantType(value) => value.capacity(),
+            InfoEntry::StructuralVariantLength(values) => vec_heap_size(values),
+            InfoEntry::NonStandard(key, value) => {
+                key.capacity()
+                    + match value {
+                        NonStandardInfoValue::NoValue => 0,
+                        NonStandardInfoValue::SingleValue(value) => value.capacity(),
+                        NonStandardInfoValue::ValueList(values) => strings_heap_size(values),
+                    }
+            }
+            _ => 0,
+        }
+    }
+
+    /// Restrict an entry with one value per allele to the alternate allele at `alt_index`, e.g. when splitting a
+    /// multiallelic record. Entries with one value per alternate allele (Number=A) keep the value of the allele,
+    /// entries with one value per allele (Number=R) keep the reference value and the value of the allele. All
+    /// other entries are returned unchanged.
+    ///
+    /// # Panics
+    /// If the entry has no value for the allele at `alt_index`.
+    pub fn subset_allele(&self, alt_index: usize) -> InfoEntry {
+        let per_alt = |values: &[u32]| vec![values[alt_index]];
+        let per_allele = |values: &[u32]| vec![values[0], values[alt_index + 1]];
+
+        match self {
+            InfoEntry::AlleleCount(counts) => InfoEntry::AlleleCount(per_alt(counts)),
+            InfoEntry::AlleleFrequency(frequencies) => InfoEntry::AlleleFrequency(vec![frequencies[alt_index]]),
+            InfoEntry::StructuralVariantLength(lengths) => InfoEntry::StructuralVariantLength(vec![lengths[alt_index]]),
+            InfoEntry::TotalAlleleReadDepth(depths) => InfoEntry::TotalAlleleReadDepth(per_allele(depths)),
+            InfoEntry::ForwardAlleleReadDepth(depths) => InfoEntry::ForwardAlleleReadDepth(per_allele(depths)),
+            InfoEntry::ReverseAlleleReadDepth(depths) => InfoEntry::ReverseAlleleReadDepth(per_allele(depths)),
+            entry => entry.clone(),
+        }
+    }
+
+    /// Parse a single `KEY=VALUE` or flag entry of the INFO column. `alt_count` is the number of alternate
+    /// alleles of the record, which is needed to validate the cardinality of per-allele entries. Values of
+    /// non-standard keys are split at commas, unless `definitions` declares the key as a single string. Returns
+    /// None if a reserved key carries a malformed value.
+    fn parse(entry: &str, alt_count: usize, definitions: &[InfoDefinition]) -> Option<InfoEntry> {
+        let (key, value) = match entry.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (entry, None),
+        };
+
+        match key {
+            "AA" => match value? {
+                "" => None,
+                "." => Some(InfoEntry::AncestralAllele(None)),
+                allele => Some(InfoEntry::AncestralAllele(Some(allele.to_ascii_uppercase()))),
+            },
+            "AD" => parse_value_list(value?, alt_count + 1).map(InfoEntry::TotalAlleleReadDepth),
+            "ADF" => parse_value_list(value?, alt_count + 1).map(InfoEntry::ForwardAlleleReadDepth),
+            "ADR" => parse_value_list(value?, alt_count + 1).map(InfoEntry::ReverseAlleleReadDepth),
+            "AF" => parse_value_list(value?, alt_count).map(InfoEntry::AlleleFrequency),
+            "END" => value?.parse().ok().map(InfoEntry::End),
+            "SVTYPE" => Some(InfoEntry::StructuralVariantType(value?.into())),
+            "SVLEN" => parse_value_list(value?, alt_count).map(InfoEntry::StructuralVariantLength),
+            "CIPOS" => parse_value_list(value?, 2).map(|ci: Vec<i32>| InfoEntry::PositionConfidenceInterval(ci[0], ci[1])),
+            "CIEND" => parse_value_list(value?, 2).map(|ci: Vec<i32>| InfoEntry::EndConfidenceInterval(ci[0], ci[1])),
+            // flags must not carry a value
+            "DB" => value.is_none().then_some(InfoEntry::SNPDatabaseMembership),
+            "H2" => value.is_none().then_some(InfoEntry::HapMap2),
+            "H3" => value.is_none().then_some(InfoEntry::HapMap3),
+            "SOMATIC" => value.is_none().then_some(InfoEntry::Somatic),
+            "VALIDATED" => value.is_none().then_some(InfoEntry::Validated),
+            "1000G" => value.is_none().then_some(InfoEntry::Flag1000G),
+            _ => Some(InfoEntry::NonStandard(
+                key.into(),
+                match value {
+                    None => NonStandardInfoValue::NoValue,
+                    Some(value) if value.contains(',') && !is_single_string(definitions, key) => {
+                        NonStandardInfoValue::ValueList(value.split(',').map(|s| s.into()).collect())
+                    }
+                    Some(value) => NonStandardInfoValue::SingleValue(value.into()),
+                },
+            )),
+        }
+    }
+}
+
+/// Number of bytes allocated by a vector for its elements, not counting allocations owned by the elements.
+fn vec_heap_size<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * std::mem::size_of::<T>()
+}
+
+/// Number of bytes allocated by a vector of strings, including the strings.
+fn strings_heap_size(strings: &Vec<String>) -> usize {
+    vec_heap_size(strings) + strings.iter().map(String::capacity).sum::<usize>()
+}
+
+/// The error of operations that would have to reread a file streamed from a reader.
+fn streamed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "the records of a reader can only be read once")
+}
+
+/// Strip a `chr` prefix in any case from a chromosome name.
+fn strip_chr_prefix(chromosome: &str) -> &str {
+    match chromosome.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("chr") => &chromosome[3..],
+        _ => chromosome,
+    }
+}
+
+/// Whether the INFO key is declared as a single string, which keeps its commas.
+fn is_single_string(definitions: &[InfoDefinition], key: &str) -> bool {
+    definitions.iter().any(|definition| definition.id == key && definition.is_single_string())
+}
+
+/// Whether an ALT allele is symbolic (`<DEL>`), a breakend, or the spanning deletion `*`, rather than a sequence
+/// of bases.
+fn is_symbolic_allele(allele: &str) -> bool {
+    allele.starts_with('<') || allele.contains(['[', ']']) || allele == VcfRecord::SPANNING_DELETION
+}
+
+/// Remove bases shared by the reference and alternate allele, first from the end and then from the start, keeping
+/// at least one base in each allele. Returns the adjusted position and the trimmed alleles.
+fn trim_alleles<'a>(position: u32, reference: &'a str, alternate: &'a str) -> (u32, &'a str, &'a str) {
+    let (mut reference, mut alternate) = (reference.as_bytes(), alternate.as_bytes());
+    while reference.len() > 1
+        && alternate.len() > 1
+        && reference.last().map(u8::to_ascii_uppercase) == alternate.last().map(u8::to_ascii_uppercase)
+    {
+        reference = &reference[..reference.len() - 1];
+        alternate = &alternate[..alternate.len() - 1];
+    }
+
+    let mut position = position;
+    while reference.len() > 1 && alternate.len() > 1 && reference[0].eq_ignore_ascii_case(&alternate[0]) {
+        reference = &reference[1..];
+        alternate = &alternate[1..];
+        position += 1;
+    }
+
+    // only ASCII bytes are removed, so the remainders are valid UTF-8
+    (
+        position,
+        std::str::from_utf8(reference).unwrap(),
+        std::str::from_utf8(alternate).unwrap(),
+    )
+}
+
+/// Parse a comma-separated list of values that must contain exactly `expected` entries. Returns None if the
+/// cardinality does not match or any value is malformed.
+fn parse_value_list<T: std::str::FromStr>(value: &str, expected: usize) -> Option<Vec<T>> {
+    let values = value.split(',').map(|v| v.parse().ok()).collect::<Option<Vec<T>>>()?;
+    if values.len() == expected {
+        Some(values)
+    } else {
+        None
+    }
+}
+
+impl<'a> Iterator for RecordIterator<'a> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_line() {
+            Ok(true) => Some(
+                self.parse_current_record(self.header).map_err(|e| self.located(e, self.buffer.as_bytes())),
+            ),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'a> Iterator for SteppedRecords<'a> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.first_taken {
+            for _ in 1..self.step {
+                match self.records.read_line() {
+                    Ok(true) => {}
+                    Ok(false) => return None,
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+        }
+        self.first_taken = true;
+        self.records.next()
+    }
+}
+
+impl<'a> IntoIterator for &'a VcfFile {
+    type Item = io::Result<VcfRecord>;
+    type IntoIter = IntoRecords<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoRecords(self.records().map_err(Some))
+    }
+}
+
+impl<'a> Iterator for IntoRecords<'a> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            Ok(records) => records.next(),
+            Err(error) => error.take().map(Err),
+        }
+    }
+}
+
+impl Checkpoint {
+    /// Serialize the checkpoint into 16 bytes, suitable for storing alongside the state of a job.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&self.offset.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.line.to_le_bytes());
+        bytes
+    }
+
+    /// Deserialize a checkpoint created by [`Checkpoint::to_bytes`].
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Checkpoint {
+            offset: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            line: u64::from_le_bytes(bytes[8..].try_into().unwrap()),
+        }
+    }
+}
+
+impl<'a> Iterator for MultiRegionIterator<'a> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            let record = match self.records.next()? {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e)),
+            };
+            let Some((regions, first)) = self.contigs.get_mut(&record.chromosome) else {
+                continue;
+            };
+
+            // records are sorted, so regions ending before this record can't overlap any later record either
+            while *first < regions.len() && regions[*first].1 < record.position {
+                *first += 1;
+                self.remaining -= 1;
+            }
+            let end = record.end_position();
+            let overlaps = regions[*first..]
+                .iter()
+                .take_while(|(start, _)| *start <= end)
+                .any(|(_, region_end)| *region_end >= record.position);
+            if overlaps {
+                return Some(Ok(record));
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for RegionIterator<'a> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let records = self.records.as_mut()?;
+        loop {
+            match records.next()? {
+                Ok(record) => {
+                    let same_contig = records.options.contig_matches(&record.chromosome, &self.chromosome);
+                    if !same_contig || record.position > self.end {
+                        self.records = None;
+                        return None;
+                    }
+
+                    if record.end_position() >= self.start {
+                        return Some(Ok(record));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Buffered reader counting the bytes consumed from the inner reader, which excludes what is only buffered.
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.set(self.count.get() + read as u64);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.count.set(self.count.get() + amount as u64);
+        self.inner.consume(amount)
+    }
+}
+
+impl<'a> GroupedRecords<'a> {
+    fn next_record(&mut self) -> Option<io::Result<VcfRecord>> {
+        self.peeked.take().or_else(|| self.records.next())
+    }
+}
+
+impl<'a> Iterator for ChromosomeGroups<'a> {
+    type Item = io::Result<(String, ChromosomeRecords<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut shared = self.records.borrow_mut();
+        loop {
+            match shared.next_record()? {
+                // skip what is left of the previous group
+                Ok(record) if shared.chromosome.as_ref() == Some(&record.chromosome) => {}
+                Ok(record) => {
+                    let chromosome = record.chromosome.clone();
+                    shared.chromosome = Some(chromosome.clone());
+                    shared.peeked = Some(Ok(record));
+
+                    let group = ChromosomeRecords {
+                        records: Rc::clone(&self.records),
+                        chromosome: chromosome.clone(),
+                    };
+                    return Some(Ok((chromosome, group)));
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for ChromosomeRecords<'a> {
+    type Item = io::Result<VcfRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut shared = self.records.borrow_mut();
+        if shared.chromosome.as_ref() != Some(&self.chromosome) {
+            return None;
+        }
+
+        match shared.next_record()? {
+            Ok(record) if record.chromosome != self.chromosome => {
+                shared.peeked = Some(Ok(record));
+                None
+            }
+            record => Some(record),
+        }
+    }
+}
+
+impl SampleInfo {
+    /// Split the FORMAT keys and keep the sample columns unparsed.
+    pub(crate) fn new(format: &str, samples: &str) -> SampleInfo {
+        SampleInfo {
+            format: format.split(':').map(|s| s.into()).collect(),
+            unparsed_info: samples.trim_end_matches(['\n', '\r']).into(),
+        }
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item=Sample<'_>> {
+        fast_split(&self.unparsed_info, '\t' as u8)
+            .map(|s| self.parse_sample(s))
+    }
+
+    fn parse_sample<'a>(&'a self, text: &'a str) -> Sample<'a> {
+        Sample {
+            format: &self.format,
+            unparsed_info: text,
+        }
+    }
+}
+
+impl<'a> Sample<'a> {
+    /// Get an iterator over all entries in the sample info field. The order of the entries is
+    /// defined by the FORMAT column.
+    pub fn entries(&self) -> impl Iterator<Item=&'a str> {
+        fast_split(self.unparsed_info, b':')
+    }
+
+    /// The FORMAT keys of the record this sample belongs to.
+    pub fn format(&self) -> &'a [String] {
+        self.format
+    }
+
+    /// Get the value of the FORMAT field `key`. Returns None if the record doesn't declare the key, or if the
+    /// sample omits trailing fields including this one.
+    pub fn get_field(&self, key: &str) -> Option<&'a str> {
+        let position = self.format.iter().position(|k| k == key)?;
+        self.entries().nth(position)
+    }
+
+    /// Extract the genotype information if present. If the sample has no genotype information, None
+    /// is returned.
+    pub fn get_genotype(&self) -> Option<&'a str> {
+        // GT must be the first FORMAT key if present
+        if self.format.first().is_some_and(|key| key == "GT") && !self.unparsed_info.is_empty() {
+            self.entries().next()
+        } else {
+            None
+        }
+    }
+
+    /// Extract and parse the genotype information if present.
+    pub fn genotype(&self) -> Option<Genotype> {
+        self.get_genotype().map(parse_genotype)
+    }
+
+    /// Get the ploidy of the sample, i.e. the number of alleles in its genotype. Samples of the same record may
+    /// have different ploidies, e.g. on sex chromosomes. If the sample has no genotype information, None is
+    /// returned.
+    pub fn ploidy(&self) -> Option<usize> {
+        self.get_genotype()
+            .map(|genotype| genotype.bytes().filter(|&b| b == b'/' || b == b'|').count() + 1)
+    }
+
+    /// Get the read depth (`DP`) of the sample. Returns None if it is absent, missing or malformed.
+    pub fn depth(&self) -> Option<u32> {
+        self.get_field("DP")?.parse().ok()
+    }
+
+    /// Get the minimum read depth over a gVCF reference block (`MIN_DP`) of the sample, while [`Sample::depth`] is
+    /// the median depth over the block. Returns None if it is absent, missing or malformed, e.g. for variant records.
+    pub fn min_depth(&self) -> Option<u32> {
+        self.get_field("MIN_DP")?.parse().ok()
+    }
+
+    /// Get the strand bias counts (`SB`) of the sample, which somatic callers like Mutect2 write as the reference
+    /// forward, reference reverse, alternate forward and alternate reverse read counts. Returns None if it is
+    /// absent, missing or malformed.
+    pub fn strand_bias(&self) -> Option<Vec<u32>> {
+        self.get_field("SB")?.split(',').map(|count| count.parse().ok()).collect()
+    }
+
+    /// Get the allele fraction of each alternate allele (`AF`) in the sample, e.g. the tumor allele fractions of
+    /// somatic callers. Unlike the `AF` INFO entry, this is estimated from the reads of this sample only. Returns
+    /// None if it is absent, missing or malformed.
+    pub fn allele_fraction(&self) -> Option<Vec<f32>> {
+        self.get_field("AF")?.split(',').map(|fraction| fraction.parse().ok()).collect()
+    }
+
+    /// Get the read depth of each allele (`AD`), including the reference allele. Returns None if it is absent,
+    /// missing or malformed.
+    pub fn allele_depths(&self) -> Option<Vec<u32>> {
+        self.get_field("AD")?.split(',').map(|depth| depth.parse().ok()).collect()
+    }
+}
+
+impl std::fmt::Debug for Sample<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.format.iter().map(String::as_str).zip(self.entries()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn test() {
+        let vcf_file = VcfFile::parse("run/example.vcf.gz", true).expect("failed to open VCF file");
+
+        let start = Instant::now();
+        let mut cells = 0;
+
+        vcf_file
+            .records()
+            .expect("failed to open VCF file")
+            .take(40000)
+            .for_each(|rec| {
+                cells += &rec
+                    .expect("failed to parse VCF record")
+                    .sample_info
+                    .expect("")
+                    .samples()
+                    .count()
+            });
+        println!("read {} cells in {:?}", cells, start.elapsed());
+    }
+
+    /// Write a two-chromosome BGZF file with a mix of short records and long deletions, and index it with both
+    /// tabix and CSI.
+    pub(crate) fn write_indexed_file(path: &str, csi_path: &str) -> io::Result<()> {
+        use std::io::Write;
+
+        let mut writer = BgzfWriter::new(File::create(path)?);
+        let mut tabix = TabixIndexBuilder::new();
+        let mut csi = CsiIndexBuilder::new(12, 6)?;
+        writer.write_all(b"##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n")?;
+        for chromosome in ["1", "2"] {
+            for i in 1..=20000 {
+                let position = i * 50;
+                let (line, end) = if i % 100 == 0 {
+                    let end = position + 5000;
+                    (format!("{}\t{}\t.\tA\t<DEL>\t.\tPASS\tEND={}\n", chromosome, position, end), end)
+                } else {
+                    (format!("{}\t{}\t.\tACGT\tA\t.\tPASS\t.\n", chromosome, position), position + 3)
+                };
+
+                let begin = writer.virtual_offset();
+                writer.write_all(line.as_bytes())?;
+                let chunk = Chunk { begin, end: writer.virtual_offset() };
+                tabix.add(chromosome, position, end, chunk)?;
+                csi.add(chromosome, position, end, chunk)?;
+            }
+        }
+        writer.finish()?;
+        tabix.finish().write(File::create(format!("{}.tbi", path))?)?;
+        std::fs::copy(path, csi_path)?;
+        csi.finish().write(File::create(format!("{}.csi", csi_path))?)?;
+        Ok(())
+    }
+
+    /// Write VCF text into a BGZF file at `path` and create its tabix index. Records must be sorted, and their end
+    /// is derived from the length of REF.
+    fn write_tabix_file(path: &str, text: &str) -> io::Result<()> {
+        use std::io::Write;
+
+        let mut writer = BgzfWriter::new(File::create(path)?);
+        let mut tabix = TabixIndexBuilder::new();
+        for line in text.split_inclusive('\n') {
+            let begin = writer.virtual_offset();
+            writer.write_all(line.as_bytes())?;
+            if !line.starts_with('#') {
+                let fields = line.split('\t').collect::<Vec<_>>();
+                let position = fields[1].parse::<u32>().unwrap();
+                let end = position + fields[3].len() as u32 - 1;
+                tabix.add(fields[0], position, end, Chunk { begin, end: writer.virtual_offset() })?;
+            }
+        }
+        writer.finish()?;
+        tabix.finish().write(File::create(format!("{}.tbi", path))?)
+    }
+
+    #[test]
+    fn test_indexed_query_round_trip() {
+        let path = std::env::temp_dir().join("vcfire_tabix_round_trip.vcf.gz");
+        let csi_path = std::env::temp_dir().join("vcfire_csi_round_trip.vcf.gz");
+        let (path, csi_path) = (path.to_str().unwrap(), csi_path.to_str().unwrap());
+        write_indexed_file(path, csi_path).expect("failed to write indexed file");
+
+        for path in [path, csi_path] {
+            let mut vcf_file = VcfFile::parse(path, true).expect("failed to open VCF file");
+            vcf_file.load_index().expect("failed to load index");
+
+            for (chromosome, start, end) in [("2", 480_000, 520_000), ("1", 1, 1), ("1", 999_990, 2_000_000), ("3", 1, 10)] {
+                let queried = vcf_file
+                    .query(chromosome, start, end)
+                    .unwrap()
+                    .map(|rec| rec.unwrap().position)
+                    .collect::<Vec<_>>();
+                let scanned = vcf_file
+                    .records()
+                    .unwrap()
+                    .map(|rec| rec.unwrap())
+                    .filter(|rec| rec.chromosome == chromosome && rec.position <= end && rec.end_position() >= start)
+                    .map(|rec| rec.position)
+                    .collect::<Vec<_>>();
+                assert_eq!(queried, scanned);
+            }
+        }
+    }
+
+    #[test]
+    fn test_position_index_query() {
+        for path in ["tests/data/windows.vcf", "tests/data/structural_variants.vcf"] {
+            let mut vcf_file = VcfFile::parse(path, false).expect("failed to open VCF file");
+            vcf_file.build_index().expect("failed to build index");
+
+            let regions = [("1", 1, 1), ("1", 2, 1000), ("1", 1200, 1200), ("2", 5000, 10_000), ("3", 1, 10)];
+            for (chromosome, start, end) in regions {
+                let queried = vcf_file
+                    .query(chromosome, start, end)
+                    .unwrap()
+                    .map(|rec| rec.unwrap().position)
+                    .collect::<Vec<_>>();
+                let scanned = vcf_file
+                    .records()
+                    .unwrap()
+                    .map(|rec| rec.unwrap())
+                    .filter(|rec| rec.chromosome == chromosome && rec.position <= end && rec.end_position() >= start)
+                    .map(|rec| rec.position)
+                    .collect::<Vec<_>>();
+                assert_eq!(queried, scanned);
+            }
+        }
+
+        let mut compressed = VcfFile::parse("tests/data/allele_frequency.vcf.gz", true).unwrap();
+        assert!(compressed.build_index().is_err());
+    }
+
+    #[test]
+    fn test_has_variant_at() {
+        let path = "tests/data/structural_variants.vcf";
+        let mut vcf_file = VcfFile::parse(path, false).expect("failed to open VCF file");
+        for indexed in [false, true] {
+            if indexed {
+                vcf_file.build_index().expect("failed to build index");
+            }
+            assert!(vcf_file.has_variant_at("1", 1000).unwrap());
+            assert!(vcf_file.has_variant_at("1", 2000).unwrap());
+            // the deletion spans 1200, but doesn't start there
+            assert!(!vcf_file.has_variant_at("1", 1200).unwrap());
+            assert!(!vcf_file.has_variant_at("2", 1000).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_error_location() {
+        let location = |error: io::Error| {
+            let error = error.get_ref().and_then(|e| e.downcast_ref::<VcfParseError>()).unwrap().clone();
+            match error {
+                VcfParseError::AtLine {
+                    line, field, column, ..
+                } => (line, field, column),
+                error => panic!("error {:?} has no location", error),
+            }
+        };
+
+        let vcf_file = VcfFile::parse("tests/data/malformed_records.vcf", false).expect("failed to open VCF file");
+        let errors = vcf_file.records().unwrap().filter_map(|rec| rec.err()).map(location).collect::<Vec<_>>();
+        assert_eq!(errors, vec![(6, Some("POS"), Some(2)), (9, None, None)]);
+
+        let lazy = vcf_file.lazy_records().unwrap().filter_map(|rec| rec.err()).map(location).collect::<Vec<_>>();
+        assert_eq!(lazy, errors);
+        let mut lending = vcf_file.lending_records().unwrap();
+        let mut lending_errors = Vec::new();
+        while let Some(record) = lending.next() {
+            lending_errors.extend(record.err().map(location));
+        }
+        assert_eq!(lending_errors, errors);
+        #[cfg(feature = "mmap")]
+        {
+            let mapped = vcf_file.mmap().unwrap();
+            let mapped = mapped.records().unwrap().filter_map(|rec| rec.err()).map(location).collect::<Vec<_>>();
+            assert_eq!(mapped, errors);
+        }
+        let mut bytes = vcf_file.byte_records().unwrap();
+        let mut byte_errors = Vec::new();
+        while let Some(record) = bytes.next() {
+            byte_errors.extend(record.err().map(location));
+        }
+        assert_eq!(byte_errors, errors);
+
+        let options = ParseOptions::new().strict(true);
+        let strict = VcfFile::parse_with_options("tests/data/malformed_records.vcf", false, options).unwrap();
+        let errors = strict.records().unwrap().filter_map(|rec| rec.err()).collect::<Vec<_>>();
+        assert_eq!(errors[1].to_string(), "line 7, column QUAL at byte 14: QUAL abc is not a number");
+        let errors = errors.into_iter().map(location).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                (6, Some("POS"), Some(2)),
+                (7, Some("QUAL"), Some(14)),
+                (8, Some("sample"), Some(31)),
+                (9, None, None)
+            ]
+        );
+
+        let error = VcfParseError::MalformedQuality("abc".into()).at_line(7, b"1\t300", &strict.header);
+        assert_eq!((error.line(), error.cause()), (Some(7), &VcfParseError::MalformedQuality("abc".into())));
+        assert_eq!(location(error.into()), (7, Some("QUAL"), None));
+    }
+
+    #[test]
+    fn test_strict_contig_length() {
+        let positions = |strict: bool| {
+            VcfFile::parse_with_options("tests/data/contig_length.vcf", false, ParseOptions::new().strict(strict))
+                .expect("failed to open VCF file")
+                .records()
+                .expect("failed to open VCF file")
+                .map(|rec| rec.map(|rec| rec.position))
+                .collect::<Vec<_>>()
+        };
+
+        let lenient = positions(false);
+        assert!(lenient.iter().all(|rec| rec.is_ok()));
+        assert_eq!(lenient.len(), 4);
+
+        // the second record exceeds the length of chr1, the last one is on a contig without declared length
+        let strict = positions(true);
+        assert_eq!(strict[0].as_ref().unwrap(), &1000);
+        let error = strict[1].as_ref().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.get_ref().and_then(|e| e.downcast_ref::<VcfParseError>()).map(VcfParseError::cause),
+            Some(&VcfParseError::PositionOutOfContig {
+                chromosome: "chr1".into(),
+                position: 1001,
+                length: 1000
+            })
+        );
+        assert_eq!(strict[2].as_ref().unwrap(), &500);
+        assert_eq!(strict[3].as_ref().unwrap(), &5000);
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        for (path, compressed) in [
+            ("tests/data/allele_frequency.vcf", false),
+            ("tests/data/allele_frequency.vcf.gz", true),
+        ] {
+            let vcf_file = VcfFile::parse(path, compressed).expect("failed to open VCF file");
+            let mut records = vcf_file.records().expect("failed to open VCF file");
+            records.nth(1).unwrap().unwrap();
+
+            let checkpoint = Checkpoint::from_bytes(records.checkpoint().to_bytes());
+            assert_eq!(checkpoint.line, 5);
+            let expected = records.map(|rec| rec.unwrap().position).collect::<Vec<_>>();
+
+            let resumed = vcf_file
+                .records_from(&checkpoint)
+                .expect("failed to resume")
+                .map(|rec| rec.unwrap().position)
+                .collect::<Vec<_>>();
+            assert_eq!(resumed, vec![300, 400, 500]);
+            assert_eq!(resumed, expected);
+        }
+    }
+
+    #[test]
+    fn test_split_by_chromosome() {
+        let vcf_file = VcfFile::parse("tests/data/windows.vcf", false).expect("failed to open VCF file");
+        let groups = vcf_file
+            .split_by_chromosome()
+            .unwrap()
+            .map(|group| {
+                let (chromosome, records) = group.unwrap();
+                (chromosome, records.map(|rec| rec.unwrap().position).collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![("1".into(), vec![1, 500, 1000, 1001, 5500]), ("2".into(), vec![5600, 5999])]
+        );
+
+        // groups that are not read to the end are skipped
+        let counts = vcf_file
+            .split_by_chromosome()
+            .unwrap()
+            .map(|group| group.unwrap().1.take(2).count())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![2, 2]);
+
+        let mut groups = vcf_file.split_by_chromosome().unwrap();
+        let (_, mut first) = groups.next().unwrap().unwrap();
+        let (second, _) = groups.next().unwrap().unwrap();
+        assert_eq!(second, "2");
+        assert!(first.next().is_none());
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let vcf_file = VcfFile::parse("tests/data/crlf.vcf", false).expect("failed to open VCF file");
+        assert_eq!(vcf_file.header.contig_length("1"), Some(5000));
+        let records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+
+        for record in &records {
+            let debug = format!("{:?}", record);
+            assert!(!debug.contains("\\r"), "stray carriage return in {}", debug);
+            assert!(!record.filter_status.contains('\r'));
+        }
+        assert_eq!(records[0].end_position(), 1500);
+        assert_eq!(records[1].info, vec![Some(InfoEntry::SNPDatabaseMembership)]);
+    }
+
+    #[test]
+    fn test_estimate_record_count() {
+        let path = std::env::temp_dir().join("vcfire_estimate.vcf.gz");
+        let csi_path = std::env::temp_dir().join("vcfire_estimate_csi.vcf.gz");
+        let (path, csi_path) = (path.to_str().unwrap(), csi_path.to_str().unwrap());
+        write_indexed_file(path, csi_path).expect("failed to write indexed file");
+
+        let uncompressed_path = std::env::temp_dir().join("vcfire_estimate.vcf");
+        let uncompressed_path = uncompressed_path.to_str().unwrap();
+        let mut text = String::new();
+        MultiGzDecoder::new(File::open(path).unwrap()).read_to_string(&mut text).unwrap();
+        std::fs::write(uncompressed_path, text).unwrap();
+
+        for (path, compressed) in [(path, true), (uncompressed_path, false)] {
+            let estimate = VcfFile::parse(path, compressed).unwrap().estimate_record_count().unwrap();
+            assert!((20_000..80_000).contains(&estimate), "estimated {} instead of 40000 records", estimate);
+        }
+
+        // small files are counted exactly
+        let vcf_file = VcfFile::parse("tests/data/allele_frequency.vcf.gz", true).unwrap();
+        assert_eq!(vcf_file.estimate_record_count().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_step_by_record() {
+        let vcf_file = VcfFile::parse("tests/data/quality.vcf", false).expect("failed to open VCF file");
+        let all = vcf_file.records().unwrap().map(|rec| rec.unwrap().position).collect::<Vec<_>>();
+        for step in 1..=all.len() + 1 {
+            let stepped = vcf_file
+                .records()
+                .unwrap()
+                .step_by_record(step)
+                .map(|rec| rec.unwrap().position)
+                .collect::<Vec<_>>();
+            assert_eq!(stepped, all.iter().copied().step_by(step).collect::<Vec<_>>());
+        }
+
+        let positions = vcf_file.records().unwrap().step_by_record(2).map(|rec| rec.unwrap().position);
+        assert_eq!(positions.collect::<Vec<_>>(), vec![100, 300, 500]);
+    }
+
+    #[test]
+    fn test_records_with_header() {
+        let vcf_file = VcfFile::parse("tests/data/sample_fields.vcf", false).expect("failed to open VCF file");
+        let depths = vcf_file
+            .records_with_header()
+            .unwrap()
+            .map(|(header, record)| {
+                let record = record.unwrap();
+                let sample = header.sample_names.as_ref()?.iter().position(|name| name == "S2")?;
+                let depth = record.sample_info.as_ref()?.samples().nth(sample)?.get_field("DP").map(String::from);
+                depth
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(depths, vec![Some(".".to_string()), Some("9".to_string())]);
+    }
+
+    #[test]
+    fn test_query_regions() {
+        let vcf_file = VcfFile::parse("tests/data/gvcf_blocks.vcf", false).expect("failed to open VCF file");
+        let regions = [("2".to_string(), 15, 15), ("1".to_string(), 120, 160), ("1".to_string(), 150, 150)];
+        let coordinates = |regions: &[(String, u32, u32)]| {
+            vcf_file
+                .query_regions(regions)
+                .unwrap()
+                .map(|rec| rec.map(|rec| (rec.chromosome, rec.position)).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            coordinates(&regions),
+            vec![("1".into(), 101), ("1".into(), 150), ("2".into(), 10)]
+        );
+
+        // the union of single-region scans, in file order
+        let regions = [("1".to_string(), 1, 1), ("1".to_string(), 420, 500), ("3".to_string(), 1, 100)];
+        assert_eq!(coordinates(&regions), vec![("1".into(), 1), ("1".into(), 350)]);
+        assert_eq!(coordinates(&[]), vec![]);
+    }
+
+    #[test]
+    fn test_collect_records() {
+        for (path, compressed) in [
+            ("tests/data/allele_frequency.vcf", false),
+            ("tests/data/allele_frequency.vcf.gz", true),
+            ("tests/data/structural_variants.vcf", false),
+        ] {
+            let vcf_file = VcfFile::parse(path, compressed).expect("failed to open VCF file");
+            let records = vcf_file.collect_records().unwrap();
+            assert_eq!(records.len() as u64, vcf_file.count_records().unwrap());
+            assert_eq!(records.len(), records.capacity());
+        }
+
+        let strict = ParseOptions::new().strict(true);
+        let vcf_file = VcfFile::parse_with_options("tests/data/empty_fields.vcf", false, strict).unwrap();
+        assert_eq!(vcf_file.collect_records().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_buffer_size() {
+        for (path, compressed) in [
+            ("tests/data/sample_fields.vcf", false),
+            ("tests/data/allele_frequency.vcf.gz", true),
+        ] {
+            let records = |options: ParseOptions| {
+                VcfFile::parse_with_options(path, compressed, options)
+                    .expect("failed to open VCF file")
+                    .records()
+                    .expect("failed to open VCF file")
+                    .map(|rec| format!("{:?}", rec.unwrap()))
+                    .collect::<Vec<_>>()
+            };
+
+            let expected = records(ParseOptions::new());
+            assert!(!expected.is_empty());
+            for size in [0, 7, 1 << 20] {
+                assert_eq!(records(ParseOptions::new().buffer_size(size)), expected);
+            }
+        }
+    }
+
+    fn record(chromosome: &str, position: u32, reference: &str, alternates: &[&str]) -> VcfRecord {
+        VcfRecord {
+            chromosome: chromosome.into(),
+            position,
+            id: None,
+            reference_bases: reference.into(),
+            alternate_bases: alternates
+                .iter()
+                .map(|alt| if *alt == "." { None } else { Some(alt.to_string()) })
+                .collect(),
+            quality: None,
+            filter_status: "PASS".into(),
+            info: Vec::new(),
+            end: None,
+            sample_info: None,
+        }
+    }
+
+    #[test]
+    fn test_filter_set() {
+        let mut record = record("1", 100, "A", &["G"]);
+        record.filter_status = "q10;s50;q10".into();
+        assert_eq!(record.filter_set(), HashSet::from(["q10", "s50"]));
+        assert!(record.has_filter("q10"));
+        assert!(!record.has_filter("PASS"));
+
+        record.filter_status = ".".into();
+        assert!(record.filter_set().is_empty());
+        assert!(!record.has_filter("."));
+    }
+
+    #[test]
+    fn test_interned_filters() {
+        let vcf_file = VcfFile::parse("tests/data/messy.vcf", false).expect("failed to open VCF file");
+        let filters = vcf_file
+            .records()
+            .unwrap()
+            .filter_map(Result::ok)
+            .map(|rec| rec.filter_status)
+            .collect::<Vec<_>>();
+        assert_eq!(filters[0], "PASS");
+        assert!(matches!(filters[0], Cow::Borrowed(_)));
+        assert_eq!(filters[1], "q10");
+        assert!(matches!(filters[1], Cow::Owned(_)));
+        assert!(filters.iter().all(|filter| matches!(filter, Cow::Borrowed(_)) == (filter == "PASS")));
+    }
+
+    #[test]
+    fn test_variant_key_normalization() {
+        // the same deletion of T at position 101, with different padding
+        let padded = record("1", 100, "ATG", &["AG"]);
+        let shifted = record("1", 99, "CAT", &["C", "CA"]);
+        assert_eq!(padded.variant_key(0), "1:100:AT:A");
+        assert_eq!(shifted.variant_key(1), padded.variant_key(0));
+
+        assert_eq!(record("1", 5, "ACGT", &["aggt"]).variant_key(0), "1:6:C:G");
+        assert_eq!(record("1", 5, "A", &["<DEL>"]).variant_key(0), "1:5:A:<DEL>");
+        assert_eq!(record("1", 5, "A", &["."]).variant_key(0), "1:5:A:.");
+    }
+
+    #[test]
+    fn test_allele_base() {
+        let biallelic = record("1", 100, "A", &["G"]);
+        assert_eq!(biallelic.allele_base(0), Some("A"));
+        assert_eq!(biallelic.allele_base(1), Some("G"));
+        assert_eq!(biallelic.allele_base(2), None);
+
+        assert_eq!(record("1", 100, "A", &["<DEL>"]).allele_base(1), None);
+        assert_eq!(record("1", 100, "A", &["."]).allele_base(1), None);
+    }
+
+    #[test]
+    fn test_spanning_deletion() {
+        let vcf_file = VcfFile::parse("tests/data/allele_frequency.vcf", false).unwrap();
+        let line = "1\t101\t.\tC\tT,*\t.\tPASS\t.\tGT\t0/1\t1/2\t2/2\t0/0";
+        let deletion = VcfRecord::parse(line, &vcf_file.header, &ParseOptions::default()).unwrap();
+        assert!(deletion.has_spanning_deletion());
+        assert_eq!(deletion.alternate_bases, vec![Some("T".into()), Some("*".into())]);
+
+        // the allele of a genotype resolves to the sentinel, instead of a sequence or a missing allele
+        let sample = deletion.sample_info.as_ref().unwrap().samples().nth(2).unwrap();
+        let genotype = sample.genotype().unwrap();
+        let alleles = genotype.alleles.iter().map(|allele| deletion.allele_base(allele.unwrap())).collect::<Vec<_>>();
+        assert_eq!(alleles, vec![Some(VcfRecord::SPANNING_DELETION); 2]);
+        assert_eq!(deletion.allele_base(1), Some("T"));
+        // the spanning deletion is never trimmed like a sequence allele
+        assert_eq!(deletion.variant_key(1), "1:101:C:*");
+
+        assert!(!record("1", 100, "A", &["G"]).has_spanning_deletion());
+        assert!(!record("1", 100, "A", &["<*>"]).has_spanning_deletion());
+    }
+
+    #[test]
+    fn test_remap_genotypes() {
+        let vcf_file = VcfFile::parse("tests/data/allele_frequency.vcf", false).unwrap();
+        let line = "1\t100\t.\tA\tG,T\t.\tPASS\t.\tGT:DP\t0/1:5\t1|2:7\t2/2:3\t./.:1";
+        let genotypes = |record: &VcfRecord| {
+            let sample_info = record.sample_info.as_ref().unwrap();
+            sample_info.samples().map(|sample| sample.entries().collect::<Vec<_>>().join(":")).collect::<Vec<_>>()
+        };
+
+        // swap the alternate alleles
+        let mut record = VcfRecord::parse(line, &vcf_file.header, &ParseOptions::default()).unwrap();
+        record.alternate_bases.swap(0, 1);
+        record.remap_genotypes(&[0, 2, 1]);
+        assert_eq!(genotypes(&record), vec!["0/2:5", "2|1:7", "1/1:3", "./.:1"]);
+        assert_eq!(record.allele_base(2), Some("G"));
+
+        // drop G, whose calls become missing
+        let mut record = VcfRecord::parse(line, &vcf_file.header, &ParseOptions::default()).unwrap();
+        record.alternate_bases.remove(0);
+        record.remap_genotypes(&[0, 2]);
+        assert_eq!(genotypes(&record), vec!["0/.:5", ".|1:7", "1/1:3", "./.:1"]);
+    }
+
+    #[test]
+    fn test_same_locus() {
+        let first = record("1", 100, "A", &["G"]);
+        assert!(first.same_locus(&record("1", 100, "A", &["T"])));
+        assert!(first.same_locus(&record("1", 100, "AC", &["A", "ACC"])));
+        assert!(!first.same_locus(&record("1", 101, "A", &["G"])));
+        assert!(!first.same_locus(&record("2", 100, "A", &["G"])));
+    }
+
+    #[test]
+    fn test_decompose_mnp() {
+        let snps = record("1", 100, "AC", &["GT"]).decompose_mnp();
+        let alleles = snps
+            .iter()
+            .map(|rec| (rec.position, rec.reference_bases.as_str(), rec.alternate_bases[0].as_deref().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(alleles, vec![(100, "A", "G"), (101, "C", "T")]);
+        assert_eq!(snps[1].chromosome, "1");
+
+        // only differing bases become SNPs, and a single differing base is already a SNP
+        let positions = |rec: VcfRecord| rec.decompose_mnp().iter().map(|rec| rec.position).collect::<Vec<_>>();
+        assert_eq!(positions(record("1", 100, "ACGT", &["GCGA"])), vec![100, 103]);
+        assert_eq!(positions(record("1", 100, "ACGT", &["AcTT"])), vec![100]);
+        assert_eq!(positions(record("1", 100, "AC", &["A"])), vec![100]);
+        assert_eq!(positions(record("1", 100, "AC", &["GT", "TT"])), vec![100]);
+    }
+
+    #[test]
+    fn test_missing_info_column() {
+        let records = VcfFile::parse("tests/data/allele_frequency.vcf", false)
+            .expect("failed to open VCF file")
+            .records()
+            .expect("failed to open VCF file")
+            .map(|rec| rec.expect("failed to parse VCF record"))
+            .collect::<Vec<_>>();
+
+        assert_eq!(records[0].info, vec![Some(InfoEntry::AlleleFrequency(vec![0.005]))]);
+        assert!(records[3].info.is_empty());
+        assert!(records[4].info.is_empty());
+    }
+
+    #[test]
+    fn test_single_info_entry() {
+        let vcf_file = VcfFile::parse("tests/data/single_info.vcf", false).expect("failed to open VCF file");
+        let expected = vec![
+            vec![Some(InfoEntry::NonStandard("DP".into(), NonStandardInfoValue::SingleValue("10".into())))],
+            vec![Some(InfoEntry::SNPDatabaseMembership)],
+            vec![Some(InfoEntry::AlleleFrequency(vec![0.1, 0.2]))],
+            vec![Some(InfoEntry::NonStandard(
+                "TAGS".into(),
+                NonStandardInfoValue::ValueList(vec!["a".into(), "b".into()]),
+            ))],
+        ];
+
+        let records = vcf_file.records().unwrap().map(|rec| rec.unwrap().info).collect::<Vec<_>>();
+        assert_eq!(records, expected);
+
+        let lazy = vcf_file.lazy_records().unwrap().map(|rec| rec.unwrap().get_info().to_vec()).collect::<Vec<_>>();
+        assert_eq!(lazy, expected);
+
+        let mut lending = vcf_file.lending_records().unwrap();
+        let record = lending.next().unwrap().unwrap();
+        assert_eq!(record.info_entries().collect::<Vec<_>>(), vec!["DP=10"]);
+    }
+
+    #[test]
+    fn test_sample_ploidy() {
+        let format = vec!["GT".to_string(), "DP".to_string()];
+        let ploidy = |unparsed_info| Sample { format: &format, unparsed_info }.ploidy();
+        assert_eq!(ploidy("1"), Some(1));
+        assert_eq!(ploidy("0/1:12"), Some(2));
+        assert_eq!(ploidy("0|1"), Some(2));
+        assert_eq!(ploidy("0/0/1"), Some(3));
+        assert_eq!(ploidy(""), None);
+    }
+
+    #[test]
+    fn test_sample_line_terminator() {
+        let records = VcfFile::parse("tests/data/sample_whitespace.vcf", false)
+            .expect("failed to open VCF file")
+            .records()
+            .expect("failed to open VCF file")
+            .map(|rec| rec.expect("failed to parse VCF record").sample_info.unwrap())
+            .collect::<Vec<_>>();
+
+        // only the line terminators are removed, the whitespace within the sample values is kept
+        let samples = |info: &SampleInfo| info.samples().map(|s| s.unparsed_info.to_string()).collect::<Vec<_>>();
+        assert_eq!(samples(&records[0]), vec!["0/1:ab", "1/1:c "]);
+        assert_eq!(samples(&records[1]), vec![" 0/0:d", "0/1:e"]);
+    }
+
+    #[test]
+    fn test_consistent_end() {
+        let vcf_file = VcfFile::parse("tests/data/quality.vcf", false).unwrap();
+        let parse = |line: &str, options: &ParseOptions| VcfRecord::parse(line, &vcf_file.header, options);
+        let lenient = ParseOptions::default();
+        let strict = ParseOptions::new().strict(true);
+
+        let consistent = "1\t100\t.\tACGT\tA\t30\tPASS\tEND=103";
+        assert!(parse(consistent, &lenient).unwrap().consistent_end());
+        assert!(parse(consistent, &strict).is_ok());
+
+        let inconsistent = "1\t100\t.\tACGT\tA\t30\tPASS\tEND=150";
+        assert!(!parse(inconsistent, &lenient).unwrap().consistent_end());
+        assert_eq!(
+            parse(inconsistent, &strict).unwrap_err(),
+            VcfParseError::InconsistentEnd {
+                end: 150,
+                reference_end: 103
+            }
+        );
+
+        // END of symbolic alleles extends beyond REF
+        assert!(parse("1\t100\t.\tA\t<DEL>\t30\tPASS\tEND=150", &strict).unwrap().consistent_end());
+    }
+
+    #[test]
+    fn test_alt_count() {
+        let biallelic = record("1", 100, "A", &["G"]);
+        assert_eq!(biallelic.alt_count(), 1);
+        assert!(!biallelic.is_multiallelic());
+
+        let triallelic = record("1", 100, "A", &["G", "T"]);
+        assert_eq!(triallelic.alt_count(), 2);
+        assert!(triallelic.is_multiallelic());
+
+        let no_alt = record("1", 100, "A", &["."]);
+        assert_eq!(no_alt.alt_count(), 0);
+        assert!(!no_alt.is_multiallelic());
+    }
+
+    #[test]
+    fn test_sample_presence() {
+        let genotyped = VcfFile::parse("tests/data/allele_frequency.vcf", false).expect("failed to open VCF file");
+        let record = genotyped.records().unwrap().next().unwrap().unwrap();
+        assert!(record.has_samples());
+        assert_eq!(record.sample_count(&genotyped.header), 4);
+
+        let sites_only = VcfFile::parse("tests/data/quality.vcf", false).expect("failed to open VCF file");
+        let record = sites_only.records().unwrap().next().unwrap().unwrap();
+        assert!(!record.has_samples());
+        assert_eq!(record.sample_count(&sites_only.header), 0);
+    }
+
+    #[test]
+    fn test_parse_record_ids() {
+        assert_eq!(RecordIds::parse("."), None);
+
+        let single = RecordIds::parse("rs6054257").unwrap();
+        assert_eq!(single, RecordIds::One("rs6054257".into()));
+        assert_eq!(single.iter().collect::<Vec<_>>(), vec!["rs6054257"]);
+
+        let multiple = RecordIds::parse("rs1;rs2;COSM3").unwrap();
+        assert_eq!(multiple.len(), 3);
+        assert!(multiple.contains("rs2"));
+        assert_eq!(multiple.iter().collect::<Vec<_>>(), vec!["rs1", "rs2", "COSM3"]);
+
+        // empty identifiers are dropped in lenient mode, and reported in strict mode
+        assert_eq!(RecordIds::parse(";rs1;"), Some(RecordIds::One("rs1".into())));
+        assert_eq!(RecordIds::parse("rs1;;rs2"), Some(RecordIds::Many(vec!["rs1".into(), "rs2".into()])));
+        assert_eq!(RecordIds::parse(";"), None);
+        assert_eq!(RecordIds::validate("rs1;rs2"), Ok(()));
+        assert_eq!(RecordIds::validate(";rs1;"), Err(VcfParseError::MalformedId(";rs1;".into())));
+
+        let vcf_file = VcfFile::parse("tests/data/quality.vcf", false).unwrap();
+        let line = "1\t100\t;rs1;\tA\tG\t30\tPASS\t.";
+        let lenient = VcfRecord::parse(line, &vcf_file.header, &ParseOptions::default()).unwrap();
+        assert_eq!(lenient.id, Some(RecordIds::One("rs1".into())));
+        let strict = VcfRecord::parse(line, &vcf_file.header, &ParseOptions::new().strict(true));
+        assert_eq!(strict.unwrap_err(), VcfParseError::MalformedId(";rs1;".into()));
+    }
+
+    #[test]
+    fn test_validate_genotypes() {
+        let path = "tests/data/genotype_alleles.vcf";
+        let vcf_file = VcfFile::parse(path, false).expect("failed to open VCF file");
+        let results = vcf_file.records().unwrap().map(|rec| rec.unwrap().validate_genotypes()).collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            vec![
+                Ok(()),
+                Err(VcfParseError::AlleleIndexOutOfRange {
+                    sample: 0,
+                    allele: 3,
+                    allele_count: 2
+                }),
+                Ok(()),
+                Err(VcfParseError::AlleleIndexOutOfRange {
+                    sample: 1,
+                    allele: 1,
+                    allele_count: 1
+                }),
+            ]
+        );
+
+        let strict = VcfFile::parse_with_options(path, false, ParseOptions::new().strict(true)).unwrap();
+        let valid = strict.records().unwrap().map(|rec| rec.is_ok()).collect::<Vec<_>>();
+        assert_eq!(valid, vec![true, false, true, false]);
+
+        let padded = "1\t100\t.\tA\tG\t.\tPASS\t.\tGT\t0 | 1\t1/1";
+        let lenient = VcfRecord::parse(padded, &vcf_file.header, &ParseOptions::default()).unwrap();
+        let genotype = lenient.sample_info.as_ref().unwrap().samples().next().unwrap().genotype().unwrap();
+        assert_eq!(genotype.alleles, vec![Some(0), Some(1)]);
+        assert_eq!(
+            VcfRecord::parse(padded, &vcf_file.header, &ParseOptions::new().strict(true)).unwrap_err(),
+            VcfParseError::MalformedGenotype("0 | 1".into())
+        );
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let vcf_file = VcfFile::parse("tests/data/sample_fields.vcf", false).unwrap();
+        let header = &vcf_file.header;
+        let parse = |line: &str| VcfRecord::parse(line, header, &ParseOptions::default()).unwrap().heap_size();
+        let samples = "\t".repeat(header.samples().len() - 1);
+
+        let small = parse(&format!("1\t100\t.\tA\tG\t.\tPASS\t.\tGT\t{}", samples));
+        let more_info = parse(&format!("1\t100\t.\tA\tG\t.\tPASS\tDP=10;AF=0.5;TAG=x\tGT\t{}", samples));
+        let more_samples = parse(&format!("1\t100\t.\tA\tG\t.\tPASS\t.\tGT:DP\t0/1:10{}", samples));
+        assert!(small >= "1AGGT".len());
+        assert!(more_info > small);
+        assert!(more_samples > small);
+
+        // the common FILTER values are not allocated
+        let filtered = parse(&format!("1\t100\t.\tA\tG\t.\tq10\t.\tGT\t{}", samples));
+        assert_eq!(filtered, small + 3);
+    }
+
+    #[test]
+    fn test_missing_values() {
+        let vcf_file = VcfFile::parse("tests/data/quality.vcf", false).unwrap();
+        let line = "1\t100\t-\tA\tG,-\t-\tPASS\t-";
+        let dash = ParseOptions::new().missing_values(&["-"]).strict(true);
+        let record = VcfRecord::parse(line, &vcf_file.header, &dash).unwrap();
+        assert_eq!(record.quality, None);
+        assert_eq!(record.id, None);
+        assert_eq!(record.alternate_bases, vec![Some("G".to_string()), None]);
+        assert!(record.info.is_empty());
+
+        // without configuring it, `-` is a malformed quality
+        let strict = VcfRecord::parse(line, &vcf_file.header, &ParseOptions::new().strict(true));
+        assert_eq!(strict.unwrap_err(), VcfParseError::MalformedQuality("-".into()));
+
+        let lending_options = ParseOptions::new().missing_values(&["-", "NA"]);
+        let lending = VcfRecordRef::parse("1\t100\t.\tA\tG\tNA\tPASS\t.", &vcf_file.header, &lending_options);
+        assert_eq!(lending.unwrap().quality, None);
+    }
+
+    #[test]
+    fn test_record_sub_parsers() {
+        let normalizer = ParseOptions::new().contig_normalizer(|contig| contig.trim_start_matches("chr").into());
+        let strict = ParseOptions::new().strict(true);
+        assert_eq!(VcfRecord::parse_chromosome("chr1", &ParseOptions::default()), Ok("chr1".into()));
+        assert_eq!(VcfRecord::parse_chromosome("chr1", &normalizer), Ok("1".into()));
+        assert_eq!(VcfRecord::parse_chromosome("", &strict), Err(VcfParseError::EmptyField("CHROM")));
+
+        assert_eq!(VcfRecord::parse_position("12345"), Ok(12345));
+        assert_eq!(VcfRecord::parse_position("-5"), Err(VcfParseError::MalformedPosition("-5".into())));
+        assert!(VcfRecord::parse_position("1e3").is_err());
+        assert!(VcfRecord::parse_position("").is_err());
+
+        assert_eq!(RecordIds::parse("rs1;rs2").map(|ids| ids.len()), Some(2));
+        assert_eq!(RecordIds::parse("."), None);
+        let ids = VcfRecord::parse_id("rs1;rs2", &strict).unwrap();
+        assert_eq!(ids.as_ref().map(|ids| ids.iter().collect::<Vec<_>>()), Some(vec!["rs1", "rs2"]));
+        assert_eq!(VcfRecord::parse_id(";rs1", &strict), Err(VcfParseError::MalformedId(";rs1".into())));
+
+        let options = ParseOptions::default();
+        assert_eq!(
+            VcfRecord::parse_alts("A,<DEL>", &options),
+            Ok(vec![Some("A".to_string()), Some("<DEL>".to_string())])
+        );
+        assert_eq!(VcfRecord::parse_alts(".", &options), Ok(vec![None]));
+        assert_eq!(VcfRecord::parse_alts("A,", &options), Ok(vec![Some("A".to_string()), Some("".to_string())]));
+        assert_eq!(VcfRecord::parse_alts("A,", &strict), Err(VcfParseError::EmptyField("ALT")));
+        let dash = ParseOptions::new().missing_values(&["-"]);
+        assert_eq!(VcfRecord::parse_alts("A,-", &dash), Ok(vec![Some("A".to_string()), None]));
+
+        let header = VcfFile::parse("tests/data/quality.vcf", false).unwrap().header;
+        assert_eq!(VcfRecord::parse_info(".", 1, &header, true), Ok(vec![]));
+        assert_eq!(
+            VcfRecord::parse_info("AF=0.5;AF=0.1,0.2;DB", 1, &header, true),
+            Ok(vec![
+                Some(InfoEntry::AlleleFrequency(vec![0.5])),
+                None,
+                Some(InfoEntry::SNPDatabaseMembership)
+            ])
+        );
+        assert_eq!(VcfRecord::parse_info("DB;;AF=0.5", 1, &header, false).map(|info| info.len()), Ok(3));
+        assert_eq!(VcfRecord::parse_info("DB;;AF=0.5", 1, &header, true), Err(VcfParseError::MalformedInfo("".into())));
+        assert_eq!(VcfRecord::parse_info("=5", 1, &header, true), Err(VcfParseError::MalformedInfo("=5".into())));
+
+        let samples = VcfRecord::parse_samples("GT:DP", "0/1:12\t./.\r\n", true).unwrap();
+        assert_eq!(samples.format, vec!["GT", "DP"]);
+        assert_eq!(samples.samples().map(|sample| sample.depth()).collect::<Vec<_>>(), vec![Some(12), None]);
+        let formatless = VcfRecord::parse_samples("", "0/1", true).err();
+        assert_eq!(formatless, Some(VcfParseError::EmptyField("FORMAT")));
+
+        // a malformed POS is reported even outside of strict mode
+        let error = VcfRecord::parse("1\tabc\t.\tA\tG\t.\tPASS\t.", &header, &ParseOptions::default());
+        assert_eq!(error.unwrap_err(), VcfParseError::MalformedPosition("abc".into()));
+    }
+
+    #[test]
+    fn test_parse_quality() {
+        assert_eq!(VcfRecord::parse_quality("30"), Ok(Some(30.0)));
+        assert_eq!(VcfRecord::parse_quality("29.5"), Ok(Some(29.5)));
+        assert_eq!(VcfRecord::parse_quality("1e2"), Ok(Some(100.0)));
+        assert_eq!(VcfRecord::parse_quality("."), Ok(None));
+        assert_eq!(VcfRecord::parse_quality("abc"), Err(VcfParseError::MalformedQuality("abc".into())));
+        assert!(VcfRecord::parse_quality("inf").is_err());
+
+        let vcf_file = VcfFile::parse("tests/data/quality.vcf", false).unwrap();
+        let line = "1\t100\t.\tA\tG\tabc\tPASS\t.";
+        let lenient = VcfRecord::parse(line, &vcf_file.header, &ParseOptions::default()).unwrap();
+        assert_eq!(lenient.quality, None);
+        let strict = VcfRecord::parse(line, &vcf_file.header, &ParseOptions::new().strict(true));
+        assert_eq!(strict.unwrap_err(), VcfParseError::MalformedQuality("abc".into()));
+    }
+
+    #[test]
+    fn test_space_delimited_record() {
+        let vcf_file = VcfFile::parse("tests/data/sample_fields.vcf", false).unwrap();
+        let options = ParseOptions::default();
+        let error = VcfRecord::parse("1 100 . A G 30 PASS . GT 0/1 1/1", &vcf_file.header, &options).unwrap_err();
+        assert_eq!(
+            error,
+            VcfParseError::MissingColumns {
+                expected: 10,
+                found: 1,
+                space_delimited: true
+            }
+        );
+        assert!(error.to_string().contains("separated by tabs, not spaces"));
+
+        let truncated = VcfRecord::parse("1\t100\t.\tA\tG", &vcf_file.header, &options).unwrap_err();
+        assert!(!truncated.to_string().contains("spaces"));
+    }
+
+    #[test]
+    fn test_empty_mandatory_fields() {
+        let records = |strict: bool| {
+            VcfFile::parse_with_options("tests/data/empty_fields.vcf", false, ParseOptions::new().strict(strict))
+                .expect("failed to open VCF file")
+                .records()
+                .expect("failed to open VCF file")
+                .collect::<Vec<_>>()
+        };
+        let parse_error = |rec: &io::Result<VcfRecord>| {
+            rec.as_ref()
+                .unwrap_err()
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<VcfParseError>())
+                .map(|e| e.cause().clone())
+        };
+
+        let lenient = records(false);
+        assert!(lenient.iter().all(|rec| rec.is_ok()));
+        assert_eq!(lenient[3].as_ref().unwrap().quality, None);
+
+        let strict = records(true);
+        assert_eq!(parse_error(&strict[0]), Some(VcfParseError::EmptyField("CHROM")));
+        assert_eq!(parse_error(&strict[1]), Some(VcfParseError::EmptyField("REF")));
+        assert_eq!(parse_error(&strict[2]), Some(VcfParseError::EmptyField("ALT")));
+        assert_eq!(strict[3].as_ref().unwrap().quality, None);
+        assert_eq!(parse_error(&strict[4]), Some(VcfParseError::EmptyField("ALT")));
+    }
+
+    #[test]
+    fn test_skip_large_header() {
+        let path = std::env::temp_dir().join("vcfire_large_header.vcf.gz");
+        let path = path.to_str().unwrap();
+        // the header spans multiple BGZF blocks
+        let contigs = (0..5000).map(|i| format!("##contig=<ID=ctg{},length=1000>\n", i)).collect::<String>();
+        write_tabix_file(
+            path,
+            &format!(
+                "##fileformat=VCFv4.2\n{}#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+                 ctg0\t100\t.\tA\tG\t.\tPASS\t.\nctg4999\t200\t.\tA\tG\t.\tPASS\t.\n",
+                contigs
+            ),
+        )
+        .expect("failed to write indexed file");
+
+        let vcf_file = VcfFile::parse(path, true).expect("failed to open VCF file");
+        assert!(vcf_file.header.size > crate::bgzf::MAX_BLOCK_SIZE);
+        let records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        let coordinates = records.iter().map(|rec| (rec.chromosome.as_str(), rec.position)).collect::<Vec<_>>();
+        assert_eq!(coordinates, vec![("ctg0", 100), ("ctg4999", 200)]);
+    }
+
+    #[test]
+    fn test_contig_normalizer() {
+        let path = std::env::temp_dir().join("vcfire_contig_normalizer.vcf.gz");
+        let path = path.to_str().unwrap();
+        write_tabix_file(
+            path,
+            "##fileformat=VCFv4.2\n##contig=<ID=chr1,length=1000>\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+             chr1\t100\t.\tA\tG\t.\tPASS\t.\nchr1\t200\t.\tA\tG\t.\tPASS\t.\nchr2\t150\t.\tA\tG\t.\tPASS\t.\n",
+        )
+        .expect("failed to write indexed file");
+
+        let options = ParseOptions::new()
+            .strict(true)
+            .contig_normalizer(|chromosome| chromosome.strip_prefix("chr").unwrap_or(chromosome).into());
+        let mut vcf_file = VcfFile::parse_with_options(path, true, options).expect("failed to open VCF file");
+        vcf_file.load_index().expect("failed to load index");
+        assert_eq!(vcf_file.header.contig_length("1"), Some(1000));
+
+        let chromosomes = vcf_file
+            .records()
+            .unwrap()
+            .map(|rec| rec.unwrap().chromosome)
+            .collect::<Vec<_>>();
+        assert_eq!(chromosomes, vec!["1", "1", "2"]);
+
+        let queried = vcf_file
+            .query("1", 150, 250)
+            .unwrap()
+            .map(|rec| rec.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(queried.len(), 1);
+        assert_eq!((queried[0].chromosome.as_str(), queried[0].position), ("1", 200));
+        assert_eq!(vcf_file.query("2", 1, 1000).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_loose_contig_matching() {
+        let path = std::env::temp_dir().join("vcfire_loose_contig_matching.vcf.gz");
+        let path = path.to_str().unwrap();
+        write_tabix_file(
+            path,
+            "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+             chr1\t100\t.\tA\tG\t.\tPASS\t.\nchr1\t200\t.\tA\tG\t.\tPASS\t.\nchr2\t150\t.\tA\tG\t.\tPASS\t.\n",
+        )
+        .expect("failed to write indexed file");
+
+        let mut exact = VcfFile::parse(path, true).expect("failed to open VCF file");
+        exact.load_index().unwrap();
+        assert_eq!(exact.query("1", 1, 1000).unwrap().count(), 0);
+
+        let options = ParseOptions::new().loose_contig_matching(true);
+        let mut loose = VcfFile::parse_with_options(path, true, options).expect("failed to open VCF file");
+        loose.load_index().unwrap();
+        let queried = loose.query("1", 1, 1000).unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        assert_eq!(queried.len(), 2);
+        assert_eq!(queried[0].chromosome, "chr1");
+        assert_eq!(loose.query("CHR2", 1, 1000).unwrap().count(), 1);
+        assert_eq!(loose.query("chr1", 150, 1000).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_parse_structural_variant_info() {
+        let records = VcfFile::parse("tests/data/structural_variants.vcf", false)
+            .expect("failed to open VCF file")
+            .records()
+            .expect("failed to open VCF file")
+            .map(|rec| rec.expect("failed to parse VCF record"))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            records[0].info,
+            vec![
+                Some(InfoEntry::StructuralVariantType("DEL".into())),
+                Some(InfoEntry::StructuralVariantLength(vec![-500])),
+                Some(InfoEntry::PositionConfidenceInterval(-10, 10)),
+                Some(InfoEntry::End(1500)),
+            ]
+        );
+        assert_eq!(records[0].end_position(), 1500);
+
+        // SVLEN needs one value per ALT allele, CIEND exactly two values
+        assert_eq!(
+            records[1].info,
+            vec![
+                Some(InfoEntry::StructuralVariantType("INS".into())),
+                None,
+                Some(InfoEntry::EndConfidenceInterval(-5, 20)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_has_format_field() {
+        let vcf_file = VcfFile::parse("tests/data/genotype_alleles.vcf", false).expect("failed to open VCF file");
+        let records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        assert!(records[2].has_format_field("DP"));
+        assert!(records[2].has_format_field("GT"));
+        assert!(!records[2].has_format_field("AD"));
+        assert!(!records[0].has_format_field("DP"));
+
+        let sites_only = VcfFile::parse("tests/data/quality.vcf", false).unwrap();
+        assert!(!sites_only.records().unwrap().next().unwrap().unwrap().has_format_field("GT"));
+    }
+
+    #[test]
+    fn test_total_allele_depths() {
+        let vcf_file = VcfFile::parse("tests/data/allele_depths.vcf", false).expect("failed to open VCF file");
+        let totals = vcf_file
+            .records()
+            .unwrap()
+            .map(|rec| rec.unwrap().total_allele_depths())
+            .collect::<Vec<_>>();
+        assert_eq!(totals, vec![Some(vec![18, 12]), Some(vec![20, 7, 3]), None]);
+    }
+
+    #[test]
+    fn test_sample_depths() {
+        let vcf_file = VcfFile::parse("tests/data/depths.vcf", false).expect("failed to open VCF file");
+        let records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        let depths = records
+            .iter()
+            .map(|rec| (rec.info_depth(), rec.sum_sample_depths(), rec.consistent_depth()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            depths,
+            vec![
+                (Some(20), Some(18), true),
+                (Some(10), Some(17), false),
+                (None, Some(18), true),
+                (Some(5), None, true)
+            ]
+        );
+
+        let options = ParseOptions::default().strict(true);
+        let strict = VcfFile::parse_with_options("tests/data/depths.vcf", false, options).unwrap();
+        let errors = strict.records().unwrap().map(|rec| rec.err().map(|e| e.to_string())).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                None,
+                Some(concat!(
+                    "line 6, column INFO at byte 19: ",
+                    "sample depths sum to 17, more than the combined depth DP=10"
+                ).into()),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_min_depth() {
+        let vcf_file = VcfFile::parse("tests/data/gvcf_min_depth.vcf", false).expect("failed to open VCF file");
+        let depths = vcf_file
+            .records()
+            .unwrap()
+            .map(|rec| {
+                let rec = rec.unwrap();
+                let samples = rec.sample_info.as_ref().unwrap().samples().collect::<Vec<_>>();
+                samples.iter().map(|sample| (sample.depth(), sample.min_depth())).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            depths,
+            vec![
+                vec![(Some(25), Some(18)), (Some(30), None)],
+                vec![(Some(30), None), (Some(28), None)]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_somatic_fields() {
+        let vcf_file = VcfFile::parse("tests/data/somatic.vcf", false).expect("failed to open VCF file");
+        let records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+
+        let samples = records[0].sample_info.as_ref().unwrap().samples().collect::<Vec<_>>();
+        assert_eq!(samples[0].strand_bias(), Some(vec![20, 20, 0, 0]));
+        assert_eq!(samples[0].allele_fraction(), Some(vec![0.024]));
+        assert_eq!(samples[1].strand_bias(), Some(vec![15, 15, 6, 4]));
+        assert_eq!(samples[1].allele_fraction(), Some(vec![0.25]));
+        assert_eq!(samples[1].allele_depths(), Some(vec![30, 10]));
+
+        let samples = records[1].sample_info.as_ref().unwrap().samples().collect::<Vec<_>>();
+        assert_eq!((samples[0].strand_bias(), samples[0].allele_fraction()), (None, None));
+        assert_eq!(samples[1].allele_fraction(), Some(vec![0.1, 0.2]));
+        assert_eq!(samples[1].strand_bias(), None);
+    }
+
+    #[test]
+    fn test_sample_fields_by_format() {
+        let vcf_file = VcfFile::parse("tests/data/sample_fields.vcf", false).expect("failed to open VCF file");
+        let record = vcf_file.records().unwrap().next().unwrap().unwrap();
+        let samples = record.sample_info.as_ref().unwrap().samples().collect::<Vec<_>>();
+
+        assert_eq!(samples[0].depth(), Some(14));
+        assert_eq!(samples[0].get_field("GQ"), Some("48"));
+        assert_eq!(samples[0].get_genotype(), Some("0/1"));
+        assert_eq!(samples[1].depth(), None);
+        assert_eq!(samples[1].get_field("HQ"), None);
+        assert_eq!(samples[0].get_field("AD"), None);
+        assert_eq!(format!("{:?}", samples[1]), r#"{"GT": "1|1", "DP": "."}"#);
+
+        // without a leading GT key, the first field is not a genotype
+        let record = vcf_file.records().unwrap().nth(1).unwrap().unwrap();
+        let sample = record.sample_info.as_ref().unwrap().samples().next().unwrap();
+        assert_eq!(sample.get_genotype(), None);
+        assert_eq!(sample.depth(), Some(7));
+    }
+
+    #[test]
+    fn test_iterate_file_reference() {
+        let vcf_file = VcfFile::parse("tests/data/allele_frequency.vcf", false).expect("failed to open VCF file");
+
+        let mut positions = Vec::new();
+        for record in &vcf_file {
+            positions.push(record.expect("failed to parse VCF record").position);
+        }
+        assert_eq!(positions, vec![100, 200, 300, 400, 500]);
+
+        // errors while opening the file are yielded once
+        let mut vcf_file = vcf_file;
+        vcf_file.source = Source::Path("tests/data/missing.vcf".into());
+        let results = (&vcf_file).into_iter().collect::<Vec<_>>();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_reader_source() {
+        for (path, compressed) in [
+            ("tests/data/allele_frequency.vcf", false),
+            ("tests/data/allele_frequency.vcf.gz", true),
+        ] {
+            let from_path = VcfFile::parse(path, compressed).expect("failed to open VCF file");
+            let data = std::fs::read(path).unwrap();
+            let from_reader = VcfFile::from_reader(Cursor::new(data), compressed).expect("failed to read VCF file");
+            assert_eq!(from_path.path(), Some(path));
+            assert_eq!(from_reader.path(), None);
+            assert_eq!(from_reader.header.samples(), from_path.header.samples());
+
+            let mut records = from_path.records().unwrap();
+            records.next();
+            let checkpoint = records.checkpoint();
+            let positions = |vcf_file: &VcfFile| vcf_file.records().unwrap().map(|rec| rec.unwrap().position).collect();
+            let expected: Vec<u32> = positions(&from_path);
+            assert_eq!(positions(&from_reader), expected);
+
+            // the records are streamed, so the reader can't be read again
+            let kind = |error: Option<io::Error>| error.map(|e| e.kind());
+            assert_eq!(kind(from_reader.records().err()), Some(io::ErrorKind::InvalidInput));
+            assert_eq!(kind(from_reader.records_from(&checkpoint).err()), Some(io::ErrorKind::InvalidInput));
+            assert_eq!(from_reader.estimate_record_count().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+
+        let path = "tests/data/unsorted_contigs.vcf";
+        let mut from_reader = VcfFile::from_reader(File::open(path).unwrap(), false).unwrap();
+        assert_eq!(from_reader.load_index().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(from_reader.build_index().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(from_reader.collect_records().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_end_column() {
+        let vcf_file = VcfFile::parse("tests/data/end_column.vcf", false).expect("failed to open VCF file");
+        assert!(vcf_file.header.has_end_column);
+
+        let records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        let ends = records.iter().map(|rec| (rec.end, rec.end_position(), rec.conflicting_end())).collect::<Vec<_>>();
+        assert_eq!(
+            ends,
+            vec![
+                (Some(1500), 1500, false),
+                (Some(2400), 2500, true),
+                (Some(3200), 3200, false),
+                (None, 4000, false)
+            ]
+        );
+        assert_eq!(records[0].sample_info.as_ref().unwrap().format, vec!["GT"]);
+
+        let issues = vcf_file.validate().unwrap();
+        assert!(issues.contains(&ValidationIssue {
+            line: 5,
+            kind: IssueKind::ConflictingEnd
+        }));
+    }
+
+    #[test]
+    fn test_format_without_samples() {
+        for strict in [false, true] {
+            let options = ParseOptions::default().strict(strict);
+            let vcf_file = VcfFile::parse_with_options("tests/data/format_without_samples.vcf", false, options)
+                .expect("failed to open VCF file");
+            assert_eq!(vcf_file.header.sample_names, Some(vec![]));
+
+            // the second record ends with an empty sample column
+            let records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+            assert_eq!(records.len(), 2);
+            for record in &records {
+                let sample_info = record.sample_info.as_ref().unwrap();
+                assert_eq!(sample_info.format, vec!["GT"]);
+                assert_eq!(sample_info.samples().count(), 0);
+            }
+
+            let mut lending = vcf_file.lending_records().unwrap();
+            assert_eq!(lending.next().unwrap().unwrap().samples, Some(""));
+        }
+    }
+
+    #[test]
+    fn test_lowercase_column_header() {
+        let vcf_file = VcfFile::parse("tests/data/lowercase_header.vcf", false).expect("failed to open VCF file");
+        assert!(!vcf_file.header.has_end_column);
+        assert_eq!(
+            vcf_file.header.sample_names,
+            Some(vec!["sample1".to_string(), "sample2".to_string()])
+        );
+
+        let record = vcf_file.records().unwrap().next().unwrap().unwrap();
+        let genotypes = record
+            .sample_info
+            .as_ref()
+            .unwrap()
+            .samples()
+            .map(|s| s.get_genotype())
+            .collect::<Vec<_>>();
+        assert_eq!(genotypes, vec![Some("0/1"), Some("1/1")]);
+    }
+
+    #[test]
+    fn test_is_sorted() {
+        let sorted = |path| VcfFile::parse(path, false).unwrap().is_sorted().unwrap();
+        assert!(sorted("tests/data/windows.vcf"));
+        assert!(sorted("tests/data/allele_frequency.vcf"));
+        assert!(!sorted("tests/data/unsorted_positions.vcf"));
+        assert!(!sorted("tests/data/unsorted_contigs.vcf"));
+    }
+
+    #[test]
+    fn test_parse_strand_allele_depth() {
+        assert_eq!(
+            InfoEntry::parse("ADF=10,2", 1, &[]),
+            Some(InfoEntry::ForwardAlleleReadDepth(vec![10, 2]))
+        );
+        assert_eq!(
+            InfoEntry::parse("ADR=8,3", 1, &[]),
+            Some(InfoEntry::ReverseAlleleReadDepth(vec![8, 3]))
+        );
+        assert_eq!(
+            InfoEntry::parse("AD=18,5", 1, &[]),
+            Some(InfoEntry::TotalAlleleReadDepth(vec![18, 5]))
+        );
+    }
+
+    #[test]
+    fn test_parse_allele_depth_cardinality() {
+        // Number=R requires one value per allele including the reference
+        assert_eq!(InfoEntry::parse("ADF=10,2", 2, &[]), None);
+        assert_eq!(InfoEntry::parse("ADR=8", 1, &[]), None);
+        assert_eq!(InfoEntry::parse("AD", 1, &[]), None);
+        assert_eq!(
+            InfoEntry::parse("DP4=1,2,3,4", 1, &[]),
+            Some(InfoEntry::NonStandard(
+                "DP4".into(),
+                NonStandardInfoValue::ValueList(vec!["1".into(), "2".into(), "3".into(), "4".into()])
+            ))
+        );
+    }
+
+    #[test]
+    fn test_subset_allele() {
+        let frequencies = InfoEntry::parse("AF=0.1,0.25", 2, &[]).unwrap();
+        assert_eq!(frequencies.subset_allele(1), InfoEntry::AlleleFrequency(vec![0.25]));
+
+        let depths = InfoEntry::parse("AD=20,5,3", 2, &[]).unwrap();
+        assert_eq!(depths.subset_allele(0), InfoEntry::TotalAlleleReadDepth(vec![20, 5]));
+        assert_eq!(depths.subset_allele(1), InfoEntry::TotalAlleleReadDepth(vec![20, 3]));
+
+        assert_eq!(InfoEntry::End(100).subset_allele(1), InfoEntry::End(100));
+    }
+
+    #[test]
+    fn test_declared_string_info() {
+        let vcf_file = VcfFile::parse("tests/data/string_info.vcf", false).expect("failed to open VCF file");
+        assert!(vcf_file.header.info_definition("NOTE").unwrap().is_single_string());
+        assert!(!vcf_file.header.info_definition("TAGS").unwrap().is_single_string());
+
+        let record = vcf_file.records().unwrap().next().unwrap().unwrap();
+        let value = |value: &str| -> Vec<String> { value.split(',').map(String::from).collect() };
+        assert_eq!(
+            record.info,
+            vec![
+                Some(InfoEntry::NonStandard(
+                    "NOTE".into(),
+                    NonStandardInfoValue::SingleValue("likely benign, see ClinVar".into())
+                )),
+                Some(InfoEntry::NonStandard("TAGS".into(), NonStandardInfoValue::ValueList(value("a,b")))),
+                Some(InfoEntry::NonStandard("OTHER".into(), NonStandardInfoValue::ValueList(value("x,y")))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_ancestral_allele() {
+        assert_eq!(InfoEntry::parse("AA=A", 1, &[]), Some(InfoEntry::AncestralAllele(Some("A".into()))));
+        assert_eq!(InfoEntry::parse("AA=a", 1, &[]), Some(InfoEntry::AncestralAllele(Some("A".into()))));
+        assert_eq!(InfoEntry::parse("AA=.", 1, &[]), Some(InfoEntry::AncestralAllele(None)));
+        assert_eq!(InfoEntry::parse("AA=-", 1, &[]), Some(InfoEntry::AncestralAllele(Some("-".into()))));
+        assert_eq!(InfoEntry::parse("AA", 1, &[]), None);
+    }
+
+    #[test]
+    fn test_parse_flags() {
+        let flags = "DB;H2;1000G;SOMATIC;VALIDATED;H3"
+            .split(';')
+            .map(|entry| InfoEntry::parse(entry, 1, &[]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            flags,
+            vec![
+                Some(InfoEntry::SNPDatabaseMembership),
+                Some(InfoEntry::HapMap2),
+                Some(InfoEntry::Flag1000G),
+                Some(InfoEntry::Somatic),
+                Some(InfoEntry::Validated),
+                Some(InfoEntry::HapMap3),
+            ]
+        );
+        assert_eq!(InfoEntry::parse("DB=1", 1, &[]), None);
     }
 }
 
@@ -0,0 +1,207 @@
+use std::io;
+
+use crate::{Genotype, VcfFile, VcfRecord};
+
+/// Dense matrix of alternate allele dosages with one row per record and one column per selected sample, stored in
+/// row-major order. The dosage of a genotype is the number of its alleles that aren't the reference allele, e.g. 0
+/// for `0/0`, 1 for `0|1` and 2 for `1/1`, or [`GenotypeMatrix::MISSING`] if any allele is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenotypeMatrix {
+    columns: usize,
+    sites: Vec<(String, u32)>,
+    dosages: Vec<i8>,
+}
+
+/// Alternate allele dosages of all samples of a biallelic record, packed into 2 bits per sample. This takes a
+/// quarter byte per sample, compared to the whole sample column kept by [`crate::SampleInfo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedDosages {
+    len: usize,
+    bits: Vec<u8>,
+}
+
+impl PackedDosages {
+    // code of missing dosages, after the dosages 0, 1 and 2
+    const MISSING: u8 = 3;
+
+    fn push(&mut self, dosage: Option<u8>) {
+        let code = dosage.filter(|&dosage| dosage < Self::MISSING).unwrap_or(Self::MISSING);
+        if self.len.is_multiple_of(4) {
+            self.bits.push(0);
+        }
+        *self.bits.last_mut().unwrap() |= code << (self.len % 4 * 2);
+        self.len += 1;
+    }
+
+    /// Number of samples
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the dosage of the sample at `index`, or None if its genotype is missing.
+    ///
+    /// # Panics
+    /// If `index` is not smaller than [`PackedDosages::len`].
+    pub fn get(&self, index: usize) -> Option<u8> {
+        assert!(index < self.len, "sample index {} out of range for {} samples", index, self.len);
+        let code = self.bits[index / 4] >> (index % 4 * 2) & 0b11;
+        (code != Self::MISSING).then_some(code)
+    }
+
+    /// Iterate over the dosages of all samples in order.
+    pub fn iter(&self) -> impl Iterator<Item=Option<u8>> + '_ {
+        (0..self.len).map(|index| self.get(index))
+    }
+}
+
+impl VcfRecord {
+    /// Pack the alternate allele dosages of all samples into [`PackedDosages`]. Genotypes with a missing allele,
+    /// and polyploid genotypes with more than two alternate alleles, are packed as missing. Returns None if the
+    /// record is not biallelic or has no samples.
+    pub fn packed_dosages(&self) -> Option<PackedDosages> {
+        if self.alternate_bases.len() != 1 {
+            return None;
+        }
+
+        let mut dosages = PackedDosages {
+            len: 0,
+            bits: Vec::new(),
+        };
+        for sample in self.sample_info.as_ref()?.samples() {
+            dosages.push(sample.genotype().and_then(|genotype| dosage(&genotype)).and_then(|d| d.try_into().ok()));
+        }
+        Some(dosages)
+    }
+}
+
+impl GenotypeMatrix {
+    /// Dosage of genotypes with a missing allele, and of samples without genotype information
+    pub const MISSING: i8 = -1;
+
+    /// Number of records in the matrix
+    pub fn rows(&self) -> usize {
+        self.sites.len()
+    }
+
+    /// Number of selected samples
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Chromosome and position of the record of a row.
+    pub fn site(&self, row: usize) -> (&str, u32) {
+        let (chromosome, position) = &self.sites[row];
+        (chromosome, *position)
+    }
+
+    /// Dosages of all selected samples of a row.
+    pub fn row(&self, row: usize) -> &[i8] {
+        &self.dosages[row * self.columns..(row + 1) * self.columns]
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> i8 {
+        self.row(row)[column]
+    }
+
+    /// All dosages in row-major order, e.g. to copy them into an array of a numerical library.
+    pub fn as_slice(&self) -> &[i8] {
+        &self.dosages
+    }
+}
+
+impl VcfFile {
+    /// Stream all records and extract the dosages of the given samples into a [`GenotypeMatrix`], with columns in
+    /// the order of `samples`. If `skip_multiallelic` is set, records with more than one alternate allele are left
+    /// out, otherwise all alternate alleles count towards the dosage. Fails with [`io::ErrorKind::InvalidInput`] if
+    /// a sample is not in the header.
+    pub fn genotype_matrix(&self, samples: &[String], skip_multiallelic: bool) -> io::Result<GenotypeMatrix> {
+        let indices = samples
+            .iter()
+            .map(|name| {
+                self.header.sample_index(name).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, format!("sample {} is not in the header", name))
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut matrix = GenotypeMatrix {
+            columns: indices.len(),
+            sites: Vec::new(),
+            dosages: Vec::new(),
+        };
+        for record in self.records()? {
+            let record = record?;
+            if skip_multiallelic && record.alt_count() > 1 {
+                continue;
+            }
+
+            let record_samples = record.sample_info.iter().flat_map(|info| info.samples()).collect::<Vec<_>>();
+            matrix.dosages.extend(indices.iter().map(|&i| {
+                let genotype = record_samples.get(i).and_then(|sample| sample.genotype());
+                genotype.and_then(|genotype| dosage(&genotype)).map_or(GenotypeMatrix::MISSING, |d| d as i8)
+            }));
+            matrix.sites.push((record.chromosome, record.position));
+        }
+
+        Ok(matrix)
+    }
+}
+
+/// Number of alleles of a genotype that aren't the reference allele, or None if any allele is missing.
+fn dosage(genotype: &Genotype) -> Option<usize> {
+    genotype
+        .alleles
+        .iter()
+        .try_fold(0, |dosage, allele| Some(dosage + ((*allele)? != 0) as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_genotype_matrix() {
+        let vcf_file = VcfFile::parse("tests/data/carriers.vcf", false).expect("failed to open VCF file");
+        let samples = vec!["S3".to_string(), "S1".to_string()];
+
+        let matrix = vcf_file.genotype_matrix(&samples, false).unwrap();
+        assert_eq!((matrix.rows(), matrix.columns()), (6, 2));
+        assert_eq!(matrix.as_slice(), &[0, 1, 0, 1, 1, 2, 1, -1, 0, 2, 0, -1]);
+        assert_eq!(matrix.site(2), ("1", 300));
+        assert_eq!(matrix.get(3, 1), GenotypeMatrix::MISSING);
+
+        let biallelic = vcf_file.genotype_matrix(&samples, true).unwrap();
+        assert_eq!(biallelic.rows(), 5);
+        assert_eq!(biallelic.site(2), ("1", 400));
+        assert_eq!(biallelic.row(2), &[1, -1]);
+
+        let unknown = vcf_file.genotype_matrix(&["S5".to_string()], false).unwrap_err();
+        assert_eq!(unknown.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_packed_dosages() {
+        let vcf_file = VcfFile::parse("tests/data/carriers.vcf", false).expect("failed to open VCF file");
+        let records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+
+        let dosages = records[0].packed_dosages().unwrap();
+        assert_eq!(dosages.len(), 4);
+        assert_eq!(dosages.iter().collect::<Vec<_>>(), vec![Some(1), Some(2), Some(0), Some(0)]);
+        assert_eq!(records[3].packed_dosages().unwrap().iter().collect::<Vec<_>>(), vec![None, None, Some(1), Some(0)]);
+        assert_eq!(records[5].packed_dosages().unwrap().get(0), None);
+        // the multiallelic site can't be packed
+        assert_eq!(records[2].packed_dosages(), None);
+
+        let line = format!("1\t100\t.\tA\tG\t.\tPASS\t.\tGT{}", "\t0/1\t1/1\t./.\t0|0\t1/1/1".repeat(3));
+        let header = crate::VcfHeaderBuilder::new().samples(&["S"; 15]).build();
+        let record = VcfRecord::parse(&line, &header, &crate::ParseOptions::default()).unwrap();
+        let dosages = record.packed_dosages().unwrap();
+        assert_eq!((dosages.len(), dosages.bits.len()), (15, 4));
+        assert_eq!(dosages.iter().take(5).collect::<Vec<_>>(), vec![Some(1), Some(2), None, Some(0), None]);
+        assert_eq!(dosages.get(13), Some(0));
+    }
+}
@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io;
+
+use memmap2::Mmap;
+
+use crate::{fast_split, ParseOptions, VcfFile, VcfHeader, VcfRecord};
+
+/// A memory-mapped view of an uncompressed VCF file. Lines are borrowed directly from the mapping, so iterating
+/// needs neither read syscalls nor copies into a line buffer.
+pub struct MappedVcf<'a> {
+    mmap: Mmap,
+    header: &'a VcfHeader,
+    options: &'a ParseOptions,
+}
+
+impl VcfFile {
+    /// Map the file into memory for zero-copy iteration. Only supported for uncompressed files read from a path.
+    pub fn mmap(&self) -> io::Result<MappedVcf<'_>> {
+        if self.compressed {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "compressed files cannot be memory-mapped"));
+        }
+
+        // SAFETY: the mapping is read-only. Modifying the file while it is mapped is undefined behaviour, which is
+        // the caller's responsibility, just like with any other memory-mapped file.
+        let path = self.path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "only files read from a path can be memory-mapped")
+        })?;
+        let mmap = unsafe { Mmap::map(&File::open(path)?)? };
+        Ok(MappedVcf {
+            mmap,
+            header: &self.header,
+            options: &self.options,
+        })
+    }
+}
+
+impl<'a> MappedVcf<'a> {
+    /// Iterate over all record lines without line terminators (`\n` or `\r\n`), borrowed from the mapping. The
+    /// file is validated to be UTF-8 once, instead of validating each line.
+    pub fn lines(&self) -> io::Result<impl Iterator<Item=&str> + '_> {
+        let body = std::str::from_utf8(&self.mmap[self.header.size..])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(fast_split(body, b'\n').map(|line| line.strip_suffix('\r').unwrap_or(line)))
+    }
+
+    /// Iterate over all records, parsed directly from the mapping. Errors are located like those of
+    /// [`VcfFile::records`].
+    pub fn records(&self) -> io::Result<impl Iterator<Item=io::Result<VcfRecord>> + '_> {
+        Ok(self.lines()?.zip(self.header.lines + 1..).map(|(line, number)| {
+            VcfRecord::parse(line, self.header, self.options)
+                .map_err(|e| e.at_line(number, line.as_bytes(), self.header).into())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::VcfFile;
+
+    #[test]
+    fn test_mmap_matches_buffered_reader() {
+        let vcf_file = VcfFile::parse("tests/data/allele_frequency.vcf", false).expect("failed to open VCF file");
+        let mapped = vcf_file.mmap().expect("failed to map VCF file");
+
+        let buffered = vcf_file.records().unwrap().map(|rec| rec.unwrap());
+        let mapped_records = mapped.records().unwrap().map(|rec| rec.unwrap());
+        let mut count = 0;
+        for (buffered, mapped) in buffered.zip(mapped_records) {
+            assert_eq!(format!("{:?}", buffered), format!("{:?}", mapped));
+            count += 1;
+        }
+        assert_eq!(count, 5);
+        assert_eq!(mapped.lines().unwrap().count(), 5);
+
+        let compressed = VcfFile::parse("tests/data/allele_frequency.vcf.gz", true).expect("failed to open VCF file");
+        assert!(compressed.mmap().is_err());
+    }
+}
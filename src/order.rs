@@ -0,0 +1,270 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+
+use crate::{LazyRecord, VcfFile, VcfHeader, VcfRecord, VcfWriter};
+
+/// An order of contigs used to sort records by genomic coordinate. Comparing chromosome names as strings would
+/// sort `chr10` before `chr2`, so the rank of each contig is given explicitly, usually by the `##contig` lines of
+/// the header. Contigs without a rank are sorted after all ranked contigs, by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContigOrder {
+    ranks: HashMap<String, usize>,
+}
+
+/// A record compared by its coordinate under a [`ContigOrder`], e.g. to merge sorted files with a `BinaryHeap`.
+#[derive(Debug)]
+pub struct OrderedRecord<'a> {
+    pub record: VcfRecord,
+    order: &'a ContigOrder,
+}
+
+/// The next record of a sorted run while merging runs, ordered by coordinate and then by the index of its run.
+struct RunHead<'a> {
+    record: LazyRecord<'a>,
+    run: usize,
+    order: &'a ContigOrder,
+}
+
+impl ContigOrder {
+    /// Rank contigs in the order of the given names.
+    pub fn new<I, S>(contigs: I) -> Self
+    where
+        I: IntoIterator<Item=S>,
+        S: Into<String>,
+    {
+        let mut ranks = HashMap::new();
+        for contig in contigs {
+            let rank = ranks.len();
+            ranks.entry(contig.into()).or_insert(rank);
+        }
+        ContigOrder { ranks }
+    }
+
+    /// Rank contigs in the order of their `##contig` declarations in the header.
+    pub fn from_header(header: &VcfHeader) -> Self {
+        Self::new(header.contigs.iter().map(|contig| contig.id.as_str()))
+    }
+
+    /// Rank of a contig, or None if it is not part of the order.
+    pub fn rank(&self, contig: &str) -> Option<usize> {
+        self.ranks.get(contig).copied()
+    }
+
+    /// Compare two records by chromosome and position, e.g. for `records.sort_by(|a, b| order.compare(a, b))`.
+    pub fn compare(&self, a: &VcfRecord, b: &VcfRecord) -> Ordering {
+        self.compare_coordinates((&a.chromosome, a.position), (&b.chromosome, b.position))
+    }
+
+    fn compare_coordinates(&self, (a, a_position): (&str, u32), (b, b_position): (&str, u32)) -> Ordering {
+        let chromosome = match (self.rank(a), self.rank(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.cmp(b),
+        };
+        chromosome.then(a_position.cmp(&b_position))
+    }
+
+    fn compare_lazy(&self, a: &LazyRecord, b: &LazyRecord) -> Ordering {
+        self.compare_coordinates((&a.chromosome, a.position), (&b.chromosome, b.position))
+    }
+
+    /// Wrap a record, so it is ordered by coordinate.
+    pub fn wrap(&self, record: VcfRecord) -> OrderedRecord<'_> {
+        OrderedRecord { record, order: self }
+    }
+}
+
+impl<'a> PartialEq for OrderedRecord<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a> Eq for OrderedRecord<'a> {}
+
+impl<'a> PartialOrd for OrderedRecord<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for OrderedRecord<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order.compare(&self.record, &other.record)
+    }
+}
+
+impl<'a> PartialEq for RunHead<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a> Eq for RunHead<'a> {}
+
+impl<'a> PartialOrd for RunHead<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for RunHead<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order.compare_lazy(&self.record, &other.record).then(self.run.cmp(&other.run))
+    }
+}
+
+impl VcfFile {
+    /// Write all records sorted by coordinate to an uncompressed VCF file at `path`, with contigs in the order of
+    /// the `##contig` lines of the header (see [`ContigOrder`]). Records with equal coordinates keep their order.
+    ///
+    /// At most `run_size` records are held in memory: sorted runs of that many records are spilled to temporary
+    /// files next to the output, which are merged into the output and removed afterwards. Only CHROM and POS of
+    /// the records are parsed, and their lines are copied byte for byte (see [`VcfWriter::write_raw`]). A
+    /// `run_size` of zero is rejected with an error of kind [`io::ErrorKind::InvalidInput`].
+    pub fn sort_to_file(&self, path: &str, run_size: usize) -> io::Result<()> {
+        if run_size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "run size must be positive"));
+        }
+        let order = ContigOrder::from_header(&self.header);
+        let mut runs = Vec::new();
+        let result = self.sort_runs(path, run_size, &order, &mut runs);
+        for run in &runs {
+            // a leftover run file is only a nuisance, the result of the sort is what matters
+            let _ = std::fs::remove_file(run);
+        }
+        result
+    }
+
+    fn sort_runs(&self, path: &str, run_size: usize, order: &ContigOrder, runs: &mut Vec<String>) -> io::Result<()> {
+        let mut records = self.lazy_records()?;
+        let mut run = Vec::with_capacity(run_size);
+        loop {
+            run.clear();
+            for record in records.by_ref().take(run_size) {
+                run.push(record?);
+            }
+            run.sort_by(|a, b| order.compare_lazy(a, b));
+
+            if runs.is_empty() && run.len() < run_size {
+                // all records fit into memory
+                return write_records(path, &self.header, &run);
+            }
+            if run.is_empty() {
+                break;
+            }
+            let run_path = format!("{}.run{}", path, runs.len());
+            runs.push(run_path.clone());
+            write_records(&run_path, &self.header, &run)?;
+        }
+
+        // the runs hold the raw lines, so contig names are normalized again when they are read
+        let files = runs
+            .iter()
+            .map(|run| VcfFile::parse_with_options(run, false, self.options.clone()))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut iterators = files.iter().map(VcfFile::lazy_records).collect::<io::Result<Vec<_>>>()?;
+
+        // ties are broken by the run index, which keeps the order of records with equal coordinates
+        let mut heap = BinaryHeap::new();
+        for (run, records) in iterators.iter_mut().enumerate() {
+            if let Some(record) = records.next() {
+                heap.push(Reverse(RunHead { record: record?, run, order }));
+            }
+        }
+
+        let mut writer = VcfWriter::new(BufWriter::new(File::create(path)?), &self.header)?;
+        while let Some(Reverse(head)) = heap.pop() {
+            writer.write_raw(&head.record)?;
+            if let Some(next) = iterators[head.run].next() {
+                heap.push(Reverse(RunHead { record: next?, ..head }));
+            }
+        }
+        writer.into_inner()?;
+        Ok(())
+    }
+}
+
+fn write_records(path: &str, header: &VcfHeader, records: &[LazyRecord]) -> io::Result<()> {
+    let mut writer = VcfWriter::new(BufWriter::new(File::create(path)?), header)?;
+    for record in records {
+        writer.write_raw(record)?;
+    }
+    writer.into_inner()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+
+    #[test]
+    fn test_sort_records() {
+        let vcf_file = VcfFile::parse("tests/data/unsorted_contigs.vcf", false).expect("failed to open VCF file");
+        let order = ContigOrder::from_header(&vcf_file.header);
+        let mut records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        records.reverse();
+
+        records.sort_by(|a, b| order.compare(a, b));
+        let coordinates = records.iter().map(|rec| (rec.chromosome.as_str(), rec.position)).collect::<Vec<_>>();
+        assert_eq!(coordinates, vec![("1", 100), ("2", 100), ("2", 200)]);
+
+        // chr10 is ranked after chr2 instead of sorting lexicographically, unranked contigs come last
+        let order = ContigOrder::new(["chr2", "chr10"]);
+        let mut heap = BinaryHeap::new();
+        for (chromosome, position) in [("chrUn", 5), ("chr10", 1), ("chr2", 300), ("chr2", 20)] {
+            let line = format!("{}\t{}\t.\tA\tG\t.\tPASS\t.", chromosome, position);
+            let record = VcfRecord::parse(&line, &vcf_file.header, &ParseOptions::default()).unwrap();
+            heap.push(Reverse(order.wrap(record)));
+        }
+        let merged = std::iter::from_fn(|| heap.pop())
+            .map(|Reverse(rec)| (rec.record.chromosome, rec.record.position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            merged,
+            vec![("chr2".into(), 20), ("chr2".into(), 300), ("chr10".into(), 1), ("chrUn".into(), 5)]
+        );
+    }
+
+    #[test]
+    fn test_sort_to_file() {
+        let vcf_file = VcfFile::parse("tests/data/shuffled.vcf", false).expect("failed to open VCF file");
+        assert!(!vcf_file.is_sorted().unwrap());
+        let order = ContigOrder::from_header(&vcf_file.header);
+        let mut expected = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        expected.sort_by(|a, b| order.compare(a, b));
+        let mut expected_lines = vcf_file.lazy_records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        expected_lines.sort_by(|a, b| order.compare_lazy(a, b));
+        let expected_lines = expected_lines.iter().map(|rec| rec.line().to_string()).collect::<Vec<_>>();
+        let coordinates = |records: &[VcfRecord]| {
+            records.iter().map(|rec| (rec.chromosome.clone(), rec.position)).collect::<Vec<_>>()
+        };
+
+        // runs of 5 records spill to multiple files, while runs of 100 records are sorted in memory
+        for run_size in [5, 24, 100] {
+            let path = std::env::temp_dir().join(format!("vcfire_sorted_{}.vcf", run_size));
+            let path = path.to_str().unwrap();
+            vcf_file.sort_to_file(path, run_size).expect("failed to sort file");
+
+            let sorted = VcfFile::parse(path, false).expect("failed to open sorted file");
+            assert!(sorted.is_sorted().unwrap());
+            let records = sorted.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+            assert_eq!(records.len(), 24);
+            assert_eq!(coordinates(&records), coordinates(&expected));
+            assert_eq!(records[0].info, expected[0].info);
+            assert!(!std::path::Path::new(&format!("{}.run0", path)).exists());
+
+            // the lines of the records are copied unchanged
+            let lines = sorted.lazy_records().unwrap().map(|rec| rec.unwrap().line().to_string()).collect::<Vec<_>>();
+            assert_eq!(lines, expected_lines);
+        }
+
+        let error = vcf_file.sort_to_file("vcfire_unused.vcf", 0).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,96 @@
+use std::io;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::{ParseOptions, RecordIterator, VcfFile, VcfHeader};
+
+/// A VCF file read from a seekable source instead of a path, e.g. an in-memory `Cursor`. Like [`VcfFile`], the
+/// header is parsed once and the records can be iterated multiple times, by seeking back to the first record.
+/// Sources that can't seek, like standard input, are streamed once by [`VcfFile::from_reader`] instead.
+pub struct VcfReader<R> {
+    reader: R,
+    compressed: bool,
+    pub header: VcfHeader,
+    options: ParseOptions,
+}
+
+impl<R: Read + Seek> VcfReader<R> {
+    /// Parse the header of the VCF file in the reader. The file must start at the beginning of the reader.
+    pub fn new(reader: R, compressed: bool) -> io::Result<VcfReader<R>> {
+        Self::with_options(reader, compressed, ParseOptions::default())
+    }
+
+    /// Parse the header like [`VcfReader::new`], and use the given options for parsing records.
+    pub fn with_options(mut reader: R, compressed: bool, options: ParseOptions) -> io::Result<VcfReader<R>> {
+        reader.rewind()?;
+        let mut header = if compressed {
+            VcfFile::parse_header(&mut BufReader::new(MultiGzDecoder::new(&mut reader)))?
+        } else {
+            VcfFile::parse_header(&mut BufReader::new(&mut reader))?
+        };
+        options.normalize_header(&mut header);
+
+        Ok(VcfReader {
+            reader,
+            compressed,
+            header,
+            options,
+        })
+    }
+
+    /// Seek back to the first record and get a sequential lazy iterator over all records.
+    pub fn records(&mut self) -> io::Result<RecordIterator<'_>> {
+        let reader: Box<dyn BufRead + '_> = if self.compressed {
+            // offsets refer to the decompressed data, so the header has to be decompressed again to skip it
+            self.reader.rewind()?;
+            let capacity = self.options.read_capacity();
+            let mut reader = BufReader::with_capacity(capacity, MultiGzDecoder::new(&mut self.reader));
+            io::copy(&mut (&mut reader).take(self.header.size as u64), &mut io::sink())?;
+            Box::new(reader)
+        } else {
+            self.reader.seek(SeekFrom::Start(self.header.size as u64))?;
+            Box::new(BufReader::with_capacity(self.options.read_capacity(), &mut self.reader))
+        };
+
+        Ok(RecordIterator::new(
+            reader,
+            &self.header,
+            &self.options,
+            self.header.size as u64,
+            self.header.lines,
+        ))
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_iterate_twice() {
+        for (path, compressed) in [
+            ("tests/data/allele_frequency.vcf", false),
+            ("tests/data/allele_frequency.vcf.gz", true),
+        ] {
+            let data = std::fs::read(path).expect("failed to read VCF file");
+            let mut reader = VcfReader::new(Cursor::new(data), compressed).expect("failed to parse header");
+
+            let first = reader.records().unwrap().map(|rec| rec.unwrap().position).collect::<Vec<_>>();
+            let second = reader.records().unwrap().map(|rec| rec.unwrap().position).collect::<Vec<_>>();
+            assert_eq!(first.len(), 5);
+            assert_eq!(first, second);
+
+            let vcf_file = VcfFile::parse(path, compressed).unwrap();
+            let from_path = vcf_file.records().unwrap().map(|rec| rec.unwrap().position).collect::<Vec<_>>();
+            assert_eq!(first, from_path);
+        }
+    }
+}
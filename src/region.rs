@@ -0,0 +1,55 @@
+/// Parse a region string as used on the command line by samtools and bcftools into the chromosome and the
+/// 1-based, inclusive range `start..=end`, e.g. for [`crate::VcfFile::query`]:
+///
+/// - `chr1` is the whole chromosome, `1..=u32::MAX`
+/// - `chr1:1000` and `chr1:1000-` start at 1000 and extend to the end of the chromosome
+/// - `chr1:1000-2000` is the range `1000..=2000`
+///
+/// Positions may contain thousands separators, as in `chr1:1,000-2,000`. If the part after the last `:` is not a
+/// range, it belongs to the chromosome name, so names containing colons like `HLA-A*01:01:01` can be queried with
+/// a range. Without a range, such a name is read as a shorter name and a start position if its last part is a
+/// number, so the whole chromosome has to be given as `HLA-A*01:01:01:1-`. Returns None for an empty chromosome, a
+/// start of 0, or a start after the end.
+pub fn parse_region(region: &str) -> Option<(String, u32, u32)> {
+    let range = region.rsplit_once(':').and_then(|(chromosome, range)| Some((chromosome, parse_range(range)?)));
+    let (chromosome, (start, end)) = range.unwrap_or((region, (1, u32::MAX)));
+
+    (!chromosome.is_empty() && start > 0 && start <= end).then(|| (chromosome.into(), start, end))
+}
+
+/// Parse `start`, `start-` or `start-end` with optional thousands separators.
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    let position = |text: &str| text.replace(',', "").parse::<u32>().ok();
+    match range.split_once('-') {
+        Some((start, "")) => Some((position(start)?, u32::MAX)),
+        Some((start, end)) => Some((position(start)?, position(end)?)),
+        None => Some((position(range)?, u32::MAX)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_region() {
+        assert_eq!(parse_region("chr1"), Some(("chr1".into(), 1, u32::MAX)));
+        assert_eq!(parse_region("chr1:1000"), Some(("chr1".into(), 1000, u32::MAX)));
+        assert_eq!(parse_region("chr1:1000-"), Some(("chr1".into(), 1000, u32::MAX)));
+        assert_eq!(parse_region("chr1:1000-2000"), Some(("chr1".into(), 1000, 2000)));
+        assert_eq!(parse_region("chr1:1,000-2,000,000"), Some(("chr1".into(), 1000, 2_000_000)));
+        assert_eq!(parse_region("chr1:5-5"), Some(("chr1".into(), 5, 5)));
+
+        // colons in contig names are kept if the suffix is not a range
+        assert_eq!(parse_region("HLA-A*01:01:01:20-30"), Some(("HLA-A*01:01:01".into(), 20, 30)));
+        assert_eq!(parse_region("chrUn:abc"), Some(("chrUn:abc".into(), 1, u32::MAX)));
+        // without a range, the last part of the name is taken as the start
+        assert_eq!(parse_region("HLA-A*01:01:01"), Some(("HLA-A*01:01".into(), 1, u32::MAX)));
+        assert_eq!(parse_region("HLA-A*01:01:01:1-"), Some(("HLA-A*01:01:01".into(), 1, u32::MAX)));
+
+        assert_eq!(parse_region(""), None);
+        assert_eq!(parse_region(":100-200"), None);
+        assert_eq!(parse_region("chr1:0-100"), None);
+        assert_eq!(parse_region("chr1:2000-1000"), None);
+    }
+}
@@ -0,0 +1,305 @@
+use std::io;
+use std::io::{BufRead, BufReader, Cursor, Read};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::bgzf::{BlockRange, MAX_BLOCK_SIZE};
+use crate::{Index, ParseOptions, RecordIterator, RegionIterator, VcfFile, VcfHeader, VcfRecord};
+
+/// A VCF file served over HTTP(S). If the file is BGZF compressed and a `.tbi` or `.csi` index is served next to
+/// it, region queries only download the blocks overlapping the region, using range requests. Otherwise, the whole
+/// file is downloaded once when it is opened.
+pub struct RemoteVcf {
+    url: String,
+    compressed: bool,
+    pub header: VcfHeader,
+    source: RemoteSource,
+    options: ParseOptions,
+}
+
+enum RemoteSource {
+    Indexed(Index),
+    Downloaded(Vec<u8>),
+}
+
+impl RemoteVcf {
+    /// Open a remote VCF file and parse its header.
+    pub fn open(url: &str, compressed: bool) -> io::Result<RemoteVcf> {
+        Self::open_with_options(url, compressed, ParseOptions::default())
+    }
+
+    /// Open a remote VCF file like [`RemoteVcf::open`], and use the given options for parsing records.
+    pub fn open_with_options(url: &str, compressed: bool, options: ParseOptions) -> io::Result<RemoteVcf> {
+        let index = if compressed {
+            match fetch_index(&format!("{}.tbi", url))? {
+                Some(index) => Some(index),
+                None => fetch_index(&format!("{}.csi", url))?,
+            }
+        } else {
+            None
+        };
+
+        let (mut header, source) = match index {
+            Some(index) => {
+                // only the header is read from the response, the rest of the file is never downloaded
+                let response = get(url, None)?.ok_or_else(|| not_found(url))?;
+                let header = VcfFile::parse_header(&mut BufReader::new(MultiGzDecoder::new(response.into_reader())))?;
+                (header, RemoteSource::Indexed(index))
+            }
+            None => {
+                let mut data = Vec::new();
+                get(url, None)?.ok_or_else(|| not_found(url))?.into_reader().read_to_end(&mut data)?;
+                let header = if compressed {
+                    VcfFile::parse_header(&mut BufReader::new(MultiGzDecoder::new(data.as_slice())))?
+                } else {
+                    VcfFile::parse_header(&mut data.as_slice())?
+                };
+                (header, RemoteSource::Downloaded(data))
+            }
+        };
+        options.normalize_header(&mut header);
+
+        Ok(RemoteVcf {
+            url: url.into(),
+            compressed,
+            header,
+            source,
+            options,
+        })
+    }
+
+    /// Whether region queries use an index, instead of scanning the downloaded file.
+    pub fn is_indexed(&self) -> bool {
+        matches!(self.source, RemoteSource::Indexed(_))
+    }
+
+    /// Get a sequential lazy iterator over all records. For indexed files, this streams the whole file.
+    pub fn records(&self) -> io::Result<RecordIterator<'_>> {
+        let capacity = self.options.read_capacity();
+        let mut reader: Box<dyn BufRead + '_> = match &self.source {
+            RemoteSource::Indexed(_) => {
+                let response = get(&self.url, None)?.ok_or_else(|| not_found(&self.url))?;
+                Box::new(BufReader::with_capacity(capacity, MultiGzDecoder::new(response.into_reader())))
+            }
+            RemoteSource::Downloaded(data) if self.compressed => {
+                Box::new(BufReader::with_capacity(capacity, MultiGzDecoder::new(data.as_slice())))
+            }
+            RemoteSource::Downloaded(data) => Box::new(data.as_slice()),
+        };
+        io::copy(&mut (&mut reader).take(self.header.size as u64), &mut io::sink())?;
+
+        Ok(RecordIterator::new(
+            reader,
+            &self.header,
+            &self.options,
+            self.header.size as u64,
+            self.header.lines,
+        ))
+    }
+
+    /// Get a lazy iterator over all records overlapping the 1-based, inclusive region `start..=end` on the given
+    /// chromosome. For indexed files, the candidate blocks are downloaded in a single range request.
+    pub fn query(
+        &self,
+        chromosome: &str,
+        start: u32,
+        end: u32,
+    ) -> io::Result<Box<dyn Iterator<Item=io::Result<VcfRecord>> + '_>> {
+        let index = match &self.source {
+            RemoteSource::Indexed(index) => index,
+            RemoteSource::Downloaded(_) => {
+                let chromosome = chromosome.to_string();
+                return Ok(Box::new(self.records()?.filter(move |record| match record {
+                    Ok(record) => {
+                        self.options.contig_matches(&record.chromosome, &chromosome)
+                            && record.position <= end
+                            && record.end_position() >= start
+                    }
+                    Err(_) => true,
+                })));
+            }
+        };
+
+        let chunks = index.chunks(self.options.indexed_chromosome(index, chromosome), start, end);
+        let records = match (chunks.first(), chunks.iter().map(|chunk| chunk.end).max()) {
+            (Some(first), Some(last)) => {
+                // the last chunk ends somewhere in its block, which is at most MAX_BLOCK_SIZE bytes long
+                let range_start = first.begin.compressed_offset();
+                let range_end = last.compressed_offset() + MAX_BLOCK_SIZE as u64 - 1;
+                let response = get(&self.url, Some((range_start, range_end)))?.ok_or_else(|| not_found(&self.url))?;
+
+                // servers without support for range requests send the whole file
+                let base = if response.status() == 206 { range_start } else { 0 };
+                let mut data = Vec::new();
+                response.into_reader().read_to_end(&mut data)?;
+
+                let blocks = BlockRange::decompress(&data, base)?;
+                let from = blocks.position(first.begin).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "server sent less data than requested")
+                })?;
+                let to = blocks.position(last);
+                let mut data = blocks.into_data();
+                data.truncate(to.unwrap_or(data.len()));
+                data.drain(..from);
+
+                let reader = Box::new(Cursor::new(data));
+                // offsets within the downloaded range are meaningless, so no checkpoints are possible
+                Some(RecordIterator::new(reader, &self.header, &self.options, 0, 0))
+            }
+            _ => None,
+        };
+
+        Ok(Box::new(RegionIterator {
+            records,
+            chromosome: chromosome.into(),
+            start,
+            end,
+        }))
+    }
+}
+
+/// Download an index file, or return None if the server doesn't have it.
+fn fetch_index(url: &str) -> io::Result<Option<Index>> {
+    match get(url, None)? {
+        Some(response) => Index::from_reader(response.into_reader()).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Send a GET request for the whole resource or the inclusive byte range. Returns None if the server responds
+/// with 404.
+fn get(url: &str, range: Option<(u64, u64)>) -> io::Result<Option<ureq::Response>> {
+    let mut request = ureq::get(url);
+    if let Some((start, end)) = range {
+        request = request.set("Range", &format!("bytes={}-{}", start, end));
+    }
+
+    match request.call() {
+        Ok(response) => Ok(Some(response)),
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(e) => Err(io::Error::other(e)),
+    }
+}
+
+fn not_found(url: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} not found", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Serve the files over HTTP on a local port, supporting range requests. Returns the base URL and the number
+    /// of bytes sent in response to range requests.
+    fn serve(files: Vec<(&'static str, Vec<u8>)>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let files = Arc::new(files);
+        let ranged_bytes = Arc::new(AtomicUsize::new(0));
+
+        let counter = Arc::clone(&ranged_bytes);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let (files, counter) = (Arc::clone(&files), Arc::clone(&counter));
+                std::thread::spawn(move || {
+                    // the client may hang up early, e.g. after reading the header
+                    let _ = respond(stream.unwrap(), &files, &counter);
+                });
+            }
+        });
+
+        (url, ranged_bytes)
+    }
+
+    fn respond(mut stream: TcpStream, files: &[(&str, Vec<u8>)], ranged_bytes: &AtomicUsize) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        let path = request.split(' ').nth(1).unwrap_or("").to_string();
+
+        let mut range = None;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            if line.trim_end().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("range") {
+                    let (start, end) = value.trim().trim_start_matches("bytes=").split_once('-').unwrap();
+                    range = Some((start.parse::<usize>().unwrap(), end.parse::<usize>().unwrap()));
+                }
+            }
+        }
+
+        let Some((_, data)) = files.iter().find(|(name, _)| *name == path) else {
+            return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+        };
+        match range {
+            Some((start, end)) => {
+                let end = end.min(data.len() - 1);
+                ranged_bytes.fetch_add(end + 1 - start, Ordering::SeqCst);
+                write!(
+                    stream,
+                    "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\n\
+                     Connection: close\r\n\r\n",
+                    end + 1 - start,
+                    start,
+                    end,
+                    data.len()
+                )?;
+                stream.write_all(&data[start..=end])
+            }
+            None => {
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", data.len())?;
+                stream.write_all(data)
+            }
+        }
+    }
+
+    #[test]
+    fn test_remote_query() {
+        let path = std::env::temp_dir().join("vcfire_remote.vcf.gz");
+        let csi_path = std::env::temp_dir().join("vcfire_remote_csi.vcf.gz");
+        let (path, csi_path) = (path.to_str().unwrap(), csi_path.to_str().unwrap());
+        crate::tests::write_indexed_file(path, csi_path).expect("failed to write indexed file");
+
+        let compressed = std::fs::read(path).unwrap();
+        let compressed_size = compressed.len();
+        let (url, ranged_bytes) = serve(vec![
+            ("/indexed.vcf.gz", compressed.clone()),
+            ("/indexed.vcf.gz.tbi", std::fs::read(format!("{}.tbi", path)).unwrap()),
+            ("/unindexed.vcf.gz", compressed),
+            ("/plain.vcf", std::fs::read("tests/data/allele_frequency.vcf").unwrap()),
+        ]);
+
+        let mut local = VcfFile::parse(path, true).unwrap();
+        local.load_index().unwrap();
+        let indexed = RemoteVcf::open(&format!("{}/indexed.vcf.gz", url), true).expect("failed to open remote file");
+        let unindexed = RemoteVcf::open(&format!("{}/unindexed.vcf.gz", url), true).unwrap();
+        assert!(indexed.is_indexed());
+        assert!(!unindexed.is_indexed());
+
+        // a region near the end of the file only downloads the blocks from the start of the region onwards
+        assert_eq!(indexed.query("2", 480_000, 520_000).unwrap().count(), 802);
+        assert!(ranged_bytes.load(Ordering::SeqCst) < compressed_size / 2);
+
+        for (chromosome, start, end) in [("2", 480_000, 520_000), ("1", 1, 1), ("1", 999_990, 2_000_000), ("3", 1, 10)] {
+            let positions = |records: Box<dyn Iterator<Item=io::Result<VcfRecord>> + '_>| {
+                records.map(|rec| rec.unwrap().position).collect::<Vec<_>>()
+            };
+            let expected = positions(Box::new(local.query(chromosome, start, end).unwrap()));
+            assert_eq!(positions(indexed.query(chromosome, start, end).unwrap()), expected);
+            assert_eq!(positions(unindexed.query(chromosome, start, end).unwrap()), expected);
+        }
+
+        let plain = RemoteVcf::open(&format!("{}/plain.vcf", url), false).unwrap();
+        assert_eq!(plain.records().unwrap().count(), 5);
+        assert!(RemoteVcf::open(&format!("{}/missing.vcf", url), false).is_err());
+    }
+}
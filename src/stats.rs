@@ -0,0 +1,384 @@
+use crate::VcfRecord;
+
+/// Streaming summary statistics of the QUAL column. Records with missing quality are counted, but otherwise
+/// skipped. Qualities are additionally collected into a histogram of fixed-width buckets, which is used to
+/// approximate the median and can be plotted to choose a quality cutoff. The histogram has a bounded number of
+/// buckets, and qualities beyond its last bucket are only counted as [`QualSummary::overflow`].
+#[derive(Debug, Clone)]
+pub struct QualSummary {
+    count: u64,
+    missing: u64,
+    sum: f64,
+    min: f32,
+    max: f32,
+    bucket_width: f32,
+    max_buckets: usize,
+    buckets: Vec<u64>,
+    overflow: u64,
+}
+
+impl Default for QualSummary {
+    fn default() -> Self {
+        Self::new(10.0)
+    }
+}
+
+impl QualSummary {
+    /// Default number of histogram buckets, which covers qualities up to 10000 with the default bucket width.
+    pub const DEFAULT_MAX_BUCKETS: usize = 1000;
+
+    /// Create an empty summary whose histogram buckets span `bucket_width` quality units each, with
+    /// [`QualSummary::DEFAULT_MAX_BUCKETS`] buckets.
+    pub fn new(bucket_width: f32) -> Self {
+        assert!(bucket_width > 0.0, "bucket width must be positive");
+        QualSummary {
+            count: 0,
+            missing: 0,
+            sum: 0.0,
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            bucket_width,
+            max_buckets: Self::DEFAULT_MAX_BUCKETS,
+            buckets: Vec::new(),
+            overflow: 0,
+        }
+    }
+
+    /// Limit the histogram to `max_buckets` buckets, covering qualities below `max_buckets * bucket_width`.
+    pub fn max_buckets(mut self, max_buckets: usize) -> Self {
+        self.max_buckets = max_buckets;
+        self
+    }
+
+    pub fn add(&mut self, record: &VcfRecord) {
+        match record.quality {
+            Some(quality) => self.add_quality(quality),
+            None => self.missing += 1,
+        }
+    }
+
+    fn add_quality(&mut self, quality: f32) {
+        self.count += 1;
+        self.sum += quality as f64;
+        self.min = self.min.min(quality);
+        self.max = self.max.max(quality);
+
+        // negative qualities are invalid, so they are clamped into the first bucket
+        let bucket = (quality.max(0.0) / self.bucket_width) as usize;
+        if bucket >= self.max_buckets {
+            self.overflow += 1;
+            return;
+        }
+        if bucket >= self.buckets.len() {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+    }
+
+    /// Number of records with a quality value
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Number of records with missing quality
+    pub fn missing(&self) -> u64 {
+        self.missing
+    }
+
+    pub fn min(&self) -> Option<f32> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<f32> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    /// Approximate the median as the center of the histogram bucket containing it, clamped to the observed range.
+    /// If the median lies beyond the last bucket, the maximum quality is returned.
+    pub fn median(&self) -> Option<f32> {
+        let mut remaining = self.count.checked_sub(1)? / 2;
+        for (bucket, &count) in self.buckets.iter().enumerate() {
+            if remaining < count {
+                let center = (bucket as f32 + 0.5) * self.bucket_width;
+                return Some(center.clamp(self.min, self.max));
+            }
+            remaining -= count;
+        }
+        Some(self.max)
+    }
+
+    pub fn bucket_width(&self) -> f32 {
+        self.bucket_width
+    }
+
+    /// Histogram counts, where bucket `i` counts qualities in `[i * bucket_width, (i + 1) * bucket_width)`.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Number of qualities beyond the last histogram bucket, see [`QualSummary::max_buckets`].
+    pub fn overflow(&self) -> u64 {
+        self.overflow
+    }
+}
+
+impl<'a> Extend<&'a VcfRecord> for QualSummary {
+    fn extend<T: IntoIterator<Item=&'a VcfRecord>>(&mut self, records: T) {
+        records.into_iter().for_each(|record| self.add(record));
+    }
+}
+
+/// Streaming union of the intervals covered by records, e.g. the callable regions of a gVCF from its reference
+/// blocks and variant records. Each record covers `position..=end_position()`, and overlapping or adjacent
+/// intervals are merged. Records don't need to be sorted, but sorted input is merged in constant time.
+#[derive(Debug, Clone, Default)]
+pub struct CoverageTracker {
+    // contigs in the order they were first seen, each with disjoint, sorted intervals
+    contigs: Vec<(String, Vec<(u32, u32)>)>,
+}
+
+impl CoverageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, record: &VcfRecord) {
+        self.add_interval(&record.chromosome, record.position, record.end_position());
+    }
+
+    /// Add the 1-based, inclusive interval `start..=end` on the given contig.
+    pub fn add_interval(&mut self, chromosome: &str, start: u32, end: u32) {
+        let intervals = match self.contigs.iter().rposition(|(name, _)| name == chromosome) {
+            Some(contig) => &mut self.contigs[contig].1,
+            None => {
+                self.contigs.push((chromosome.into(), Vec::new()));
+                &mut self.contigs.last_mut().unwrap().1
+            }
+        };
+
+        // find all intervals that overlap or touch the new one and replace them with their union
+        let first = intervals.partition_point(|&(_, e)| e.saturating_add(1) < start);
+        let (mut start, mut end) = (start, end.max(start));
+        let mut last = first;
+        while last < intervals.len() && intervals[last].0 <= end.saturating_add(1) {
+            start = start.min(intervals[last].0);
+            end = end.max(intervals[last].1);
+            last += 1;
+        }
+        intervals.splice(first..last, [(start, end)]);
+    }
+
+    /// Names of all contigs with covered intervals, in the order they were first seen
+    pub fn contigs(&self) -> impl Iterator<Item=&str> {
+        self.contigs.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Sorted, disjoint, 1-based and inclusive intervals covered on the given contig
+    pub fn intervals(&self, chromosome: &str) -> &[(u32, u32)] {
+        self.contigs
+            .iter()
+            .find(|(name, _)| name == chromosome)
+            .map_or(&[], |(_, intervals)| intervals.as_slice())
+    }
+
+    /// Number of bases covered on the given contig
+    pub fn covered_bases(&self, chromosome: &str) -> u64 {
+        self.intervals(chromosome)
+            .iter()
+            .map(|&(start, end)| (end - start) as u64 + 1)
+            .sum()
+    }
+}
+
+impl<'a> Extend<&'a VcfRecord> for CoverageTracker {
+    fn extend<T: IntoIterator<Item=&'a VcfRecord>>(&mut self, records: T) {
+        records.into_iter().for_each(|record| self.add(record));
+    }
+}
+
+/// Streaming site frequency spectrum of biallelic sites, counting for each number of alternate alleles called
+/// across all samples how many sites carry it. The unfolded spectrum counts the derived allele, which is polarized
+/// with the ancestral allele (`AA`), while the folded spectrum counts the minor allele and needs no ancestral state.
+///
+/// All sites should be called in the same samples with the same ploidy, since the bins are only comparable for a
+/// fixed number of chromosomes. Multiallelic sites, sites with a missing allele, and for the unfolded spectrum
+/// sites whose ancestral allele is neither REF nor ALT, are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct AlleleFrequencySpectrum {
+    folded: bool,
+    bins: Vec<u64>,
+    skipped: u64,
+}
+
+impl AlleleFrequencySpectrum {
+    /// Create an empty unfolded spectrum of derived allele counts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty folded spectrum of minor allele counts.
+    pub fn folded() -> Self {
+        AlleleFrequencySpectrum {
+            folded: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn add(&mut self, record: &VcfRecord) {
+        match self.bin(record) {
+            Some((bin, chromosomes)) => {
+                let len = if self.folded { chromosomes / 2 } else { chromosomes } + 1;
+                if self.bins.len() < len {
+                    self.bins.resize(len, 0);
+                }
+                self.bins[bin] += 1;
+            }
+            None => self.skipped += 1,
+        }
+    }
+
+    /// The bin of a site and the number of called chromosomes, or None if the site is skipped.
+    fn bin(&self, record: &VcfRecord) -> Option<(usize, usize)> {
+        if record.alternate_bases.len() != 1 {
+            return None;
+        }
+
+        let (mut chromosomes, mut alternate) = (0, 0);
+        for sample in record.sample_info.as_ref()?.samples() {
+            for allele in sample.genotype()?.alleles {
+                chromosomes += 1;
+                alternate += (allele? != 0) as usize;
+            }
+        }
+
+        if self.folded {
+            return Some((alternate.min(chromosomes - alternate), chromosomes));
+        }
+        let ancestral = record.ancestral_allele()?;
+        let is_ancestral = |allele: &str| allele.eq_ignore_ascii_case(ancestral);
+        if is_ancestral(&record.reference_bases) {
+            Some((alternate, chromosomes))
+        } else if record.alternate_bases[0].as_deref().is_some_and(is_ancestral) {
+            Some((chromosomes - alternate, chromosomes))
+        } else {
+            None
+        }
+    }
+
+    pub fn is_folded(&self) -> bool {
+        self.folded
+    }
+
+    /// Number of sites per bin, where bin `i` counts sites with `i` derived (or minor, if folded) alleles.
+    pub fn bins(&self) -> &[u64] {
+        &self.bins
+    }
+
+    /// Number of sites that couldn't be binned
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+impl<'a> Extend<&'a VcfRecord> for AlleleFrequencySpectrum {
+    fn extend<T: IntoIterator<Item=&'a VcfRecord>>(&mut self, records: T) {
+        records.into_iter().for_each(|record| self.add(record));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VcfFile;
+
+    #[test]
+    fn test_qual_summary() {
+        let records = VcfFile::parse("tests/data/quality.vcf", false)
+            .expect("failed to open VCF file")
+            .records()
+            .expect("failed to open VCF file")
+            .map(|rec| rec.expect("failed to parse VCF record"))
+            .collect::<Vec<_>>();
+
+        let mut summary = QualSummary::new(10.0);
+        summary.extend(&records);
+
+        assert_eq!(summary.count(), 4);
+        assert_eq!(summary.missing(), 2);
+        assert_eq!(summary.min(), Some(12.5));
+        assert_eq!(summary.max(), Some(99.0));
+        assert_eq!(summary.mean(), Some(48.0));
+        assert_eq!(summary.median(), Some(35.0));
+        assert_eq!(summary.buckets(), &[0, 1, 0, 1, 0, 1, 0, 0, 0, 1]);
+
+        let empty = QualSummary::default();
+        assert_eq!((empty.min(), empty.mean(), empty.median()), (None, None, None));
+    }
+
+    #[test]
+    fn test_qual_summary_overflow() {
+        let vcf_file = VcfFile::parse("tests/data/huge_quality.vcf", false).expect("failed to open VCF file");
+        let records = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+
+        let mut summary = QualSummary::default();
+        summary.extend(&records);
+        assert_eq!((summary.count(), summary.overflow()), (3, 2));
+        assert_eq!(summary.buckets(), &[0, 0, 1]);
+        assert_eq!(summary.max(), Some(3e38));
+        assert_eq!(summary.median(), Some(3e38));
+
+        let mut narrow = QualSummary::new(10.0).max_buckets(2);
+        narrow.extend(&records);
+        assert_eq!((narrow.buckets(), narrow.overflow()), (&[][..], 3));
+    }
+
+    #[test]
+    fn test_coverage_tracker() {
+        let records = VcfFile::parse("tests/data/gvcf_blocks.vcf", false)
+            .expect("failed to open VCF file")
+            .records()
+            .expect("failed to open VCF file")
+            .map(|rec| rec.expect("failed to parse VCF record"))
+            .collect::<Vec<_>>();
+
+        let mut coverage = CoverageTracker::new();
+        coverage.extend(&records);
+
+        // adjacent blocks and the variant inside them are merged, overlapping blocks are united
+        assert_eq!(coverage.contigs().collect::<Vec<_>>(), vec!["1", "2"]);
+        assert_eq!(coverage.intervals("1"), &[(1, 200), (300, 450)]);
+        assert_eq!(coverage.intervals("2"), &[(10, 20)]);
+        assert_eq!(coverage.covered_bases("1"), 351);
+        assert!(coverage.intervals("3").is_empty());
+
+        // an unsorted interval bridging the gap merges everything
+        coverage.add_interval("1", 190, 310);
+        assert_eq!(coverage.intervals("1"), &[(1, 450)]);
+    }
+
+    #[test]
+    fn test_allele_frequency_spectrum() {
+        let records = VcfFile::parse("tests/data/spectrum.vcf", false)
+            .expect("failed to open VCF file")
+            .records()
+            .expect("failed to open VCF file")
+            .map(|rec| rec.expect("failed to parse VCF record"))
+            .collect::<Vec<_>>();
+
+        let mut unfolded = AlleleFrequencySpectrum::new();
+        unfolded.extend(&records);
+        assert!(!unfolded.is_folded());
+        // a derived reference allele at 200 counts the reference alleles
+        assert_eq!(unfolded.bins(), &[0, 2, 0, 1, 0, 1, 0]);
+        assert_eq!(unfolded.skipped(), 4);
+
+        let mut folded = AlleleFrequencySpectrum::folded();
+        folded.extend(&records);
+        assert_eq!(folded.bins(), &[0, 3, 2, 1]);
+        assert_eq!(folded.skipped(), 2);
+    }
+}
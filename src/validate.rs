@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+use std::io;
+
+use crate::header::parse_structured_meta;
+use crate::{InfoEntry, ParseOptions, RecordIds, VcfFile, VcfHeader, VcfParseError, VcfRecord, VcfRecordRef};
+
+/// A problem found by [`VcfFile::validate`], at a 1-based line number of the (decompressed) file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub line: u64,
+    pub kind: IssueKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The record can't be parsed, or violates the header in a way that strict mode reports
+    Invalid(VcfParseError),
+    /// An INFO key without a `##INFO` declaration in the header
+    UndeclaredInfo(String),
+    /// A filter without a `##FILTER` declaration in the header. `PASS` needs no declaration
+    UndeclaredFilter(String),
+    /// An INFO entry with a malformed value, or with a number of values other than declared for its key
+    InfoCardinality(String),
+    /// The `END` INFO entry and the END column of the record disagree
+    ConflictingEnd,
+    /// The record lies before the previous record of its contig, or its contig is out of order (see
+    /// [`VcfFile::is_sorted`])
+    Unsorted,
+}
+
+/// Tracks the contigs and positions of consecutive records to detect records out of order.
+#[derive(Default)]
+pub(crate) struct SortCheck {
+    seen_contigs: HashSet<String>,
+    // chromosome, rank in the header and position of the previous record
+    previous: Option<(String, Option<usize>, u32)>,
+}
+
+impl SortCheck {
+    /// Check whether a record is in order after the previous records. A record out of order is still remembered,
+    /// so that the next record is compared against it.
+    pub(crate) fn check(&mut self, header: &VcfHeader, chromosome: &str, position: u32) -> bool {
+        if let Some((previous_chromosome, _, previous_position)) = &mut self.previous {
+            if previous_chromosome == chromosome {
+                let in_order = position >= *previous_position;
+                *previous_position = position;
+                return in_order;
+            }
+        }
+
+        let first_block = self.seen_contigs.insert(chromosome.to_string());
+        let rank = header.contig_index(chromosome);
+        let ranked = match (&self.previous, rank) {
+            (Some((_, Some(previous_rank), _)), Some(rank)) => rank >= *previous_rank,
+            _ => true,
+        };
+        self.previous = Some((chromosome.to_string(), rank, position));
+        first_block && ranked
+    }
+}
+
+impl VcfFile {
+    /// Scan the whole file and collect all problems, instead of stopping at the first like strict mode. Besides
+    /// the violations reported in strict mode, this reports undeclared INFO and FILTER keys, INFO entries with a
+    /// wrong number of values, conflicting END values and records out of order.
+    pub fn validate(&self) -> io::Result<Vec<ValidationIssue>> {
+        let options = ParseOptions {
+            strict: false,
+            ..self.options.clone()
+        };
+        let declared_filters = declared_filters(&self.header);
+        let mut order = SortCheck::default();
+        let mut issues = Vec::new();
+
+        let mut records = self.records()?;
+        while records.read_line()? {
+            let line = records.line;
+            let mut report = |kind| issues.push(ValidationIssue { line, kind });
+
+            let record = match VcfRecord::parse(&records.buffer, &self.header, &options) {
+                Ok(record) => record,
+                Err(e) => {
+                    report(IssueKind::Invalid(e));
+                    continue;
+                }
+            };
+            // the line parsed as a record, so it has all columns and a valid POS
+            let raw = VcfRecordRef::parse(&records.buffer, &self.header, &options).expect("record was parsed");
+
+            if !order.check(&self.header, &record.chromosome, record.position) {
+                report(IssueKind::Unsorted);
+            }
+            if record.conflicting_end() {
+                report(IssueKind::ConflictingEnd);
+            }
+            let checks = [
+                record.validate_mandatory_fields(),
+                RecordIds::validate(raw.id),
+                record.validate_contig_length(&self.header),
+                VcfRecord::parse_quality(options.missing(records.buffer.split('\t').nth(5).unwrap_or(""))).map(|_| ()),
+                record.validate_end(),
+                record.validate_depth(),
+                record.validate_genotypes(),
+            ];
+            for error in checks.into_iter().filter_map(Result::err) {
+                report(IssueKind::Invalid(error));
+            }
+
+            for filter in raw.filter_status.split(';').filter(|f| !matches!(*f, "." | "PASS")) {
+                if !declared_filters.contains(filter) {
+                    report(IssueKind::UndeclaredFilter(filter.into()));
+                }
+            }
+
+            let alt_count = record.alternate_bases.len();
+            // Number=G fields depend on the ploidy, which is taken from the first sample
+            let ploidy = record
+                .sample_info
+                .as_ref()
+                .and_then(|sample_info| sample_info.samples().next()?.ploidy())
+                .unwrap_or(2);
+            for entry in raw.info_entries() {
+                let (key, value) = match entry.split_once('=') {
+                    Some((key, value)) => (key, Some(value)),
+                    None => (entry, None),
+                };
+                let definition = self.header.info_definition(key);
+                if definition.is_none() {
+                    report(IssueKind::UndeclaredInfo(key.into()));
+                }
+
+                let malformed = InfoEntry::parse(entry, alt_count, &self.header.info_definitions).is_none();
+                let miscounted = definition.is_some_and(|definition| {
+                    let count = match value {
+                        None => 0,
+                        Some(_) if definition.is_single_string() => 1,
+                        Some(value) => value.split(',').count(),
+                    };
+                    let expected = definition.number.expected_count(alt_count, ploidy);
+                    expected.is_some_and(|expected| expected != count)
+                });
+                if malformed || miscounted {
+                    report(IssueKind::InfoCardinality(key.into()));
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+}
+
+/// Collect the IDs of all `##FILTER` meta lines of the header.
+fn declared_filters(header: &VcfHeader) -> HashSet<String> {
+    header
+        .values
+        .iter()
+        .filter(|(key, _)| key == "FILTER")
+        .filter_map(|(_, value)| parse_structured_meta(value)?.into_iter().find(|(key, _)| key == "ID"))
+        .map(|(_, id)| id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let vcf_file = VcfFile::parse("tests/data/messy.vcf", false).expect("failed to open VCF file");
+        let issues = vcf_file.validate().unwrap();
+        let issue = |line, kind| ValidationIssue { line, kind };
+
+        assert_eq!(
+            issues,
+            vec![
+                issue(10, IssueKind::InfoCardinality("AF".into())),
+                issue(11, IssueKind::Unsorted),
+                issue(11, IssueKind::UndeclaredFilter("lowqual".into())),
+                issue(11, IssueKind::UndeclaredInfo("XX".into())),
+                issue(
+                    12,
+                    IssueKind::Invalid(VcfParseError::PositionOutOfContig {
+                        chromosome: "1".into(),
+                        position: 1200,
+                        length: 1000
+                    })
+                ),
+                issue(12, IssueKind::Invalid(VcfParseError::MalformedQuality("abc".into()))),
+                issue(
+                    12,
+                    IssueKind::Invalid(VcfParseError::AlleleIndexOutOfRange {
+                        sample: 0,
+                        allele: 3,
+                        allele_count: 2
+                    })
+                ),
+                issue(13, IssueKind::InfoCardinality("DP".into())),
+                issue(15, IssueKind::Unsorted),
+                issue(
+                    16,
+                    IssueKind::Invalid(VcfParseError::MissingColumns {
+                        expected: 10,
+                        found: 8,
+                        space_delimited: false
+                    })
+                ),
+            ]
+        );
+
+        let clean = VcfFile::parse("tests/data/allele_frequency.vcf", false).unwrap();
+        assert_eq!(clean.validate().unwrap(), vec![]);
+
+        // a configured missing token is not a malformed quality
+        let default = VcfFile::parse("tests/data/missing_quality.vcf", false).unwrap();
+        let malformed = IssueKind::Invalid(VcfParseError::MalformedQuality("NA".into()));
+        assert_eq!(default.validate().unwrap(), vec![issue(3, malformed)]);
+        let options = ParseOptions::new().missing_values(&["NA"]);
+        let missing = VcfFile::parse_with_options("tests/data/missing_quality.vcf", false, options).unwrap();
+        assert_eq!(missing.validate().unwrap(), vec![]);
+    }
+}
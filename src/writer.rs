@@ -0,0 +1,414 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::{BgzfWriter, InfoEntry, LazyRecord, NonStandardInfoValue, VcfFile, VcfHeader, VcfRecord};
+
+/// A writer serializing a header and records as VCF text. Wrap the output in a [`crate::BgzfWriter`] to write a
+/// compressed file. Records can be modified between reading and writing, e.g. to annotate them with additional
+/// INFO entries, which should be declared in the header as well.
+///
+/// INFO entries that failed to parse (`None` entries of [`VcfRecord::info`]) are not written, since their raw
+/// value is not kept. A filtering pass that reads [`LazyRecord`]s can write the records it keeps unchanged with
+/// [`VcfWriter::write_raw`] instead, and only reserialize the records it modifies.
+pub struct VcfWriter<W: Write> {
+    inner: W,
+    has_end_column: bool,
+    has_samples: bool,
+}
+
+impl<W: Write> VcfWriter<W> {
+    /// Write the meta lines and the column header line of the header, and create a writer for records matching
+    /// its columns.
+    pub fn new(mut inner: W, header: &VcfHeader) -> io::Result<VcfWriter<W>> {
+        write!(inner, "{}", header)?;
+
+        Ok(VcfWriter {
+            inner,
+            has_end_column: header.has_end_column,
+            has_samples: header.sample_names.is_some(),
+        })
+    }
+
+    /// Write a record as a single line. Fails with [`io::ErrorKind::InvalidInput`] if the record has sample data
+    /// but the header declares no samples, or vice versa.
+    pub fn write_record(&mut self, record: &VcfRecord) -> io::Result<()> {
+        if record.sample_info.is_some() != self.has_samples {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "sample data of the record doesn't match the columns of the header",
+            ));
+        }
+
+        let inner = &mut self.inner;
+        write!(inner, "{}\t{}\t", record.chromosome, record.position)?;
+        match &record.id {
+            Some(ids) => write_joined(inner, ids.iter(), ';')?,
+            None => write!(inner, ".")?,
+        }
+        write!(inner, "\t{}\t", record.reference_bases)?;
+        write_joined(inner, record.alternate_bases.iter().map(|alt| alt.as_deref().unwrap_or(".")), ',')?;
+        match record.quality {
+            Some(quality) => write!(inner, "\t{}", quality)?,
+            None => write!(inner, "\t.")?,
+        }
+        write!(inner, "\t{}\t", record.filter_status)?;
+
+        let mut entries = record.info.iter().flatten().peekable();
+        if entries.peek().is_some() {
+            write_joined(inner, entries, ';')?;
+        } else {
+            write!(inner, ".")?;
+        }
+
+        if self.has_end_column {
+            match record.end {
+                Some(end) => write!(inner, "\t{}", end)?,
+                None => write!(inner, "\t.")?,
+            }
+        }
+        if let Some(sample_info) = &record.sample_info {
+            write!(inner, "\t")?;
+            write_joined(inner, sample_info.format.iter(), ':')?;
+            if !sample_info.unparsed_info.is_empty() {
+                write!(inner, "\t{}", sample_info.unparsed_info)?;
+            }
+        }
+        writeln!(inner)
+    }
+
+    /// Write the original line of a lazy record, preserving the formatting of the input byte for byte. Only a `\r\n`
+    /// line terminator is written as `\n`.
+    pub fn write_raw(&mut self, record: &LazyRecord) -> io::Result<()> {
+        writeln!(self.inner, "{}", record.line())
+    }
+
+    /// Flush the writer and return the underlying output.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// The output file of a single chromosome written by [`VcfFile::split_output_by_chromosome`].
+enum Shard {
+    Plain(VcfWriter<BufWriter<File>>),
+    Compressed(VcfWriter<BgzfWriter<BufWriter<File>>>),
+}
+
+impl Shard {
+    fn create(path: &Path, header: &VcfHeader, compressed: bool) -> io::Result<Shard> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(if compressed {
+            Shard::Compressed(VcfWriter::new(BgzfWriter::new(file), header)?)
+        } else {
+            Shard::Plain(VcfWriter::new(file, header)?)
+        })
+    }
+
+    fn write_raw(&mut self, record: &LazyRecord) -> io::Result<()> {
+        match self {
+            Shard::Plain(writer) => writer.write_raw(record),
+            Shard::Compressed(writer) => writer.write_raw(record),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Shard::Plain(writer) => writer.into_inner()?.flush(),
+            Shard::Compressed(writer) => writer.into_inner()?.finish()?.flush(),
+        }
+    }
+}
+
+impl VcfFile {
+    /// Write the records of each chromosome to their own file `<dir>/<chromosome>.vcf`, or `.vcf.gz` compressed
+    /// with BGZF, in a single pass over the file. Every shard starts with the header of this file, followed by the
+    /// lines of its records copied byte for byte (see [`VcfWriter::write_raw`]). The directory is created if it
+    /// doesn't exist. Returns the paths of the shards in the order of the chromosomes in the file.
+    ///
+    /// The records of each chromosome must be contiguous, as they are in sorted files. Otherwise, this fails with
+    /// [`io::ErrorKind::InvalidInput`] when a chromosome appears again, leaving the shards written so far. A
+    /// chromosome containing a path separator or `..` fails the same way, so no shard is written outside `dir`.
+    pub fn split_output_by_chromosome(&self, dir: &str, compressed: bool) -> io::Result<Vec<String>> {
+        std::fs::create_dir_all(dir)?;
+        let extension = if compressed { "vcf.gz" } else { "vcf" };
+
+        let mut paths = Vec::new();
+        let mut seen = HashSet::new();
+        let mut shard: Option<(String, Shard)> = None;
+        for record in self.lazy_records()? {
+            let record = record?;
+            if shard.as_ref().is_none_or(|(chromosome, _)| *chromosome != record.chromosome) {
+                if let Some((_, finished)) = shard.take() {
+                    finished.finish()?;
+                }
+                if !seen.insert(record.chromosome.clone()) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("records of chromosome {} are not contiguous", record.chromosome),
+                    ));
+                }
+                if record.chromosome.contains(['/', '\\']) || record.chromosome.contains("..") {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("chromosome {} is not a valid file name", record.chromosome),
+                    ));
+                }
+
+                let path = Path::new(dir).join(format!("{}.{}", record.chromosome, extension));
+                paths.push(path.to_string_lossy().into_owned());
+                shard = Some((record.chromosome.clone(), Shard::create(&path, &self.header, compressed)?));
+            }
+            shard.as_mut().unwrap().1.write_raw(&record)?;
+        }
+
+        if let Some((_, finished)) = shard {
+            finished.finish()?;
+        }
+        Ok(paths)
+    }
+}
+
+fn write_joined<W: Write, T: fmt::Display>(
+    writer: &mut W,
+    values: impl IntoIterator<Item=T>,
+    separator: char,
+) -> io::Result<()> {
+    for (i, value) in values.into_iter().enumerate() {
+        if i > 0 {
+            write!(writer, "{}", separator)?;
+        }
+        write!(writer, "{}", value)?;
+    }
+    Ok(())
+}
+
+/// A list of values separated by commas.
+struct Values<'a, T>(&'a [T]);
+
+impl<T: fmt::Display> fmt::Display for Values<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, value) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes the header as the meta lines followed by the column header line, each terminated by a newline, so
+/// the text is a valid VCF file without records.
+impl fmt::Display for VcfHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "##fileformat={}", self.file_format)?;
+        for (key, value) in &self.values {
+            writeln!(f, "##{}={}", key, value)?;
+        }
+
+        write!(f, "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO")?;
+        if self.has_end_column {
+            write!(f, "\tEND")?;
+        }
+        if let Some(samples) = &self.sample_names {
+            write!(f, "\tFORMAT")?;
+            for sample in samples {
+                write!(f, "\t{}", sample)?;
+            }
+        }
+        writeln!(f)
+    }
+}
+
+/// Serializes the entry as it appears in the INFO column, i.e. `KEY=VALUE` or just the key of flags.
+impl fmt::Display for InfoEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InfoEntry::AncestralAllele(allele) => write!(f, "AA={}", allele.as_deref().unwrap_or(".")),
+            InfoEntry::AlleleCount(counts) => write!(f, "AC={}", Values(counts)),
+            InfoEntry::TotalAlleleReadDepth(depths) => write!(f, "AD={}", Values(depths)),
+            InfoEntry::ForwardAlleleReadDepth(depths) => write!(f, "ADF={}", Values(depths)),
+            InfoEntry::ReverseAlleleReadDepth(depths) => write!(f, "ADR={}", Values(depths)),
+            InfoEntry::AlleleFrequency(frequencies) => write!(f, "AF={}", Values(frequencies)),
+            InfoEntry::AlleleNumber(number) => write!(f, "AN={}", number),
+            InfoEntry::RmsBaseQuality(quality) => write!(f, "BQ={}", quality),
+            InfoEntry::Cigar(cigars) => write!(f, "CIGAR={}", Values(cigars)),
+            InfoEntry::SNPDatabaseMembership => write!(f, "DB"),
+            InfoEntry::CombinedDepth(depth) => write!(f, "DP={}", depth),
+            InfoEntry::End(end) => write!(f, "END={}", end),
+            InfoEntry::HapMap2 => write!(f, "H2"),
+            InfoEntry::HapMap3 => write!(f, "H3"),
+            InfoEntry::RmsMappingQuality(quality) => write!(f, "MQ={}", quality),
+            InfoEntry::MapQReads(reads) => write!(f, "MQ0={}", reads),
+            InfoEntry::SamplesWithData(samples) => write!(f, "NS={}", samples),
+            InfoEntry::StrandBias(a, b, c, d) => write!(f, "SB={},{},{},{}", a, b, c, d),
+            InfoEntry::Somatic => write!(f, "SOMATIC"),
+            InfoEntry::Validated => write!(f, "VALIDATED"),
+            InfoEntry::Flag1000G => write!(f, "1000G"),
+            InfoEntry::StructuralVariantType(sv_type) => write!(f, "SVTYPE={}", sv_type),
+            InfoEntry::StructuralVariantLength(lengths) => write!(f, "SVLEN={}", Values(lengths)),
+            InfoEntry::PositionConfidenceInterval(low, high) => write!(f, "CIPOS={},{}", low, high),
+            InfoEntry::EndConfidenceInterval(low, high) => write!(f, "CIEND={},{}", low, high),
+            InfoEntry::NonStandard(key, NonStandardInfoValue::NoValue) => write!(f, "{}", key),
+            InfoEntry::NonStandard(key, NonStandardInfoValue::SingleValue(value)) => write!(f, "{}={}", key, value),
+            InfoEntry::NonStandard(key, NonStandardInfoValue::ValueList(values)) => {
+                write!(f, "{}={}", key, Values(values))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VcfFile;
+
+    #[test]
+    fn test_display_header() {
+        for path in ["tests/data/trio.vcf", "tests/data/end_column.vcf", "tests/data/string_info.vcf"] {
+            let header = VcfFile::parse(path, false).expect("failed to open VCF file").header;
+            let text = header.to_string();
+            let parsed = VcfFile::parse_header(&mut text.as_bytes()).expect("failed to parse header");
+
+            assert_eq!(parsed.file_format, header.file_format);
+            assert_eq!(parsed.values, header.values);
+            assert_eq!(parsed.has_end_column, header.has_end_column);
+            assert_eq!(parsed.sample_names, header.sample_names);
+            assert_eq!(parsed.contigs, header.contigs);
+            assert_eq!(parsed.info_definitions, header.info_definitions);
+            assert_eq!(parsed.pedigrees, header.pedigrees);
+            assert_eq!(parsed.size, text.len());
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for path in [
+            "tests/data/allele_frequency.vcf",
+            "tests/data/sample_fields.vcf",
+            "tests/data/gvcf_blocks.vcf",
+            "tests/data/string_info.vcf",
+            "tests/data/end_column.vcf",
+        ] {
+            let vcf_file = VcfFile::parse(path, false).expect("failed to open VCF file");
+            let mut writer = VcfWriter::new(Vec::new(), &vcf_file.header).unwrap();
+            for record in vcf_file.records().unwrap() {
+                writer.write_record(&record.unwrap()).unwrap();
+            }
+            let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+            assert_eq!(written, std::fs::read_to_string(path).unwrap(), "{} differs", path);
+        }
+    }
+
+    #[test]
+    fn test_write_raw() {
+        let path = "tests/data/quirks.vcf";
+        let vcf_file = VcfFile::parse(path, false).expect("failed to open VCF file");
+        let input = std::fs::read_to_string(path).unwrap();
+        let body = &input[vcf_file.header.size..];
+
+        let mut writer = VcfWriter::new(Vec::new(), &vcf_file.header).unwrap();
+        for record in vcf_file.lazy_records().unwrap() {
+            writer.write_raw(&record.unwrap()).unwrap();
+        }
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(&written[vcf_file.header.to_string().len()..], body);
+
+        // only the modified record is reserialized, which normalizes its numbers
+        let mut writer = VcfWriter::new(Vec::new(), &vcf_file.header).unwrap();
+        for record in vcf_file.lazy_records().unwrap() {
+            let record = record.unwrap();
+            match record.position {
+                200 => continue,
+                300 => {
+                    let mut modified = record.to_record().unwrap();
+                    modified.filter_status = "q10".into();
+                    writer.write_record(&modified).unwrap();
+                }
+                _ => writer.write_raw(&record).unwrap(),
+            }
+        }
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        let mut lines = body.lines().filter(|line| !line.contains("\t200\t")).collect::<Vec<_>>();
+        lines[1] = "1\t300\t.\tG\tA\t30\tq10\tDP=1,2;AF=0.5\tGT\t0/1";
+        assert_eq!(written[vcf_file.header.to_string().len()..].lines().collect::<Vec<_>>(), lines);
+    }
+
+    #[test]
+    fn test_split_output_by_chromosome() {
+        let vcf_file = VcfFile::parse("tests/data/unsorted_contigs.vcf", false).expect("failed to open VCF file");
+        for compressed in [false, true] {
+            let dir = std::env::temp_dir().join(format!("vcfire_shards_{}", compressed));
+            let dir = dir.to_str().unwrap();
+            let paths = vcf_file.split_output_by_chromosome(dir, compressed).expect("failed to split file");
+            let extension = if compressed { "vcf.gz" } else { "vcf" };
+            assert_eq!(paths, vec![format!("{}/2.{}", dir, extension), format!("{}/1.{}", dir, extension)]);
+
+            let shards = paths
+                .iter()
+                .map(|path| {
+                    let shard = VcfFile::parse(path, compressed).expect("failed to open shard");
+                    assert_eq!(shard.header.values, vcf_file.header.values);
+                    let records = shard.records().unwrap().map(|rec| rec.unwrap());
+                    records.map(|rec| (rec.chromosome, rec.position)).collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(shards, vec![vec![("2".into(), 100), ("2".into(), 200)], vec![("1".into(), 100)]]);
+
+            // the lines of the records are copied unchanged
+            let lines = |file: VcfFile| {
+                file.lazy_records().unwrap().map(|rec| rec.unwrap().line().to_string()).collect::<Vec<_>>()
+            };
+            let shard_lines = paths.iter().flat_map(|path| lines(VcfFile::parse(path, compressed).unwrap()));
+            let input_lines = lines(VcfFile::parse("tests/data/unsorted_contigs.vcf", false).unwrap());
+            assert_eq!(shard_lines.collect::<Vec<_>>(), input_lines);
+        }
+
+        // contig 1 appears again after contig 2
+        let messy = VcfFile::parse("tests/data/messy.vcf", false).unwrap();
+        let dir = std::env::temp_dir().join("vcfire_shards_messy");
+        let error = messy.split_output_by_chromosome(dir.to_str().unwrap(), false).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        // a chromosome named like a relative path must not escape the directory
+        let unsafe_names = VcfFile::parse("tests/data/unsafe_contig.vcf", false).unwrap();
+        let dir = std::env::temp_dir().join("vcfire_shards_unsafe");
+        let error = unsafe_names.split_output_by_chromosome(dir.to_str().unwrap(), false).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(!std::env::temp_dir().join("escaped.vcf").exists());
+    }
+
+    #[test]
+    fn test_annotate_info() {
+        let mut vcf_file = VcfFile::parse("tests/data/allele_frequency.vcf", false).expect("failed to open VCF file");
+        vcf_file.header.values.push((
+            "INFO".into(),
+            "<ID=ANNOTATED,Number=0,Type=Flag,Description=\"Seen by the annotator\">".into(),
+        ));
+
+        let path = std::env::temp_dir().join("vcfire_annotated.vcf");
+        let mut writer = VcfWriter::new(std::fs::File::create(&path).unwrap(), &vcf_file.header).unwrap();
+        let original = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        for mut record in vcf_file.records().unwrap().map(|rec| rec.unwrap()) {
+            record.info.push(Some(InfoEntry::NonStandard("ANNOTATED".into(), NonStandardInfoValue::NoValue)));
+            writer.write_record(&record).unwrap();
+        }
+        writer.into_inner().unwrap();
+
+        let annotated = VcfFile::parse(path.to_str().unwrap(), false).expect("failed to open written file");
+        assert!(annotated.header.info_definition("ANNOTATED").is_some());
+        let records = annotated.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+        assert_eq!(records.len(), original.len());
+        for (record, original) in records.iter().zip(&original) {
+            let (added, info) = record.info.split_last().unwrap();
+            assert_eq!(added, &Some(InfoEntry::NonStandard("ANNOTATED".into(), NonStandardInfoValue::NoValue)));
+            assert_eq!(info, original.info.as_slice());
+            assert_eq!((record.position, &record.alternate_bases), (original.position, &original.alternate_bases));
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Checks that lending iterators don't allocate per record. This replaces the global allocator, so it lives in its
+//! own test binary instead of affecting all unit tests.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use vcfire::VcfFile;
+
+// counts allocations per thread, so tests running in parallel don't interfere
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_lending_records_do_not_allocate() {
+    let vcf_file = VcfFile::parse("tests/data/sample_fields.vcf", false).expect("failed to open VCF file");
+    let owned = vcf_file.records().unwrap().map(|rec| rec.unwrap()).collect::<Vec<_>>();
+
+    let mut records = vcf_file.lending_records().unwrap();
+    let before = ALLOCATIONS.with(Cell::get);
+    let mut positions = [0; 2];
+    let mut count = 0;
+    while let Some(record) = records.next() {
+        let record = record.unwrap();
+        positions[count] = record.position;
+        assert_eq!(record.chromosome, owned[count].chromosome);
+        assert_eq!(record.info_entries().count(), 0);
+        count += 1;
+    }
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+    assert_eq!(positions, [100, 200]);
+}